@@ -0,0 +1,115 @@
+//! Reading, filtering, sorting, counting facets of and writing a generated CSV
+//! file of `JONATHAN_BENCH_ROWS` rows (two million by default), the sizes the
+//! viewer has to stay responsive at. The file is made once and kept in the
+//! temporary directory.
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use jonathan_core::data::{facet_counts, filter_data, sort_data, write_csv};
+use jonathan_core::read_csv::Dialect;
+use jonathan_core::readers::{FileFormat, read_rows};
+use jonathan_core::{Facets, FileHeader, SortOrder};
+use std::hint::black_box;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+const CITIES: [&str; 8] = [
+    "Oslo", "Bergen", "Rome", "Milan", "Lyon", "Paris", "Porto", "Lisbon",
+];
+
+fn rows() -> usize {
+    std::env::var("JONATHAN_BENCH_ROWS")
+        .ok()
+        .and_then(|rows| rows.parse().ok())
+        .unwrap_or(2_000_000)
+}
+
+/// `id,name,city,amount,note` with names and amounts from a fixed pseudo-random
+/// sequence, so every run reads the same file.
+fn generated_file(rows: usize) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("jonathan-bench-{rows}.csv"));
+    if path.exists() {
+        return path;
+    }
+    let partial = path.with_extension("partial");
+    let mut out = BufWriter::new(std::fs::File::create(&partial).unwrap());
+    writeln!(out, "id,name,city,amount,note").unwrap();
+    let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = || {
+        seed = seed
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        seed >> 33
+    };
+    for id in 0..rows {
+        let name = format!("user{:06}", next() % 1_000_000);
+        let city = CITIES[next() as usize % CITIES.len()];
+        let amount = next() % 100_000;
+        let note = match next() % 4 {
+            0 => "\"late, again\"",
+            1 => "\"said \"\"hi\"\"\"",
+            _ => "",
+        };
+        writeln!(
+            out,
+            "{id},{name},{city},{}.{:02},{note}",
+            amount / 100,
+            amount % 100
+        )
+        .unwrap();
+    }
+    out.into_inner().unwrap().sync_all().unwrap();
+    std::fs::rename(&partial, &path).unwrap();
+    path
+}
+
+fn engine(c: &mut Criterion) {
+    let rows = rows();
+    let path = generated_file(rows);
+    let path = path.to_str().unwrap();
+    let dialect = Dialect::default();
+    let (headers, data, _) = read_rows(path, &FileFormat::Delimited, &dialect, u64::MAX).unwrap();
+    let headers = headers.unwrap_or_else(|| {
+        ["id", "name", "city", "amount", "note"]
+            .map(|name| FileHeader {
+                name: name.to_string(),
+                visible: true,
+                ..FileHeader::default()
+            })
+            .to_vec()
+    });
+
+    let mut group = c.benchmark_group(format!("{rows} rows"));
+    group.sample_size(10);
+    group.bench_function("read", |b| {
+        b.iter(|| read_rows(black_box(path), &FileFormat::Delimited, &dialect, u64::MAX).unwrap())
+    });
+    group.bench_function("filter", |b| {
+        b.iter(|| {
+            filter_data(
+                data.clone(),
+                black_box("user12".to_string()),
+                &Facets::new(),
+            )
+        })
+    });
+    group.bench_function("facet filter", |b| {
+        let facets = Facets::from([(2, ["Oslo".to_string(), "Rome".to_string()].into())]);
+        b.iter(|| filter_data(data.clone(), String::new(), black_box(&facets)))
+    });
+    group.bench_function("sort", |b| {
+        b.iter(|| sort_data(data.clone(), black_box((1, SortOrder::Asc))))
+    });
+    group.bench_function("facet counts", |b| {
+        b.iter(|| facet_counts(black_box(&data), "", &Facets::new()))
+    });
+    let out = std::env::temp_dir().join(format!("jonathan-bench-{rows}-out.csv"));
+    let out = out.to_str().unwrap();
+    group.bench_function("write", |b| {
+        b.iter(|| write_csv(black_box(out), &headers, &data, &dialect).unwrap())
+    });
+    group.finish();
+    let _ = std::fs::remove_file(out);
+}
+
+criterion_group!(benches, engine);
+criterion_main!(benches);
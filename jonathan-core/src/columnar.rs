@@ -0,0 +1,309 @@
+use arrow::array::{
+    ArrayRef, BooleanArray, Float64Array, Int64Array, RecordBatch, StringArray,
+    TimestampMillisecondArray,
+};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use arrow::ipc::reader::{FileReader, read_footer_length};
+use arrow::util::display::{ArrayFormatter, FormatOptions};
+use csv::StringRecord;
+use parquet::arrow::ArrowWriter;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::Arc;
+
+use crate::data::write_atomic;
+use crate::profile::{ColumnType, infer_type, parse_bool, parse_date};
+use crate::readers::FileFormat;
+use crate::stats::parse_number;
+use crate::types::{FileHeader, SheetVec};
+
+/// Rows per row group when saving as Parquet.
+const PARQUET_ROW_GROUP: usize = 64 * 1024;
+
+/// Columns of a Parquet or Arrow IPC file and how its rows are split into row
+/// groups (record batches for Arrow), read from the footer alone.
+pub struct ColumnarLayout {
+    pub headers: Vec<FileHeader>,
+    pub group_rows: Vec<usize>,
+}
+
+/// Read `path` without reading its rows.
+pub fn layout(path: &str, format: &FileFormat) -> csv::Result<ColumnarLayout> {
+    let (schema, group_rows) = match format {
+        FileFormat::Parquet => {
+            let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)
+                .map_err(io::Error::other)?;
+            let group_rows = builder
+                .metadata()
+                .row_groups()
+                .iter()
+                .map(|group| group.num_rows() as usize)
+                .collect();
+            (builder.schema().clone(), group_rows)
+        }
+        FileFormat::ArrowIpc => {
+            let reader = FileReader::try_new(File::open(path)?, None).map_err(io::Error::other)?;
+            (reader.schema(), ipc_batch_rows(path)?)
+        }
+        _ => return Err(io::Error::other("not a columnar file").into()),
+    };
+
+    let headers = schema
+        .fields()
+        .iter()
+        .map(|field| FileHeader {
+            name: field.name().clone(),
+            visible: true,
+            sort: None,
+            kind: Some(column_type(field.data_type())),
+        })
+        .collect();
+    Ok(ColumnarLayout {
+        headers,
+        group_rows,
+    })
+}
+
+/// Rows of each record batch, from the batch headers the footer points at, so
+/// that no batch has to be decoded to know where its rows go.
+fn ipc_batch_rows(path: &str) -> io::Result<Vec<usize>> {
+    let mut file = File::open(path)?;
+    let end = file.seek(SeekFrom::End(0))?;
+    let mut trailer = [0; 10];
+    file.seek(SeekFrom::Start(end.saturating_sub(10)))?;
+    file.read_exact(&mut trailer)?;
+    let footer_len = read_footer_length(trailer).map_err(io::Error::other)?;
+    let mut footer = vec![0; footer_len];
+    file.seek(SeekFrom::Start(end.saturating_sub(10 + footer_len as u64)))?;
+    file.read_exact(&mut footer)?;
+    let footer =
+        arrow::ipc::root_as_footer(&footer).map_err(|e| io::Error::other(e.to_string()))?;
+
+    let mut rows = Vec::new();
+    for block in footer.recordBatches().into_iter().flatten() {
+        let mut meta = vec![0; block.metaDataLength() as usize];
+        file.seek(SeekFrom::Start(block.offset() as u64))?;
+        file.read_exact(&mut meta)?;
+        // A continuation marker precedes the length prefix, except in files
+        // written before the marker was introduced.
+        let skip = if meta.starts_with(&[0xff; 4]) { 8 } else { 4 };
+        let message = arrow::ipc::root_as_message(meta.get(skip..).unwrap_or_default())
+            .map_err(|e| io::Error::other(e.to_string()))?;
+        let batch = message
+            .header_as_record_batch()
+            .ok_or_else(|| io::Error::other("record batch without header"))?;
+        rows.push(batch.length() as usize);
+    }
+    Ok(rows)
+}
+
+fn column_type(data_type: &DataType) -> ColumnType {
+    match data_type {
+        DataType::Null => ColumnType::Empty,
+        DataType::Boolean => ColumnType::Boolean,
+        DataType::Int8
+        | DataType::Int16
+        | DataType::Int32
+        | DataType::Int64
+        | DataType::UInt8
+        | DataType::UInt16
+        | DataType::UInt32
+        | DataType::UInt64 => ColumnType::Integer,
+        DataType::Float16
+        | DataType::Float32
+        | DataType::Float64
+        | DataType::Decimal128(..)
+        | DataType::Decimal256(..) => ColumnType::Float,
+        DataType::Date32 | DataType::Date64 | DataType::Timestamp(..) => ColumnType::Date,
+        DataType::Dictionary(_, value) => column_type(value),
+        _ => ColumnType::Text,
+    }
+}
+
+/// Read the rows of one row group (record batch for Arrow IPC).
+pub fn read_group(path: &str, format: &FileFormat, group: usize) -> csv::Result<SheetVec> {
+    let batches = match format {
+        FileFormat::Parquet => ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)
+            .and_then(|builder| builder.with_row_groups(vec![group]).build())
+            .map_err(io::Error::other)?
+            .collect::<Result<Vec<_>, _>>(),
+        FileFormat::ArrowIpc => {
+            let mut reader =
+                FileReader::try_new(File::open(path)?, None).map_err(io::Error::other)?;
+            reader.set_index(group).map_err(io::Error::other)?;
+            reader.next().into_iter().collect::<Result<Vec<_>, _>>()
+        }
+        _ => return Err(io::Error::other("not a columnar file").into()),
+    }
+    .map_err(io::Error::other)?;
+
+    let mut rows = Vec::new();
+    for batch in &batches {
+        rows.extend(batch_rows(batch)?);
+    }
+    Ok(rows)
+}
+
+/// Cells as Arrow displays them; nulls become empty cells.
+fn batch_rows(batch: &RecordBatch) -> io::Result<SheetVec> {
+    let options = FormatOptions::default();
+    let formatters = batch
+        .columns()
+        .iter()
+        .map(|column| ArrayFormatter::try_new(column.as_ref(), &options))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(io::Error::other)?;
+    Ok((0..batch.num_rows())
+        .map(|row| {
+            formatters
+                .iter()
+                .map(|f| f.value(row).to_string())
+                .collect::<StringRecord>()
+        })
+        .collect())
+}
+
+/// Write `data` to a Parquet file, with each column typed by what all its cells
+/// hold. Only empty cells are written as nulls.
+pub fn write_parquet(path: &str, headers: &[FileHeader], data: &SheetVec) -> csv::Result<()> {
+    let kinds = (0..headers.len())
+        .map(|col| parquet_type(data, col))
+        .collect::<Vec<_>>();
+    let schema: SchemaRef = Arc::new(Schema::new(
+        headers
+            .iter()
+            .zip(&kinds)
+            .enumerate()
+            .map(|(i, (header, kind))| {
+                let name = if header.name.is_empty() {
+                    format!("column_{}", i + 1)
+                } else {
+                    header.name.clone()
+                };
+                Field::new(name, arrow_type(*kind), true)
+            })
+            .collect::<Vec<_>>(),
+    ));
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .set_max_row_group_size(PARQUET_ROW_GROUP)
+        .build();
+
+    write_atomic(path, |file| {
+        let mut writer = ArrowWriter::try_new(file, schema.clone(), Some(properties))
+            .map_err(io::Error::other)?;
+        for chunk in data.chunks(PARQUET_ROW_GROUP) {
+            let arrays = kinds
+                .iter()
+                .enumerate()
+                .map(|(col, kind)| column_array(chunk, col, *kind))
+                .collect();
+            let batch = RecordBatch::try_new(schema.clone(), arrays).map_err(io::Error::other)?;
+            writer.write(&batch).map_err(io::Error::other)?;
+        }
+        writer.close().map_err(io::Error::other)?;
+        Ok::<(), io::Error>(())
+    })
+    .map_err(csv::Error::from)
+}
+
+/// Type of column `col` that every non-empty cell of `data` parses as, so that
+/// no value is lost; text when some don't.
+fn parquet_type(data: &SheetVec, col: usize) -> ColumnType {
+    let values = || data.iter().map(|r| r.get(col).unwrap_or_default());
+    let kind = infer_type(values());
+    let fits = |value: &str| match kind {
+        ColumnType::Integer => value.trim().parse::<i64>().is_ok(),
+        ColumnType::Float => parse_number(value).is_some(),
+        ColumnType::Boolean => parse_bool(value).is_some(),
+        ColumnType::Date => parse_date(value).is_some(),
+        ColumnType::Empty | ColumnType::Text => true,
+    };
+    if values().all(|value| value.is_empty() || fits(value)) {
+        kind
+    } else {
+        ColumnType::Text
+    }
+}
+
+fn arrow_type(kind: ColumnType) -> DataType {
+    match kind {
+        ColumnType::Integer => DataType::Int64,
+        ColumnType::Float => DataType::Float64,
+        ColumnType::Boolean => DataType::Boolean,
+        ColumnType::Date => DataType::Timestamp(TimeUnit::Millisecond, None),
+        ColumnType::Empty | ColumnType::Text => DataType::Utf8,
+    }
+}
+
+fn column_array(rows: &[StringRecord], col: usize, kind: ColumnType) -> ArrayRef {
+    let values = rows.iter().map(|r| r.get(col).unwrap_or_default());
+    let typed = values.clone().map(move |v| (!v.is_empty()).then_some(v));
+    match kind {
+        ColumnType::Integer => Arc::new(
+            typed
+                .map(|v| v.and_then(|v| v.trim().parse::<i64>().ok()))
+                .collect::<Int64Array>(),
+        ),
+        ColumnType::Float => Arc::new(
+            typed
+                .map(|v| v.and_then(parse_number))
+                .collect::<Float64Array>(),
+        ),
+        ColumnType::Boolean => Arc::new(
+            typed
+                .map(|v| v.and_then(parse_bool))
+                .collect::<BooleanArray>(),
+        ),
+        ColumnType::Date => Arc::new(
+            typed
+                .map(|v| {
+                    v.and_then(parse_date)
+                        .map(|d| d.and_utc().timestamp_millis())
+                })
+                .collect::<TimestampMillisecondArray>(),
+        ),
+        ColumnType::Empty | ColumnType::Text => Arc::new(values.map(Some).collect::<StringArray>()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(name: &str) -> FileHeader {
+        FileHeader {
+            name: name.to_string(),
+            visible: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parquet_keeps_cells_that_do_not_fit_the_column_type() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let path = file.path().to_str().unwrap();
+        let mut headers = vec![header("id"), header("count")];
+        // A profile of a filtered view may have seen only the integers.
+        headers[1].kind = Some(ColumnType::Integer);
+        let data = [
+            ["1", "10"],
+            ["2", "20"],
+            ["3", ""],
+            ["4", "lots"],
+            ["5", "NA"],
+        ]
+        .into_iter()
+        .map(StringRecord::from_iter)
+        .collect::<SheetVec>();
+
+        write_parquet(path, &headers, &data).unwrap();
+        let layout = layout(path, &FileFormat::Parquet).unwrap();
+        assert_eq!(layout.headers[0].kind, Some(ColumnType::Integer));
+        assert_eq!(layout.headers[1].kind, Some(ColumnType::Text));
+        assert_eq!(read_group(path, &FileFormat::Parquet, 0).unwrap(), data);
+    }
+}
@@ -0,0 +1,639 @@
+use csv::{QuoteStyle, StringRecord, Terminator};
+use encoding_rs::UTF_8;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::read_csv::Dialect;
+use crate::types::{ColumnId, FacetCounts, Facets, FileHeader, SheetVec, SortOrder};
+
+/// Update a single cell in a sheet. Returns `true` if the row and column existed.
+pub fn edit_record(sheet: &mut SheetVec, row: usize, col: usize, value: &str) -> bool {
+    if let Some(record) = sheet.get_mut(row)
+        && col < record.len()
+    {
+        *record = record
+            .iter()
+            .enumerate()
+            .map(|(i, f)| if i == col { value } else { f })
+            .collect();
+        return true;
+    }
+    false
+}
+
+/// Wrap a CSV field value in double-quotes if it contains a comma, double-quote, or newline.
+/// Internal double-quotes are escaped by doubling them.
+pub fn csv_quote(value: &str) -> String {
+    if value.contains(',') || value.contains('"') || value.contains('\n') || value.contains('\r') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub fn write_csv(
+    path: &str,
+    headers: &[FileHeader],
+    data: &SheetVec,
+    dialect: &Dialect,
+) -> Result<(), csv::Error> {
+    write_columns(path, headers, data, None, dialect)
+}
+
+/// Write `data` in `dialect`, keeping only `columns` (all columns when `None`).
+pub fn write_columns(
+    path: &str,
+    headers: &[FileHeader],
+    data: &SheetVec,
+    columns: Option<&[ColumnId]>,
+    dialect: &Dialect,
+) -> Result<(), csv::Error> {
+    write_atomic(path, |file| {
+        write_records(file, headers, data, columns, dialect)
+    })
+}
+
+/// Run `write` on a temporary file next to `path`, sync it and rename it over
+/// `path`, so a crash halfway leaves the old file intact.
+pub fn write_atomic<E: From<std::io::Error>>(
+    path: &str,
+    write: impl FnOnce(&mut File) -> Result<(), E>,
+) -> Result<(), E> {
+    let temp = temp_path(Path::new(path));
+    let result = File::create(&temp).map_err(E::from).and_then(|mut file| {
+        write(&mut file)?;
+        file.sync_all()?;
+        if let Ok(metadata) = fs::metadata(path) {
+            fs::set_permissions(&temp, metadata.permissions())?;
+        }
+        fs::rename(&temp, path)?;
+        Ok(())
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Copy `path` to `path.bak`, replacing an older backup. Nothing to do when
+/// `path` doesn't exist yet.
+pub fn backup_file(path: &str) -> std::io::Result<()> {
+    if !Path::new(path).is_file() {
+        return Ok(());
+    }
+    fs::copy(path, format!("{path}.bak")).map(|_| ())
+}
+
+/// `.name.tmp` in the same directory, so the final rename stays on one filesystem.
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{name}.tmp"))
+}
+
+/// Text in another encoding than UTF-8 is written as UTF-8 first, then encoded.
+pub fn write_records(
+    file: &mut impl Write,
+    headers: &[FileHeader],
+    data: &SheetVec,
+    columns: Option<&[ColumnId]>,
+    dialect: &Dialect,
+) -> Result<(), csv::Error> {
+    if dialect.encoding != UTF_8 {
+        let utf8 = Dialect {
+            encoding: UTF_8,
+            ..*dialect
+        };
+        let mut text = Vec::new();
+        write_records(&mut text, headers, data, columns, &utf8)?;
+        let text = String::from_utf8_lossy(&text);
+        file.write_all(&dialect.encoding.encode(&text).0)?;
+        return Ok(());
+    }
+    if dialect.bom {
+        file.write_all(&[0xEF, 0xBB, 0xBF])?;
+    }
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(dialect.delimiter)
+        .quote_style(if dialect.quote_all {
+            QuoteStyle::Always
+        } else {
+            QuoteStyle::Necessary
+        })
+        .terminator(if dialect.crlf {
+            Terminator::CRLF
+        } else {
+            Terminator::Any(b'\n')
+        })
+        .from_writer(file);
+
+    let names = headers.iter().map(|h| h.name.as_str()).collect::<Vec<_>>();
+    match columns {
+        Some(columns) => {
+            if dialect.headers {
+                writer.write_record(
+                    columns
+                        .iter()
+                        .map(|&c| names.get(c).copied().unwrap_or_default()),
+                )?;
+            }
+            for record in data {
+                writer.write_record(columns.iter().map(|&c| record.get(c).unwrap_or_default()))?;
+            }
+        }
+        None => {
+            if dialect.headers {
+                writer.write_record(&names)?;
+            }
+            for record in data {
+                writer.write_record(record)?;
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn sort_data(mut sheet_clone: SheetVec, sort_by: (usize, SortOrder)) -> SheetVec {
+    sheet_clone.sort_by(|a, b| -> std::cmp::Ordering {
+        let val_a = a.get(sort_by.0).unwrap_or_default();
+        let val_b = b.get(sort_by.0).unwrap_or_default();
+
+        if sort_by.1 == SortOrder::Asc {
+            val_a.cmp(val_b)
+        } else {
+            val_b.cmp(val_a)
+        }
+    });
+
+    sheet_clone
+}
+
+pub fn matches_facets(record: &StringRecord, facets: &Facets) -> bool {
+    facets.iter().all(|(col, values)| {
+        values.is_empty() || values.contains(record.get(*col).unwrap_or_default())
+    })
+}
+
+pub fn matches_text(record: &StringRecord, filter: &str) -> bool {
+    record.iter().any(|c| c.contains(filter))
+}
+
+pub fn filter_data(master_data: SheetVec, filter: String, facets: &Facets) -> SheetVec {
+    master_data
+        .iter()
+        .filter(|r| matches_text(r, &filter) && matches_facets(r, facets))
+        .cloned()
+        .collect::<Vec<_>>()
+}
+
+/// The rows a tab shows for `filter`, `facets` and `sort`: the master rows that
+/// match, in sorted order. The command line's `query` gives the same rows.
+pub fn view_rows(
+    master_data: SheetVec,
+    filter: String,
+    facets: &Facets,
+    sort: Option<(ColumnId, SortOrder)>,
+) -> SheetVec {
+    let view = filter_data(master_data, filter, facets);
+    match sort {
+        Some(sort) => sort_data(view, sort),
+        None => view,
+    }
+}
+
+/// Sheet row numbers of the rows `view_rows` gives, in the same order. Views keep
+/// them, so an edit or delete in a view reaches the right row of the sheet.
+pub fn view_row_numbers(
+    master_data: &SheetVec,
+    filter: &str,
+    facets: &Facets,
+    sort: Option<(ColumnId, SortOrder)>,
+) -> Vec<usize> {
+    let mut rows = master_data
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| matches_text(r, filter) && matches_facets(r, facets))
+        .map(|(row, _)| row)
+        .collect::<Vec<_>>();
+    if let Some(sort) = sort {
+        sort_row_numbers(master_data, &mut rows, sort);
+    }
+    rows
+}
+
+/// Order sheet row numbers the way `sort_data` orders the rows they stand for.
+pub fn sort_row_numbers(master_data: &SheetVec, rows: &mut [usize], sort_by: (usize, SortOrder)) {
+    let cell = |row: usize| {
+        master_data
+            .get(row)
+            .and_then(|r| r.get(sort_by.0))
+            .unwrap_or_default()
+    };
+    rows.sort_by(|&a, &b| {
+        if sort_by.1 == SortOrder::Asc {
+            cell(a).cmp(cell(b))
+        } else {
+            cell(b).cmp(cell(a))
+        }
+    });
+}
+
+/// The sheet rows at `rows`, in that order.
+pub fn rows_at(master_data: &SheetVec, rows: &[usize]) -> SheetVec {
+    rows.iter()
+        .map(|&row| master_data.get(row).cloned().unwrap_or_default())
+        .collect()
+}
+
+/// Add the sheet rows from `first` on that match to a view built by
+/// `view_row_numbers`, where building it again would put them, without going
+/// over the rows it already has. Returns, for each row added, how many of the
+/// view's earlier rows come before it.
+pub fn extend_view(
+    master_data: &SheetVec,
+    view: &mut SheetVec,
+    rows: &mut Vec<usize>,
+    first: usize,
+    filter: &str,
+    facets: &Facets,
+    sort: Option<(ColumnId, SortOrder)>,
+) -> Vec<usize> {
+    let mut added = (first..master_data.len())
+        .filter(|&row| {
+            matches_text(&master_data[row], filter) && matches_facets(&master_data[row], facets)
+        })
+        .collect::<Vec<_>>();
+    let Some(sort) = sort else {
+        let before = vec![view.len(); added.len()];
+        view.extend(rows_at(master_data, &added));
+        rows.extend(added);
+        return before;
+    };
+    sort_row_numbers(master_data, &mut added, sort);
+
+    // Ties keep sheet order, and every added row comes after the view's rows.
+    let goes_first = |new: &StringRecord, old: &StringRecord| {
+        let new = new.get(sort.0).unwrap_or_default();
+        let old = old.get(sort.0).unwrap_or_default();
+        if sort.1 == SortOrder::Asc {
+            new < old
+        } else {
+            new > old
+        }
+    };
+    let mut old = std::mem::take(view)
+        .into_iter()
+        .zip(std::mem::take(rows))
+        .peekable();
+    let mut before = Vec::with_capacity(added.len());
+    for row in added {
+        let record = master_data[row].clone();
+        while let Some((old_record, old_row)) = old.next_if(|(r, _)| !goes_first(&record, r)) {
+            view.push(old_record);
+            rows.push(old_row);
+        }
+        before.push(view.len() - before.len());
+        view.push(record);
+        rows.push(row);
+    }
+    for (old_record, old_row) in old {
+        view.push(old_record);
+        rows.push(old_row);
+    }
+    before
+}
+
+/// Columns with at most this many distinct values get a facet list.
+pub const FACET_MAX_VALUES: usize = 50;
+
+/// Value counts for every low-cardinality column. The counts of a column reflect the
+/// text filter and the facets of all *other* columns, so checking a value in one
+/// facet never hides its siblings.
+pub fn facet_counts(master_data: &SheetVec, filter: &str, facets: &Facets) -> FacetCounts {
+    let num_columns = master_data.iter().map(|r| r.len()).max().unwrap_or(0);
+
+    // Pass 1: find low-cardinality columns and all of their values.
+    let mut values: Vec<Option<HashMap<&str, usize>>> = vec![Some(HashMap::new()); num_columns];
+    for record in master_data {
+        for (col, slot) in values.iter_mut().enumerate() {
+            let too_many = slot.as_mut().is_some_and(|counts| {
+                counts
+                    .entry(record.get(col).unwrap_or_default())
+                    .or_insert(0);
+                counts.len() > FACET_MAX_VALUES
+            });
+            if too_many {
+                *slot = None;
+            }
+        }
+    }
+
+    // Pass 2: count rows that pass everything but (at most) the column being counted.
+    for record in master_data.iter().filter(|r| matches_text(r, filter)) {
+        let failing: Vec<ColumnId> = facets
+            .iter()
+            .filter(|(col, allowed)| {
+                !allowed.is_empty() && !allowed.contains(record.get(**col).unwrap_or_default())
+            })
+            .map(|(col, _)| *col)
+            .collect();
+
+        let count_col = |col: usize| failing.is_empty() || failing == [col];
+        for (col, slot) in values.iter_mut().enumerate() {
+            if let Some(counts) = slot
+                && count_col(col)
+            {
+                *counts
+                    .entry(record.get(col).unwrap_or_default())
+                    .or_insert(0) += 1;
+            }
+        }
+    }
+
+    values
+        .into_iter()
+        .enumerate()
+        .filter_map(|(col, counts)| {
+            let mut counts = counts?
+                .into_iter()
+                .map(|(v, c)| (v.to_string(), c))
+                .collect::<Vec<_>>();
+            // A single distinct value (or one per row) makes a useless facet.
+            if counts.len() < 2 || counts.len() == master_data.len() {
+                return None;
+            }
+            counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            Some((col, counts))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::WINDOWS_1252;
+
+    fn sheet(rows: &[&[&str]]) -> SheetVec {
+        rows.iter()
+            .map(|row| StringRecord::from(row.to_vec()))
+            .collect()
+    }
+
+    fn headers(names: &[&str]) -> Vec<FileHeader> {
+        names
+            .iter()
+            .map(|name| FileHeader {
+                name: name.to_string(),
+                visible: true,
+                ..FileHeader::default()
+            })
+            .collect()
+    }
+
+    fn written(data: &SheetVec, columns: Option<&[ColumnId]>, dialect: &Dialect) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_records(&mut out, &headers(&["a", "b"]), data, columns, dialect).unwrap();
+        out
+    }
+
+    #[test]
+    fn edit_record_sets_only_that_cell() {
+        let mut data = sheet(&[&["1", "2", "3"], &["4", "5", "6"]]);
+        assert!(edit_record(&mut data, 1, 2, "x"));
+        assert_eq!(data, sheet(&[&["1", "2", "3"], &["4", "5", "x"]]));
+    }
+
+    #[test]
+    fn edit_record_outside_the_sheet() {
+        let mut data = sheet(&[&["1", "2"]]);
+        assert!(!edit_record(&mut data, 1, 0, "x"));
+        assert!(!edit_record(&mut data, 0, 2, "x"));
+        assert_eq!(data, sheet(&[&["1", "2"]]));
+    }
+
+    #[test]
+    fn csv_quote_only_when_needed() {
+        assert_eq!(csv_quote("plain"), "plain");
+        assert_eq!(csv_quote(""), "");
+        assert_eq!(csv_quote("a,b"), "\"a,b\"");
+        assert_eq!(csv_quote("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_quote("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_quote("cr\r"), "\"cr\r\"");
+    }
+
+    #[test]
+    fn sort_data_orders_text() {
+        let data = sheet(&[&["b", "2"], &["a", "10"], &["c", "9"]]);
+        let names = |data: &SheetVec| data.iter().map(|r| r[0].to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            names(&sort_data(data.clone(), (0, SortOrder::Asc))),
+            ["a", "b", "c"]
+        );
+        assert_eq!(
+            names(&sort_data(data.clone(), (0, SortOrder::Dsc))),
+            ["c", "b", "a"]
+        );
+        // Numbers are compared as text.
+        assert_eq!(
+            names(&sort_data(data, (1, SortOrder::Asc))),
+            ["a", "b", "c"]
+        );
+    }
+
+    #[test]
+    fn sort_data_keeps_order_of_ties_and_short_rows_first() {
+        let data = sheet(&[&["x", "1"], &["y"], &["z", "1"], &["w", "0"]]);
+        let sorted = sort_data(data, (1, SortOrder::Asc));
+        assert_eq!(
+            sorted,
+            sheet(&[&["y"], &["w", "0"], &["x", "1"], &["z", "1"]])
+        );
+    }
+
+    #[test]
+    fn filter_data_matches_substrings_case_sensitively() {
+        let data = sheet(&[&["Oslo", "no"], &["Rome", "it"], &["oslo", "no"]]);
+        let kept = filter_data(data, "Osl".to_string(), &Facets::new());
+        assert_eq!(kept, sheet(&[&["Oslo", "no"]]));
+    }
+
+    #[test]
+    fn filter_data_facets_or_within_a_column_and_across_columns() {
+        let data = sheet(&[&["a", "1"], &["b", "1"], &["c", "2"], &["a", "2"]]);
+        let mut facets = Facets::new();
+        facets.insert(0, ["a".to_string(), "b".to_string()].into());
+        facets.insert(1, ["1".to_string()].into());
+        assert_eq!(
+            filter_data(data.clone(), String::new(), &facets),
+            sheet(&[&["a", "1"], &["b", "1"]])
+        );
+        // An empty value set doesn't narrow anything down.
+        facets.insert(1, Default::default());
+        assert_eq!(filter_data(data, String::new(), &facets).len(), 3);
+    }
+
+    #[test]
+    fn view_rows_filters_then_sorts() {
+        let data = sheet(&[&["b", "x"], &["c", "y"], &["a", "x"]]);
+        let rows = view_rows(
+            data,
+            "x".to_string(),
+            &Facets::new(),
+            Some((0, SortOrder::Dsc)),
+        );
+        assert_eq!(rows, sheet(&[&["b", "x"], &["a", "x"]]));
+    }
+
+    #[test]
+    fn view_row_numbers_point_at_the_rows_of_the_view() {
+        let data = sheet(&[&["b", "x"], &["c", "y"], &["a", "x"], &["b", "x"]]);
+        for sort in [None, Some((0, SortOrder::Asc)), Some((0, SortOrder::Dsc))] {
+            let numbers = view_row_numbers(&data, "x", &Facets::new(), sort);
+            assert_eq!(
+                rows_at(&data, &numbers),
+                view_rows(data.clone(), "x".to_string(), &Facets::new(), sort)
+            );
+        }
+        assert_eq!(
+            view_row_numbers(&data, "x", &Facets::new(), Some((0, SortOrder::Asc))),
+            [2, 0, 3]
+        );
+    }
+
+    #[test]
+    fn extend_view_puts_rows_where_a_rebuild_would() {
+        let data = sheet(&[&["b", "x"], &["d", "y"], &["a", "x"]]);
+        let appended = sheet(&[&["c", "x"], &["e", "y"], &["a", "x"], &["z", "x"]]);
+        let all = data.iter().chain(&appended).cloned().collect::<SheetVec>();
+        for sort in [None, Some((0, SortOrder::Asc)), Some((0, SortOrder::Dsc))] {
+            let mut rows = view_row_numbers(&data, "x", &Facets::new(), sort);
+            let mut view = rows_at(&data, &rows);
+            let before = view.len();
+            let added = extend_view(&all, &mut view, &mut rows, 3, "x", &Facets::new(), sort);
+            assert_eq!(rows, view_row_numbers(&all, "x", &Facets::new(), sort));
+            assert_eq!(view, rows_at(&all, &rows));
+            assert_eq!(added.len(), 3);
+            assert!(added.iter().all(|&earlier| earlier <= before));
+        }
+        let mut rows = view_row_numbers(&data, "", &Facets::new(), Some((0, SortOrder::Asc)));
+        let mut view = rows_at(&data, &rows);
+        let added = extend_view(
+            &all,
+            &mut view,
+            &mut rows,
+            3,
+            "",
+            &Facets::new(),
+            Some((0, SortOrder::Asc)),
+        );
+        // a, a | b, c | d, e | z
+        assert_eq!(added, [1, 2, 3, 3]);
+    }
+
+    #[test]
+    fn write_records_quotes_what_needs_it() {
+        let data = sheet(&[&["1", "a,b"], &["2", "say \"hi\"\nbye"]]);
+        let out = written(&data, None, &Dialect::default());
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "a,b\n1,\"a,b\"\n2,\"say \"\"hi\"\"\nbye\"\n"
+        );
+    }
+
+    #[test]
+    fn write_records_keeps_the_dialect() {
+        let dialect = Dialect {
+            delimiter: b';',
+            quote_all: true,
+            crlf: true,
+            bom: true,
+            ..Dialect::default()
+        };
+        let out = written(&sheet(&[&["1", "x"]]), None, &dialect);
+        assert_eq!(out, b"\xEF\xBB\xBF\"a\";\"b\"\r\n\"1\";\"x\"\r\n");
+    }
+
+    #[test]
+    fn write_records_without_header_row() {
+        let dialect = Dialect {
+            headers: false,
+            ..Dialect::default()
+        };
+        let out = written(&sheet(&[&["1", "x"]]), None, &dialect);
+        assert_eq!(out, b"1,x\n");
+    }
+
+    #[test]
+    fn write_records_picks_columns() {
+        let out = written(
+            &sheet(&[&["1", "x"], &["2"]]),
+            Some(&[1, 0]),
+            &Dialect::default(),
+        );
+        assert_eq!(String::from_utf8(out).unwrap(), "b,a\nx,1\n,2\n");
+    }
+
+    #[test]
+    fn write_records_encodes() {
+        let dialect = Dialect {
+            encoding: WINDOWS_1252,
+            ..Dialect::default()
+        };
+        let out = written(&sheet(&[&["café", "€"]]), None, &dialect);
+        assert_eq!(out, b"a,b\ncaf\xE9,\x80\n");
+    }
+
+    #[test]
+    fn write_csv_replaces_the_file_and_leaves_no_temporary() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.csv");
+        let path = path.to_str().unwrap();
+        std::fs::write(path, "old").unwrap();
+        write_csv(
+            path,
+            &headers(&["a", "b"]),
+            &sheet(&[&["1", "2"]]),
+            &Dialect::default(),
+        )
+        .unwrap();
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "a,b\n1,2\n");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn backup_file_copies_next_to_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.csv");
+        let path = path.to_str().unwrap();
+        backup_file(path).unwrap();
+        std::fs::write(path, "a\n1\n").unwrap();
+        backup_file(path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(format!("{path}.bak")).unwrap(),
+            "a\n1\n"
+        );
+    }
+
+    #[test]
+    fn facet_counts_ignore_their_own_facet() {
+        let data = sheet(&[&["a", "x"], &["a", "y"], &["b", "x"], &["b", "x"]]);
+        let mut facets = Facets::new();
+        facets.insert(0, ["a".to_string()].into());
+        let counts = facet_counts(&data, "", &facets);
+        let count = |col: ColumnId| {
+            counts
+                .iter()
+                .find(|(c, _)| *c == col)
+                .map(|(_, values)| values.clone())
+                .unwrap_or_default()
+        };
+        // Column 0 still lists `b`; column 1 only counts rows with `a`.
+        assert_eq!(count(0), [("a".to_string(), 2), ("b".to_string(), 2)]);
+        assert_eq!(count(1), [("x".to_string(), 1), ("y".to_string(), 1)]);
+    }
+}
@@ -0,0 +1,461 @@
+use std::io::{self, Write};
+
+use crate::columnar::write_parquet;
+use crate::data::{edit_record, facet_counts, view_row_numbers, view_rows, write_csv};
+use crate::export::{ExportSpec, export, write_export};
+use crate::profile::{ColumnProfile, profile_column};
+use crate::read_csv::{Dialect, DialectOverrides, FileStamp, sniff_dialect};
+use crate::readers::{FileFormat, column_headers, detect_format, read_rows, split_sheet};
+use crate::sqlite::{self, SqlEdit, SqlSource};
+use crate::stats::{SelectionStats, aggregate};
+use crate::types::{
+    ColumnId, FacetCounts, Facets, FileHeader, Filter, SheetVec, SortOrder, column_named,
+};
+
+/// The columns and rows of a file, read whole, and how to write them back: in
+/// the file's own delimiter, quoting and encoding for delimited text, as
+/// `UPDATE`s for a SQLite table.
+#[derive(Default)]
+pub struct Dataset {
+    /// File the rows came from; `book.xlsx#Sheet` for a worksheet and
+    /// `shop.db#orders` for a table
+    pub path: String,
+    pub format: FileFormat,
+    pub dialect: Dialect,
+    pub headers: Vec<FileHeader>,
+    pub rows: SheetVec,
+    /// What the file looked like on disk when it was read or last saved
+    stamp: Option<FileStamp>,
+    /// What a SQLite table, view or query was read with
+    sql_source: Option<SqlSource>,
+    /// Cell edits of a SQLite table, written as `UPDATE`s on save
+    sql_edits: Vec<SqlEdit>,
+    dirty: bool,
+}
+
+impl Dataset {
+    /// Read a file, guessing its format from the extension and its delimiter,
+    /// quoting and header row from the first lines.
+    pub fn open(path: &str) -> csv::Result<Dataset> {
+        Dataset::open_with(path, None)
+    }
+
+    /// Read a file with the delimiter, encoding or header row given instead of
+    /// guessed.
+    pub fn open_with(path: &str, overrides: Option<DialectOverrides>) -> csv::Result<Dataset> {
+        let format = detect_format(path);
+        let mut dialect = sniff_dialect(path);
+        if let Some(overrides) = overrides {
+            dialect = overrides.apply(dialect);
+        }
+        let sql_source = match &format {
+            FileFormat::Sqlite(query) => {
+                let (db, name) = split_sheet(path);
+                Some(sqlite::source(
+                    db,
+                    name.unwrap_or_default(),
+                    query.as_deref(),
+                )?)
+            }
+            _ => None,
+        };
+
+        let headers = column_headers(path, &format, &dialect)?;
+        let (read_headers, rows, _) = read_rows(path, &format, &dialect, u64::MAX)?;
+        Ok(Dataset {
+            path: path.to_string(),
+            format,
+            dialect,
+            headers: read_headers.unwrap_or(headers),
+            rows,
+            stamp: FileStamp::of(path).ok(),
+            sql_source,
+            sql_edits: Vec::new(),
+            dirty: false,
+        })
+    }
+
+    /// Rows made in memory, with no file behind them until `save_as`.
+    pub fn new(headers: Vec<FileHeader>, rows: SheetVec) -> Dataset {
+        Dataset {
+            headers,
+            rows,
+            dirty: true,
+            ..Dataset::default()
+        }
+    }
+
+    /// Column named `name`, or failing that named so in another case.
+    pub fn column(&self, name: &str) -> Option<ColumnId> {
+        column_named(&self.headers, name)
+    }
+
+    /// Whether there are edits that haven't been saved.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Set one cell, by row of the file rather than of a view. Returns `false` when
+    /// the row or column doesn't exist, and for a SQLite view or query, which has
+    /// no table to write the edit to.
+    pub fn edit(&mut self, row: usize, col: ColumnId, value: &str) -> bool {
+        if self
+            .sql_source
+            .as_ref()
+            .is_some_and(|source| source.table.is_none())
+        {
+            return false;
+        }
+        if !edit_record(&mut self.rows, row, col, value) {
+            return false;
+        }
+        // Tables of a database are saved by rowid, the hidden first column.
+        if self.sql_source.is_some()
+            && let Some(rowid) = self.rows[row]
+                .get(0)
+                .and_then(|rowid| rowid.parse::<i64>().ok())
+        {
+            self.sql_edits.push((rowid, col, value.to_string()));
+        }
+        self.dirty = true;
+        true
+    }
+
+    /// Write the edits back into the file. Fails when the file changed on disk
+    /// since it was read, and for formats that can only be saved as a new file
+    /// (see `FileFormat::writable`), which is what `save_as` is for.
+    pub fn save(&mut self) -> csv::Result<()> {
+        if let Some(source) = &self.sql_source {
+            sqlite::write_edits(split_sheet(&self.path).0, source, &self.sql_edits)?;
+            self.sql_edits.clear();
+        } else if self.path.is_empty() {
+            return Err(io::Error::other("no file to save to, save it as a new file").into());
+        } else if !self.format.writable() {
+            return Err(io::Error::other(format!(
+                "{} can't be saved in place, save it as a new file",
+                self.format.label()
+            ))
+            .into());
+        } else if self
+            .stamp
+            .as_ref()
+            .is_some_and(|stamp| stamp.changed_on_disk(&self.path))
+        {
+            return Err(io::Error::other(format!("{} changed on disk", self.path)).into());
+        } else {
+            write_csv(&self.path, &self.headers, &self.rows, &self.dialect)?;
+        }
+        self.stamp = FileStamp::of(&self.path).ok();
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Write every row to a new file, Parquet for `.parquet` and `.pq` and CSV
+    /// otherwise, and carry on with that file. Delimited text keeps its dialect.
+    pub fn save_as(&mut self, path: &str) -> csv::Result<()> {
+        if detect_format(path) == FileFormat::Parquet {
+            write_parquet(path, &self.headers, &self.rows)?;
+            self.format = FileFormat::Parquet;
+        } else {
+            if !self.format.writable() {
+                self.dialect = Dialect::default();
+            }
+            write_csv(path, &self.headers, &self.rows, &self.dialect)?;
+            self.format = FileFormat::Delimited;
+        }
+        self.path = path.to_string();
+        self.stamp = FileStamp::of(path).ok();
+        self.sql_source = None;
+        self.sql_edits.clear();
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Type, nulls, distinct and most frequent values, and histograms of a column.
+    pub fn profile(&self, col: ColumnId) -> ColumnProfile {
+        profile_column(&self.rows, col)
+    }
+}
+
+/// Which rows of a dataset to show, in which order: a text filter, exact-match
+/// facets and a sort column, as in a tab of the viewer.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct View {
+    /// Keep rows with this text in some cell
+    pub filter: Filter,
+    pub facets: Facets,
+    pub sort: Option<(ColumnId, SortOrder)>,
+}
+
+impl View {
+    /// Keep rows whose `col` is `value`, besides other values checked for `col`.
+    pub fn facet(mut self, col: ColumnId, value: &str) -> View {
+        self.facets
+            .entry(col)
+            .or_default()
+            .insert(value.to_string());
+        self
+    }
+
+    /// The rows of `dataset` that match, in sorted order.
+    pub fn rows(&self, dataset: &Dataset) -> SheetVec {
+        view_rows(
+            dataset.rows.clone(),
+            self.filter.clone(),
+            &self.facets,
+            self.sort,
+        )
+    }
+
+    /// Row of `dataset` behind each row of `rows`, for editing through the view.
+    pub fn row_numbers(&self, dataset: &Dataset) -> Vec<usize> {
+        view_row_numbers(&dataset.rows, &self.filter, &self.facets, self.sort)
+    }
+
+    /// Values of the low-cardinality columns of `dataset` with the number of rows
+    /// each would match, as the facet sidebar lists them.
+    pub fn facet_counts(&self, dataset: &Dataset) -> FacetCounts {
+        facet_counts(&dataset.rows, &self.filter, &self.facets)
+    }
+
+    /// Count, sum, min, max and distinct values of one column of the rows.
+    pub fn stats(&self, dataset: &Dataset, col: ColumnId) -> SelectionStats {
+        let rows = self.rows(dataset);
+        aggregate(
+            rows.iter()
+                .map(|record| record.get(col).unwrap_or_default()),
+        )
+    }
+
+    /// Write the `columns` of the rows to `path` in the chosen format.
+    pub fn export(
+        &self,
+        dataset: &Dataset,
+        path: &str,
+        spec: &ExportSpec,
+        columns: &[ColumnId],
+    ) -> csv::Result<()> {
+        let rows = self.rows(dataset);
+        export(
+            path,
+            spec,
+            &dataset.headers,
+            &rows,
+            columns,
+            &dataset.dialect,
+        )
+    }
+
+    /// Write the `columns` of the rows to `out` in the chosen format.
+    pub fn write(
+        &self,
+        dataset: &Dataset,
+        out: &mut impl Write,
+        spec: &ExportSpec,
+        columns: &[ColumnId],
+    ) -> csv::Result<()> {
+        let rows = self.rows(dataset);
+        write_export(
+            out,
+            spec,
+            &dataset.headers,
+            &rows,
+            columns,
+            &dataset.dialect,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::ExportFormat;
+    use csv::StringRecord;
+    use rusqlite::Connection;
+    use std::time::{Duration, SystemTime};
+
+    fn file(dir: &tempfile::TempDir, name: &str, contents: &str) -> String {
+        let path = dir.path().join(name).to_str().unwrap().to_string();
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn database(dir: &tempfile::TempDir) -> String {
+        let path = dir.path().join("shop.db").to_str().unwrap().to_string();
+        Connection::open(&path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE items (name TEXT, stock INTEGER);
+                 INSERT INTO items VALUES ('apple', 7), ('pear', 12);
+                 CREATE VIEW stocked AS SELECT name FROM items WHERE stock > 10;",
+            )
+            .unwrap();
+        path
+    }
+
+    #[test]
+    fn open_reads_the_dialect_columns_and_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = file(&dir, "people.csv", "Name;City\nAda;Oslo\nBo;Rome\n");
+        let people = Dataset::open(&path).unwrap();
+        assert_eq!(people.dialect.delimiter, b';');
+        assert_eq!(people.column("city"), Some(1));
+        assert_eq!(people.rows.len(), 2);
+        assert!(!people.is_dirty());
+
+        let view = View::default().facet(1, "Rome");
+        assert_eq!(view.rows(&people), [StringRecord::from(vec!["Bo", "Rome"])]);
+        assert_eq!(view.stats(&people, 0).count, 1);
+    }
+
+    #[test]
+    fn edit_marks_dirty_and_save_keeps_the_dialect() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = file(&dir, "people.csv", "name;city\r\nAda;Oslo\r\n");
+        let mut people = Dataset::open(&path).unwrap();
+        assert!(!people.edit(1, 0, "x"));
+        assert!(!people.edit(0, 2, "x"));
+        assert!(!people.is_dirty());
+        assert!(people.edit(0, 1, "Bergen"));
+        assert!(people.is_dirty());
+        people.save().unwrap();
+        assert!(!people.is_dirty());
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "name;city\r\nAda;Bergen\r\n"
+        );
+    }
+
+    #[test]
+    fn save_refuses_when_the_file_changed_on_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = file(&dir, "people.csv", "name\nAda\n");
+        let mut people = Dataset::open(&path).unwrap();
+        assert!(people.edit(0, 0, "Bo"));
+
+        std::fs::write(&path, "name\nCy\n").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+        assert!(people.save().is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "name\nCy\n");
+        assert!(people.is_dirty());
+
+        // Saving as a new file still works, and carries on with that file.
+        let copy = dir.path().join("copy.csv").to_str().unwrap().to_string();
+        people.save_as(&copy).unwrap();
+        assert_eq!(people.path, copy);
+        assert_eq!(std::fs::read_to_string(&copy).unwrap(), "name\nBo\n");
+        assert!(people.edit(0, 0, "Di"));
+        people.save().unwrap();
+        assert_eq!(std::fs::read_to_string(&copy).unwrap(), "name\nDi\n");
+    }
+
+    #[test]
+    fn save_as_parquet_reads_back_the_same_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = file(&dir, "people.jsonl", "{\"name\":\"Ada\",\"age\":36}\n");
+        let mut people = Dataset::open(&path).unwrap();
+        // JSON Lines can only be saved as a new file.
+        assert!(people.save().is_err());
+
+        let parquet = dir
+            .path()
+            .join("people.parquet")
+            .to_str()
+            .unwrap()
+            .to_string();
+        people.save_as(&parquet).unwrap();
+        assert_eq!(people.format, FileFormat::Parquet);
+        let read = Dataset::open(&parquet).unwrap();
+        assert_eq!(read.rows, people.rows);
+        assert_eq!(read.headers.len(), 2);
+    }
+
+    #[test]
+    fn rows_made_in_memory_need_a_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let headers = vec![FileHeader {
+            name: "n".to_string(),
+            visible: true,
+            ..FileHeader::default()
+        }];
+        let mut made = Dataset::new(headers, vec![StringRecord::from(vec!["1"])]);
+        assert!(made.is_dirty());
+        assert!(made.save().is_err());
+
+        let path = dir.path().join("made.csv").to_str().unwrap().to_string();
+        made.save_as(&path).unwrap();
+        assert!(!made.is_dirty());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "n\n1\n");
+
+        let mut out = Vec::new();
+        let spec = ExportSpec {
+            format: ExportFormat::JsonLines,
+            ..ExportSpec::default()
+        };
+        View::default().write(&made, &mut out, &spec, &[0]).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "{\"n\": 1}\n");
+    }
+
+    #[test]
+    fn table_edits_are_saved_as_updates() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = database(&dir);
+        let mut items = Dataset::open(&format!("{db}#items")).unwrap();
+        let stock = items.column("stock").unwrap();
+        assert!(!items.headers[0].visible);
+        assert!(items.edit(1, stock, "3"));
+        items.save().unwrap();
+        assert!(!items.is_dirty());
+
+        let read = Dataset::open(&format!("{db}#items")).unwrap();
+        assert_eq!(read.rows[1].get(stock), Some("3"));
+        // Nothing left to write the second time.
+        items.save().unwrap();
+    }
+
+    #[test]
+    fn sqlite_views_are_read_only_but_save_without_edits() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = database(&dir);
+        let mut stocked = Dataset::open(&format!("{db}#stocked")).unwrap();
+        assert_eq!(stocked.rows, [StringRecord::from(vec!["pear"])]);
+        assert!(!stocked.edit(0, 0, "plum"));
+        assert!(!stocked.is_dirty());
+        stocked.save().unwrap();
+
+        let csv = dir.path().join("stocked.csv").to_str().unwrap().to_string();
+        stocked.save_as(&csv).unwrap();
+        assert!(stocked.edit(0, 0, "plum"));
+        stocked.save().unwrap();
+        assert_eq!(std::fs::read_to_string(&csv).unwrap(), "name\nplum\n");
+    }
+
+    #[test]
+    fn edits_through_a_filtered_view_are_saved() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("people.csv");
+        let path = path.to_str().unwrap();
+        std::fs::write(path, "name,city\nAda,Oslo\nBo,Rome\nCy,Oslo\n").unwrap();
+
+        let mut people = Dataset::open(path).unwrap();
+        let view = View {
+            filter: "Oslo".to_string(),
+            sort: Some((0, SortOrder::Dsc)),
+            ..View::default()
+        };
+        let row = view.row_numbers(&people)[0];
+        assert!(people.edit(row, 1, "Bergen"));
+        people.save().unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(path).unwrap(),
+            "name,city\nAda,Oslo\nBo,Rome\nCy,Bergen\n"
+        );
+        assert_eq!(Dataset::open(path).unwrap().rows, people.rows);
+    }
+}
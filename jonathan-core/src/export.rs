@@ -0,0 +1,620 @@
+use std::io::{BufWriter, Write};
+
+use crate::data::{write_atomic, write_records};
+use crate::formats::{html_escape, json_string, sql_ident};
+use crate::profile::{ColumnType, infer_type, is_null_like, parse_bool, parse_date};
+use crate::read_csv::Dialect;
+use crate::stats::parse_number;
+use crate::types::{ColumnId, FileHeader, SheetVec};
+
+/// File formats a tab's view can be exported to.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum ExportFormat {
+    /// In the dialect the file was read with
+    #[default]
+    Csv,
+    Tsv,
+    Json,
+    JsonLines,
+    Markdown,
+    Html,
+    Sql,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 7] = [
+        ExportFormat::Csv,
+        ExportFormat::Tsv,
+        ExportFormat::Json,
+        ExportFormat::JsonLines,
+        ExportFormat::Markdown,
+        ExportFormat::Html,
+        ExportFormat::Sql,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Tsv => "TSV",
+            ExportFormat::Json => "JSON array",
+            ExportFormat::JsonLines => "JSON Lines",
+            ExportFormat::Markdown => "Markdown table",
+            ExportFormat::Html => "HTML page",
+            ExportFormat::Sql => "SQL script",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Tsv => "tsv",
+            ExportFormat::Json => "json",
+            ExportFormat::JsonLines => "jsonl",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Html => "html",
+            ExportFormat::Sql => "sql",
+        }
+    }
+}
+
+/// Database a SQL export is meant for; decides column types, quoting and literals.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum SqlDialect {
+    #[default]
+    Sqlite,
+    Postgres,
+    MySql,
+}
+
+impl SqlDialect {
+    pub const ALL: [SqlDialect; 3] = [SqlDialect::Sqlite, SqlDialect::Postgres, SqlDialect::MySql];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SqlDialect::Sqlite => "SQLite",
+            SqlDialect::Postgres => "PostgreSQL",
+            SqlDialect::MySql => "MySQL",
+        }
+    }
+
+    fn type_name(&self, kind: ColumnType) -> &'static str {
+        match (self, kind) {
+            (SqlDialect::Sqlite, ColumnType::Integer | ColumnType::Boolean) => "INTEGER",
+            (SqlDialect::Sqlite, ColumnType::Float) => "REAL",
+            (_, ColumnType::Integer) => "BIGINT",
+            (SqlDialect::Postgres, ColumnType::Float) => "DOUBLE PRECISION",
+            (_, ColumnType::Float) => "DOUBLE",
+            (_, ColumnType::Boolean) => "BOOLEAN",
+            (SqlDialect::Postgres, ColumnType::Date) => "TIMESTAMP",
+            (SqlDialect::MySql, ColumnType::Date) => "DATETIME",
+            _ => "TEXT",
+        }
+    }
+
+    fn ident(&self, name: &str) -> String {
+        match self {
+            SqlDialect::MySql => format!("`{}`", name.replace('`', "``")),
+            _ => sql_ident(name),
+        }
+    }
+
+    fn string(&self, value: &str) -> String {
+        let value = value.replace('\'', "''");
+        match self {
+            // MySQL treats backslashes in string literals as escapes by default.
+            SqlDialect::MySql => format!("'{}'", value.replace('\\', "\\\\")),
+            _ => format!("'{value}'"),
+        }
+    }
+
+    fn bool(&self, value: bool) -> &'static str {
+        match (self, value) {
+            (SqlDialect::Sqlite, true) => "1",
+            (SqlDialect::Sqlite, false) => "0",
+            (_, true) => "TRUE",
+            (_, false) => "FALSE",
+        }
+    }
+}
+
+/// What the export dialog is set to.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ExportSpec {
+    pub format: ExportFormat,
+    pub sql_dialect: SqlDialect,
+    /// Table name in SQL output and page title in HTML output
+    pub table_name: String,
+}
+
+/// Type of each exported column: the type the file declares for it, otherwise
+/// inferred from the exported rows.
+pub fn column_types(
+    headers: &[FileHeader],
+    data: &SheetVec,
+    columns: &[ColumnId],
+) -> Vec<ColumnType> {
+    columns
+        .iter()
+        .map(|&col| {
+            headers.get(col).and_then(|h| h.kind).unwrap_or_else(|| {
+                infer_type(
+                    data.iter()
+                        .map(|record| record.get(col).unwrap_or_default()),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Write the `columns` of `data` to `path` in the chosen format. CSV keeps the
+/// file's own `dialect`.
+pub fn export(
+    path: &str,
+    spec: &ExportSpec,
+    headers: &[FileHeader],
+    data: &SheetVec,
+    columns: &[ColumnId],
+    dialect: &Dialect,
+) -> Result<(), csv::Error> {
+    write_atomic(path, |file| {
+        write_export(file, spec, headers, data, columns, dialect)
+    })
+}
+
+/// Write the `columns` of `data` to `out` in the chosen format, see `export`.
+pub fn write_export(
+    out: &mut impl Write,
+    spec: &ExportSpec,
+    headers: &[FileHeader],
+    data: &SheetVec,
+    columns: &[ColumnId],
+    dialect: &Dialect,
+) -> Result<(), csv::Error> {
+    let tsv = Dialect {
+        delimiter: b'\t',
+        ..Dialect::default()
+    };
+    match spec.format {
+        ExportFormat::Csv => return write_records(out, headers, data, Some(columns), dialect),
+        ExportFormat::Tsv => return write_records(out, headers, data, Some(columns), &tsv),
+        _ => {}
+    }
+
+    let names = columns
+        .iter()
+        .map(|&col| match headers.get(col) {
+            Some(h) if !h.name.is_empty() => h.name.clone(),
+            _ => format!("column_{}", col + 1),
+        })
+        .collect::<Vec<_>>();
+    let kinds = column_types(headers, data, columns);
+    let rows = data.iter().map(|record| {
+        columns
+            .iter()
+            .map(|&col| record.get(col).unwrap_or_default())
+    });
+
+    let mut out = BufWriter::new(out);
+    match spec.format {
+        ExportFormat::Json => {
+            write!(out, "[")?;
+            for (i, row) in rows.enumerate() {
+                let separator = if i == 0 { "\n" } else { ",\n" };
+                write!(out, "{separator}  {}", json_object(&names, &kinds, row))?;
+            }
+            writeln!(out, "\n]")?;
+        }
+        ExportFormat::JsonLines => {
+            for row in rows {
+                writeln!(out, "{}", json_object(&names, &kinds, row))?;
+            }
+        }
+        ExportFormat::Markdown => {
+            let cell = |v: &str| v.replace('|', "\\|").replace(['\n', '\r'], " ");
+            let header = names.iter().map(|n| cell(n)).collect::<Vec<_>>();
+            let rule = kinds
+                .iter()
+                .map(|k| if k.is_numeric() { " ---: " } else { " --- " })
+                .collect::<Vec<_>>();
+            writeln!(out, "| {} |", header.join(" | "))?;
+            writeln!(out, "|{}|", rule.join("|"))?;
+            for row in rows {
+                let row = row.map(cell).collect::<Vec<_>>();
+                writeln!(out, "| {} |", row.join(" | "))?;
+            }
+        }
+        ExportFormat::Html => {
+            write_html(&mut out, &spec.table_name, &names, &kinds, rows)?;
+        }
+        ExportFormat::Sql => {
+            write_sql(&mut out, spec, &names, &kinds, rows)?;
+        }
+        ExportFormat::Csv | ExportFormat::Tsv => unreachable!(),
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Empty cells, and placeholders such as "NA" in columns that aren't text.
+fn is_null(value: &str, kind: ColumnType) -> bool {
+    value.is_empty() || (kind != ColumnType::Text && is_null_like(value))
+}
+
+/// Numbers and booleans are written bare in columns of that type, dates and text
+/// as strings. Values that don't parse as their column's type stay strings.
+fn json_value(value: &str, kind: ColumnType) -> String {
+    if is_null(value, kind) {
+        return "null".to_string();
+    }
+    let typed = match kind {
+        ColumnType::Integer => value.trim().parse::<i64>().ok().map(|n| n.to_string()),
+        ColumnType::Float => parse_number(value).map(|n| n.to_string()),
+        ColumnType::Boolean => parse_bool(value).map(|b| b.to_string()),
+        _ => None,
+    };
+    typed.unwrap_or_else(|| json_string(value))
+}
+
+fn json_object<'a>(
+    names: &[String],
+    kinds: &[ColumnType],
+    row: impl Iterator<Item = &'a str>,
+) -> String {
+    let fields = names
+        .iter()
+        .zip(kinds)
+        .zip(row)
+        .map(|((name, kind), value)| format!("{}: {}", json_string(name), json_value(value, *kind)))
+        .collect::<Vec<_>>();
+    format!("{{{}}}", fields.join(", "))
+}
+
+fn sql_value(dialect: SqlDialect, value: &str, kind: ColumnType) -> String {
+    if is_null(value, kind) {
+        return "NULL".to_string();
+    }
+    let typed = match kind {
+        ColumnType::Integer => value.trim().parse::<i64>().ok().map(|n| n.to_string()),
+        ColumnType::Float => parse_number(value).map(|n| n.to_string()),
+        ColumnType::Boolean => parse_bool(value).map(|b| dialect.bool(b).to_string()),
+        ColumnType::Date => {
+            parse_date(value).map(|d| dialect.string(&d.format("%Y-%m-%d %H:%M:%S").to_string()))
+        }
+        _ => None,
+    };
+    typed.unwrap_or_else(|| dialect.string(value))
+}
+
+fn write_sql<'a, R: Iterator<Item = &'a str>>(
+    out: &mut impl Write,
+    spec: &ExportSpec,
+    names: &[String],
+    kinds: &[ColumnType],
+    rows: impl Iterator<Item = R>,
+) -> std::io::Result<()> {
+    let dialect = spec.sql_dialect;
+    let table = dialect.ident(if spec.table_name.is_empty() {
+        "export"
+    } else {
+        &spec.table_name
+    });
+
+    writeln!(out, "CREATE TABLE {table} (")?;
+    for (i, (name, kind)) in names.iter().zip(kinds).enumerate() {
+        let separator = if i + 1 < names.len() { "," } else { "" };
+        writeln!(
+            out,
+            "  {} {}{separator}",
+            dialect.ident(name),
+            dialect.type_name(*kind)
+        )?;
+    }
+    writeln!(out, ");")?;
+    writeln!(out)?;
+
+    let columns = names
+        .iter()
+        .map(|n| dialect.ident(n))
+        .collect::<Vec<_>>()
+        .join(", ");
+    writeln!(out, "BEGIN;")?;
+    for row in rows {
+        let values = row
+            .zip(kinds)
+            .map(|(value, kind)| sql_value(dialect, value, *kind))
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(out, "INSERT INTO {table} ({columns}) VALUES ({values});")?;
+    }
+    writeln!(out, "COMMIT;")
+}
+
+fn write_html<'a, R: Iterator<Item = &'a str>>(
+    out: &mut impl Write,
+    title: &str,
+    names: &[String],
+    kinds: &[ColumnType],
+    rows: impl Iterator<Item = R>,
+) -> std::io::Result<()> {
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html>\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>{}</title>", html_escape(title))?;
+    writeln!(
+        out,
+        "<style>\n\
+         table {{ border-collapse: collapse; font-family: sans-serif; font-size: 14px; }}\n\
+         th, td {{ border: 1px solid #ccc; padding: 4px 8px; }}\n\
+         th {{ background: #f4f4f4; position: sticky; top: 0; }}\n\
+         td.num {{ text-align: right; }}\n\
+         </style>"
+    )?;
+    writeln!(out, "</head>\n<body>\n<table>\n  <thead>\n    <tr>")?;
+    for name in names {
+        writeln!(out, "      <th>{}</th>", html_escape(name))?;
+    }
+    writeln!(out, "    </tr>\n  </thead>\n  <tbody>")?;
+    for row in rows {
+        write!(out, "    <tr>")?;
+        for (value, kind) in row.zip(kinds) {
+            let class = if kind.is_numeric() {
+                " class=\"num\""
+            } else {
+                ""
+            };
+            write!(out, "<td{class}>{}</td>", html_escape(value))?;
+        }
+        writeln!(out, "</tr>")?;
+    }
+    writeln!(out, "  </tbody>\n</table>\n</body>\n</html>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use csv::StringRecord;
+    use serde_json::json;
+
+    const NOTE: &str = "say \"hi\" C:\\dir | a\nb <&>";
+
+    fn sheet(rows: &[&[&str]]) -> SheetVec {
+        rows.iter()
+            .map(|row| StringRecord::from(row.to_vec()))
+            .collect()
+    }
+
+    fn headers(names: &[&str]) -> Vec<FileHeader> {
+        names
+            .iter()
+            .map(|name| FileHeader {
+                name: name.to_string(),
+                visible: true,
+                ..FileHeader::default()
+            })
+            .collect()
+    }
+
+    /// An integer, float, boolean, date and text column, with a null in each of
+    /// the last four.
+    fn exported(format: ExportFormat, sql_dialect: SqlDialect, table_name: &str) -> String {
+        let spec = ExportSpec {
+            format,
+            sql_dialect,
+            table_name: table_name.to_string(),
+        };
+        let data = sheet(&[
+            &["1", "2.5", "yes", "2024-01-02", NOTE],
+            &["2", "NA", "no", "", "it's"],
+            &["3", "-1.5", "", "2024-01-03 04:05:06", ""],
+        ]);
+        let headers = headers(&["id", "price", "ok", "when", "note"]);
+        let mut out = Vec::new();
+        write_export(
+            &mut out,
+            &spec,
+            &headers,
+            &data,
+            &[0, 1, 2, 3, 4],
+            &Dialect::default(),
+        )
+        .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn expected_json() -> serde_json::Value {
+        json!([
+            {"id": 1, "price": 2.5, "ok": true, "when": "2024-01-02", "note": NOTE},
+            {"id": 2, "price": null, "ok": false, "when": null, "note": "it's"},
+            {"id": 3, "price": -1.5, "ok": null, "when": "2024-01-03 04:05:06", "note": null},
+        ])
+    }
+
+    #[test]
+    fn json_round_trips_types_nulls_and_escapes() {
+        let text = exported(ExportFormat::Json, SqlDialect::default(), "");
+        let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(value, expected_json());
+    }
+
+    #[test]
+    fn json_lines_hold_one_object_per_row() {
+        let text = exported(ExportFormat::JsonLines, SqlDialect::default(), "");
+        let rows = text
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(serde_json::Value::Array(rows), expected_json());
+    }
+
+    #[test]
+    fn values_that_dont_parse_as_the_declared_type_stay_strings() {
+        let mut headers = headers(&["n", "b"]);
+        headers[0].kind = Some(ColumnType::Integer);
+        headers[1].kind = Some(ColumnType::Boolean);
+        let data = sheet(&[&["7", "TRUE"], &["seven", "maybe"]]);
+        assert_eq!(
+            column_types(&headers, &data, &[0, 1]),
+            [ColumnType::Integer, ColumnType::Boolean]
+        );
+        let spec = ExportSpec {
+            format: ExportFormat::JsonLines,
+            ..ExportSpec::default()
+        };
+        let mut out = Vec::new();
+        write_export(
+            &mut out,
+            &spec,
+            &headers,
+            &data,
+            &[0, 1],
+            &Dialect::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"n\": 7, \"b\": true}\n{\"n\": \"seven\", \"b\": \"maybe\"}\n"
+        );
+    }
+
+    #[test]
+    fn markdown_escapes_pipes_and_flattens_newlines() {
+        let text = exported(ExportFormat::Markdown, SqlDialect::default(), "");
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "| id | price | ok | when | note |");
+        assert_eq!(lines[1], "| ---: | ---: | --- | --- | --- |");
+        assert_eq!(
+            lines[2],
+            "| 1 | 2.5 | yes | 2024-01-02 | say \"hi\" C:\\dir \\| a b <&> |"
+        );
+        assert_eq!(lines[3], "| 2 | NA | no |  | it's |");
+        assert_eq!(lines.len(), 5);
+    }
+
+    #[test]
+    fn html_escapes_markup_and_right_aligns_numbers() {
+        let text = exported(
+            ExportFormat::Html,
+            SqlDialect::default(),
+            "<Prices & \"more\">",
+        );
+        assert!(text.contains("<title>&lt;Prices &amp; &quot;more&quot;&gt;</title>"));
+        assert!(text.contains("      <th>note</th>\n"));
+        assert!(text.contains(
+            "    <tr><td class=\"num\">1</td><td class=\"num\">2.5</td><td>yes</td>\
+             <td>2024-01-02</td><td>say &quot;hi&quot; C:\\dir | a\nb &lt;&amp;&gt;</td></tr>\n"
+        ));
+        assert!(!text.contains("<&>"));
+    }
+
+    #[test]
+    fn sql_literals_follow_the_dialect() {
+        let inserts = |dialect| {
+            let text = exported(ExportFormat::Sql, dialect, "");
+            let start = text.find("BEGIN;\n").unwrap() + "BEGIN;\n".len();
+            let end = text.find("COMMIT;").unwrap();
+            text[start..end].to_string()
+        };
+        let columns = "(\"id\", \"price\", \"ok\", \"when\", \"note\")";
+        assert_eq!(
+            inserts(SqlDialect::Sqlite),
+            format!(
+                "INSERT INTO \"export\" {columns} VALUES \
+                 (1, 2.5, 1, '2024-01-02 00:00:00', 'say \"hi\" C:\\dir | a\nb <&>');\n\
+                 INSERT INTO \"export\" {columns} VALUES (2, NULL, 0, NULL, 'it''s');\n\
+                 INSERT INTO \"export\" {columns} VALUES \
+                 (3, -1.5, NULL, '2024-01-03 04:05:06', NULL);\n"
+            )
+        );
+        assert_eq!(
+            inserts(SqlDialect::Postgres),
+            format!(
+                "INSERT INTO \"export\" {columns} VALUES \
+                 (1, 2.5, TRUE, '2024-01-02 00:00:00', 'say \"hi\" C:\\dir | a\nb <&>');\n\
+                 INSERT INTO \"export\" {columns} VALUES (2, NULL, FALSE, NULL, 'it''s');\n\
+                 INSERT INTO \"export\" {columns} VALUES \
+                 (3, -1.5, NULL, '2024-01-03 04:05:06', NULL);\n"
+            )
+        );
+        let columns = "(`id`, `price`, `ok`, `when`, `note`)";
+        assert_eq!(
+            inserts(SqlDialect::MySql),
+            format!(
+                "INSERT INTO `export` {columns} VALUES \
+                 (1, 2.5, TRUE, '2024-01-02 00:00:00', 'say \"hi\" C:\\\\dir | a\nb <&>');\n\
+                 INSERT INTO `export` {columns} VALUES (2, NULL, FALSE, NULL, 'it''s');\n\
+                 INSERT INTO `export` {columns} VALUES \
+                 (3, -1.5, NULL, '2024-01-03 04:05:06', NULL);\n"
+            )
+        );
+    }
+
+    #[test]
+    fn sql_declares_the_dialect_types_and_quotes_identifiers() {
+        let create = |dialect, table| {
+            let text = exported(ExportFormat::Sql, dialect, table);
+            text[..text.find(");").unwrap() + 2].to_string()
+        };
+        assert_eq!(
+            create(SqlDialect::Sqlite, "my \"table\""),
+            "CREATE TABLE \"my \"\"table\"\"\" (\n  \"id\" INTEGER,\n  \"price\" REAL,\n  \
+             \"ok\" INTEGER,\n  \"when\" TEXT,\n  \"note\" TEXT\n);"
+        );
+        assert_eq!(
+            create(SqlDialect::Postgres, ""),
+            "CREATE TABLE \"export\" (\n  \"id\" BIGINT,\n  \"price\" DOUBLE PRECISION,\n  \
+             \"ok\" BOOLEAN,\n  \"when\" TIMESTAMP,\n  \"note\" TEXT\n);"
+        );
+        assert_eq!(
+            create(SqlDialect::MySql, "my`table"),
+            "CREATE TABLE `my``table` (\n  `id` BIGINT,\n  `price` DOUBLE,\n  \
+             `ok` BOOLEAN,\n  `when` DATETIME,\n  `note` TEXT\n);"
+        );
+    }
+
+    #[test]
+    fn sqlite_scripts_read_back_the_same_values() {
+        for dialect in [SqlDialect::Sqlite, SqlDialect::Postgres] {
+            let script = exported(ExportFormat::Sql, dialect, "my \"table\"");
+            let conn = rusqlite::Connection::open_in_memory().unwrap();
+            conn.execute_batch(&script).unwrap();
+            let mut stmt = conn
+                .prepare(
+                    "SELECT id, price, ok, \"when\", note FROM \"my \"\"table\"\"\" ORDER BY id",
+                )
+                .unwrap();
+            let rows = stmt
+                .query_map([], |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, Option<f64>>(1)?,
+                        row.get::<_, Option<bool>>(2)?,
+                        row.get::<_, Option<String>>(3)?,
+                        row.get::<_, Option<String>>(4)?,
+                    ))
+                })
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            assert_eq!(
+                rows,
+                [
+                    (
+                        1,
+                        Some(2.5),
+                        Some(true),
+                        Some("2024-01-02 00:00:00".to_string()),
+                        Some(NOTE.to_string())
+                    ),
+                    (2, None, Some(false), None, Some("it's".to_string())),
+                    (
+                        3,
+                        Some(-1.5),
+                        None,
+                        Some("2024-01-03 04:05:06".to_string()),
+                        None
+                    ),
+                ],
+                "{dialect:?}"
+            );
+        }
+    }
+}
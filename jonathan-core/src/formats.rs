@@ -0,0 +1,201 @@
+use serde::{Deserialize, Serialize};
+
+use crate::data::csv_quote;
+
+/// Text formats a block of cells can be rendered into (clipboard, exports).
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum ClipboardFormat {
+    #[default]
+    Csv,
+    CsvWithHeaders,
+    /// Tab-separated, pastes straight into spreadsheet cells
+    Tsv,
+    Markdown,
+    Html,
+    Json,
+    SqlInsert,
+}
+
+impl ClipboardFormat {
+    pub const ALL: [ClipboardFormat; 7] = [
+        ClipboardFormat::Csv,
+        ClipboardFormat::CsvWithHeaders,
+        ClipboardFormat::Tsv,
+        ClipboardFormat::Markdown,
+        ClipboardFormat::Html,
+        ClipboardFormat::Json,
+        ClipboardFormat::SqlInsert,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ClipboardFormat::Csv => "CSV",
+            ClipboardFormat::CsvWithHeaders => "CSV with headers",
+            ClipboardFormat::Tsv => "TSV",
+            ClipboardFormat::Markdown => "Markdown table",
+            ClipboardFormat::Html => "HTML table",
+            ClipboardFormat::Json => "JSON",
+            ClipboardFormat::SqlInsert => "SQL INSERT",
+        }
+    }
+}
+
+/// Render `rows` (already restricted to the copied columns) as text.
+/// `table_name` is only used by SQL output.
+pub fn render(
+    format: ClipboardFormat,
+    table_name: &str,
+    headers: &[&str],
+    rows: &[Vec<&str>],
+) -> String {
+    match format {
+        ClipboardFormat::Csv => delimited(rows, ",", csv_quote),
+        ClipboardFormat::CsvWithHeaders => {
+            let mut out = headers
+                .iter()
+                .map(|h| csv_quote(h))
+                .collect::<Vec<_>>()
+                .join(",");
+            if !rows.is_empty() {
+                out.push('\n');
+                out.push_str(&delimited(rows, ",", csv_quote));
+            }
+            out
+        }
+        ClipboardFormat::Tsv => delimited(rows, "\t", tsv_clean),
+        ClipboardFormat::Markdown => markdown(headers, rows),
+        ClipboardFormat::Html => html(headers, rows),
+        ClipboardFormat::Json => json(headers, rows),
+        ClipboardFormat::SqlInsert => sql_insert(table_name, headers, rows),
+    }
+}
+
+fn delimited(rows: &[Vec<&str>], sep: &str, quote: fn(&str) -> String) -> String {
+    rows.iter()
+        .map(|row| row.iter().map(|v| quote(v)).collect::<Vec<_>>().join(sep))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Tabs and newlines would break the cell grid when pasted, so flatten them to spaces.
+fn tsv_clean(value: &str) -> String {
+    value.replace(['\t', '\n', '\r'], " ")
+}
+
+fn markdown(headers: &[&str], rows: &[Vec<&str>]) -> String {
+    let cell = |v: &str| v.replace('|', "\\|").replace(['\n', '\r'], " ");
+    let mut lines = vec![
+        format!(
+            "| {} |",
+            headers
+                .iter()
+                .map(|h| cell(h))
+                .collect::<Vec<_>>()
+                .join(" | ")
+        ),
+        format!("|{}|", vec![" --- "; headers.len()].join("|")),
+    ];
+    for row in rows {
+        lines.push(format!(
+            "| {} |",
+            row.iter().map(|v| cell(v)).collect::<Vec<_>>().join(" | ")
+        ));
+    }
+    lines.join("\n")
+}
+
+pub fn html_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn html(headers: &[&str], rows: &[Vec<&str>]) -> String {
+    let mut out = String::from("<table>\n  <thead>\n    <tr>");
+    for h in headers {
+        out.push_str(&format!("<th>{}</th>", html_escape(h)));
+    }
+    out.push_str("</tr>\n  </thead>\n  <tbody>\n");
+    for row in rows {
+        out.push_str("    <tr>");
+        for v in row {
+            out.push_str(&format!("<td>{}</td>", html_escape(v)));
+        }
+        out.push_str("</tr>\n");
+    }
+    out.push_str("  </tbody>\n</table>");
+    out
+}
+
+pub fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for ch in value.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn json(headers: &[&str], rows: &[Vec<&str>]) -> String {
+    let objects = rows
+        .iter()
+        .map(|row| {
+            let fields = headers
+                .iter()
+                .zip(row)
+                .map(|(h, v)| format!("{}: {}", json_string(h), json_string(v)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("  {{{fields}}}")
+        })
+        .collect::<Vec<_>>();
+    format!("[\n{}\n]", objects.join(",\n"))
+}
+
+pub fn sql_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Numbers are written bare, empty cells as NULL, everything else as a string literal.
+pub fn sql_literal(value: &str) -> String {
+    if value.is_empty() {
+        "NULL".to_string()
+    } else if value.parse::<f64>().is_ok_and(|n| n.is_finite()) {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "''"))
+    }
+}
+
+fn sql_insert(table_name: &str, headers: &[&str], rows: &[Vec<&str>]) -> String {
+    let columns = headers
+        .iter()
+        .map(|h| sql_ident(h))
+        .collect::<Vec<_>>()
+        .join(", ");
+    rows.iter()
+        .map(|row| {
+            let values = row
+                .iter()
+                .map(|v| sql_literal(v))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!(
+                "INSERT INTO {} ({columns}) VALUES ({values});",
+                sql_ident(table_name)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,39 @@
+//! The table engine of Jonathan: reading CSV, JSON Lines, fixed-width text,
+//! Parquet, Arrow, worksheets and SQLite, filtering, sorting, editing, profiling
+//! and writing them back or exporting them. Nothing here knows about a window;
+//! the viewer, `jonathan query` and the terminal view all build on it.
+//!
+//! Start with [`Dataset`] and [`View`]:
+//!
+//! ```no_run
+//! use jonathan_core::{Dataset, SortOrder, View};
+//!
+//! let mut people = Dataset::open("people.csv")?;
+//! let view = View {
+//!     filter: "Oslo".to_string(),
+//!     sort: Some((people.column("age").unwrap(), SortOrder::Dsc)),
+//!     ..View::default()
+//! };
+//! println!("{} people in Oslo", view.rows(&people).len());
+//! people.edit(0, 1, "42");
+//! people.save()?;
+//! # Ok::<(), csv::Error>(())
+//! ```
+
+pub mod columnar;
+pub mod data;
+pub mod dataset;
+pub mod export;
+pub mod formats;
+pub mod pivot;
+pub mod profile;
+pub mod read_csv;
+pub mod readers;
+pub mod selection;
+pub mod spreadsheet;
+pub mod sqlite;
+pub mod stats;
+pub mod types;
+
+pub use dataset::{Dataset, View};
+pub use types::{ColumnId, Facets, FileHeader, Filter, SheetVec, SortOrder};
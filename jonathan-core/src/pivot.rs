@@ -0,0 +1,230 @@
+use csv::StringRecord;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use crate::stats::{format_number, parse_number};
+use crate::types::{ColumnId, Facets, FileHeader, Filename, SheetVec};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Aggregation {
+    /// Number of rows in the group (the value column is ignored)
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+    DistinctCount,
+}
+
+impl Aggregation {
+    pub const ALL: [Aggregation; 6] = [
+        Aggregation::Count,
+        Aggregation::Sum,
+        Aggregation::Avg,
+        Aggregation::Min,
+        Aggregation::Max,
+        Aggregation::DistinctCount,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Aggregation::Count => "count",
+            Aggregation::Sum => "sum",
+            Aggregation::Avg => "avg",
+            Aggregation::Min => "min",
+            Aggregation::Max => "max",
+            Aggregation::DistinctCount => "distinct",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PivotSpec {
+    pub row_groups: Vec<ColumnId>,
+    pub column_group: Option<ColumnId>,
+    pub values: Vec<(ColumnId, Aggregation)>,
+}
+
+/// Where a pivot tab came from, so an aggregate cell can be traced back to its rows.
+#[derive(Debug, Clone)]
+pub struct PivotSource {
+    pub source_file: Filename,
+    pub spec: PivotSpec,
+    /// Text filter and facets of the source view the pivot was built from
+    pub base_filter: String,
+    pub base_facets: Facets,
+    /// Column-group value of every aggregate column block, in output order
+    pub column_keys: Vec<Option<String>>,
+}
+
+impl PivotSource {
+    /// Facets selecting the source rows behind an output cell, or `None` for a
+    /// row-group cell.
+    pub fn drill_down(&self, row: &StringRecord, col: usize) -> Option<Facets> {
+        let num_groups = self.spec.row_groups.len();
+        let num_values = self.spec.values.len().max(1);
+        let block = col.checked_sub(num_groups)? / num_values;
+
+        let mut facets = self.base_facets.clone();
+        for (i, group_col) in self.spec.row_groups.iter().enumerate() {
+            facets.insert(*group_col, BTreeSet::from([row.get(i)?.to_string()]));
+        }
+        if let (Some(group_col), Some(Some(key))) =
+            (self.spec.column_group, self.column_keys.get(block))
+        {
+            facets.insert(group_col, BTreeSet::from([key.clone()]));
+        }
+        Some(facets)
+    }
+}
+
+#[derive(Default, Clone)]
+struct Accumulator<'a> {
+    count: usize,
+    numeric: usize,
+    sum: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+    min_text: Option<&'a str>,
+    max_text: Option<&'a str>,
+    distinct: HashSet<&'a str>,
+}
+
+impl<'a> Accumulator<'a> {
+    fn add(&mut self, value: &'a str) {
+        self.count += 1;
+        self.distinct.insert(value);
+        if let Some(n) = parse_number(value) {
+            self.numeric += 1;
+            self.sum += n;
+            self.min = Some(self.min.map_or(n, |m| m.min(n)));
+            self.max = Some(self.max.map_or(n, |m| m.max(n)));
+        } else if !value.is_empty() {
+            self.min_text = Some(self.min_text.map_or(value, |m| m.min(value)));
+            self.max_text = Some(self.max_text.map_or(value, |m| m.max(value)));
+        }
+    }
+
+    /// Numeric min/max win over text ones when a column mixes both.
+    fn result(&self, aggregation: Aggregation) -> String {
+        match aggregation {
+            Aggregation::Count => self.count.to_string(),
+            Aggregation::Sum => format_number(self.sum),
+            Aggregation::Avg if self.numeric > 0 => format_number(self.sum / self.numeric as f64),
+            Aggregation::Avg => String::new(),
+            Aggregation::Min => self
+                .min
+                .map(format_number)
+                .or(self.min_text.map(str::to_string))
+                .unwrap_or_default(),
+            Aggregation::Max => self
+                .max
+                .map(format_number)
+                .or(self.max_text.map(str::to_string))
+                .unwrap_or_default(),
+            Aggregation::DistinctCount => self.distinct.len().to_string(),
+        }
+    }
+}
+
+fn header_name(headers: &[FileHeader], col: ColumnId) -> String {
+    match headers.get(col) {
+        Some(h) if h.name.is_empty() && col == 0 => "id".to_string(),
+        Some(h) => h.name.clone(),
+        None => format!("column {}", col + 1),
+    }
+}
+
+/// Group `data` by the spec's row groups (and column group) and aggregate the value
+/// columns. Returns the output headers, rows and the column-group key of every
+/// aggregate block.
+pub fn pivot(
+    data: &SheetVec,
+    headers: &[FileHeader],
+    spec: &PivotSpec,
+) -> (Vec<FileHeader>, SheetVec, Vec<Option<String>>) {
+    let values = if spec.values.is_empty() {
+        vec![(0, Aggregation::Count)]
+    } else {
+        spec.values.clone()
+    };
+
+    let mut groups: BTreeMap<Vec<&str>, BTreeMap<Option<&str>, Vec<Accumulator>>> = BTreeMap::new();
+    let mut column_keys: BTreeSet<Option<&str>> = BTreeSet::new();
+
+    for record in data {
+        let row_key = spec
+            .row_groups
+            .iter()
+            .map(|c| record.get(*c).unwrap_or_default())
+            .collect::<Vec<_>>();
+        let column_key = spec.column_group.map(|c| record.get(c).unwrap_or_default());
+        column_keys.insert(column_key);
+
+        let accumulators = groups
+            .entry(row_key)
+            .or_default()
+            .entry(column_key)
+            .or_insert_with(|| vec![Accumulator::default(); values.len()]);
+        for (acc, (col, _)) in accumulators.iter_mut().zip(&values) {
+            acc.add(record.get(*col).unwrap_or_default());
+        }
+    }
+
+    let mut out_headers = spec
+        .row_groups
+        .iter()
+        .map(|c| header_name(headers, *c))
+        .collect::<Vec<_>>();
+    for column_key in &column_keys {
+        for (col, aggregation) in &values {
+            let value_name = match aggregation {
+                Aggregation::Count => aggregation.label().to_string(),
+                _ => format!("{}({})", aggregation.label(), header_name(headers, *col)),
+            };
+            out_headers.push(match (spec.column_group, column_key) {
+                (Some(group_col), Some(key)) => {
+                    format!(
+                        "{}={} · {}",
+                        header_name(headers, group_col),
+                        key,
+                        value_name
+                    )
+                }
+                _ => value_name,
+            });
+        }
+    }
+
+    let rows = groups
+        .iter()
+        .map(|(row_key, by_column)| {
+            let mut fields = row_key.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+            for column_key in &column_keys {
+                for (i, (_, aggregation)) in values.iter().enumerate() {
+                    fields.push(
+                        by_column
+                            .get(column_key)
+                            .map(|accs| accs[i].result(*aggregation))
+                            .unwrap_or_default(),
+                    );
+                }
+            }
+            StringRecord::from(fields)
+        })
+        .collect::<Vec<_>>();
+
+    let out_headers = out_headers
+        .into_iter()
+        .map(|name| FileHeader {
+            name,
+            visible: true,
+            ..FileHeader::default()
+        })
+        .collect();
+    let column_keys = column_keys
+        .into_iter()
+        .map(|k| k.map(str::to_string))
+        .collect();
+
+    (out_headers, rows, column_keys)
+}
@@ -0,0 +1,262 @@
+use std::collections::HashMap;
+
+use crate::stats::parse_number;
+use crate::types::{ColumnId, SheetVec};
+
+/// How many of the most frequent values a profile keeps.
+pub const TOP_N: usize = 10;
+pub const HISTOGRAM_BINS: usize = 12;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ColumnType {
+    /// Only null-like values
+    Empty,
+    Integer,
+    Float,
+    Boolean,
+    Date,
+    Text,
+}
+
+impl ColumnType {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ColumnType::Empty => "empty",
+            ColumnType::Integer => "integer",
+            ColumnType::Float => "float",
+            ColumnType::Boolean => "boolean",
+            ColumnType::Date => "date",
+            ColumnType::Text => "text",
+        }
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        matches!(self, ColumnType::Integer | ColumnType::Float)
+    }
+}
+
+/// Placeholders that mean "no value" in the files we get ("-" is common in exports).
+pub fn is_null_like(value: &str) -> bool {
+    matches!(
+        value.trim(),
+        "" | "-" | "NA" | "N/A" | "n/a" | "null" | "NULL" | "None" | "NaN"
+    )
+}
+
+pub fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" | "yes" => Some(true),
+        "false" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+pub fn parse_date(value: &str) -> Option<chrono::NaiveDateTime> {
+    use chrono::{DateTime, NaiveDate, NaiveDateTime};
+    let value = value.trim();
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S"))
+        .ok()
+        .or_else(|| {
+            DateTime::parse_from_rfc3339(value)
+                .ok()
+                .map(|d| d.naive_utc())
+        })
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        })
+}
+
+/// The narrowest type every non-null value fits into.
+pub fn infer_type<'a>(values: impl Iterator<Item = &'a str>) -> ColumnType {
+    let mut kind = ColumnType::Empty;
+    for value in values.filter(|v| !is_null_like(v)) {
+        let fits = |k: ColumnType| match k {
+            ColumnType::Integer => value.trim().parse::<i64>().is_ok(),
+            ColumnType::Float => parse_number(value).is_some(),
+            ColumnType::Boolean => parse_bool(value).is_some(),
+            ColumnType::Date => parse_date(value).is_some(),
+            ColumnType::Text | ColumnType::Empty => true,
+        };
+        kind = match kind {
+            ColumnType::Empty => [
+                ColumnType::Integer,
+                ColumnType::Float,
+                ColumnType::Boolean,
+                ColumnType::Date,
+            ]
+            .into_iter()
+            .find(|k| fits(*k))
+            .unwrap_or(ColumnType::Text),
+            ColumnType::Integer if !fits(ColumnType::Integer) => {
+                if fits(ColumnType::Float) {
+                    ColumnType::Float
+                } else {
+                    ColumnType::Text
+                }
+            }
+            k if !fits(k) => ColumnType::Text,
+            k => k,
+        };
+        if kind == ColumnType::Text {
+            break;
+        }
+    }
+    kind
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumericSummary {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub median: f64,
+    pub stddev: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bin {
+    pub start: f64,
+    pub end: f64,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnProfile {
+    pub column: ColumnId,
+    pub kind: ColumnType,
+    pub count: usize,
+    pub nulls: usize,
+    pub distinct: usize,
+    /// Most frequent values, most common first
+    pub top_values: Vec<(String, usize)>,
+    /// Smallest/largest non-null value (numeric order for numbers, text order otherwise)
+    pub min: Option<String>,
+    pub max: Option<String>,
+    pub numeric: Option<NumericSummary>,
+    /// Histogram of the numeric values (empty for non-numeric columns)
+    pub histogram: Vec<Bin>,
+    /// Histogram of value lengths in characters
+    pub lengths: Vec<Bin>,
+}
+
+/// Split `values` into `bins` equal-width buckets between their min and max.
+pub fn histogram(values: &[f64], bins: usize) -> Vec<Bin> {
+    let (Some(min), Some(max)) = (
+        values.iter().copied().reduce(f64::min),
+        values.iter().copied().reduce(f64::max),
+    ) else {
+        return vec![];
+    };
+    if bins == 0 {
+        return vec![];
+    }
+    if min == max {
+        return vec![Bin {
+            start: min,
+            end: max,
+            count: values.len(),
+        }];
+    }
+
+    let width = (max - min) / bins as f64;
+    let mut out = (0..bins)
+        .map(|i| Bin {
+            start: min + width * i as f64,
+            end: min + width * (i + 1) as f64,
+            count: 0,
+        })
+        .collect::<Vec<_>>();
+    for v in values {
+        let i = (((v - min) / width) as usize).min(bins - 1);
+        out[i].count += 1;
+    }
+    out
+}
+
+pub fn numeric_summary(values: &mut [f64]) -> Option<NumericSummary> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+    let mid = values.len() / 2;
+    let median = if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    };
+    Some(NumericSummary {
+        min: values[0],
+        max: values[values.len() - 1],
+        mean,
+        median,
+        stddev: variance.sqrt(),
+    })
+}
+
+pub fn profile_column(data: &SheetVec, column: ColumnId) -> ColumnProfile {
+    let values = || data.iter().map(|r| r.get(column).unwrap_or_default());
+    let kind = infer_type(values());
+
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    let mut nulls = 0;
+    let mut numbers = Vec::new();
+    let mut lengths = Vec::new();
+    let mut min: Option<&str> = None;
+    let mut max: Option<&str> = None;
+
+    for value in values() {
+        *counts.entry(value).or_default() += 1;
+        if is_null_like(value) {
+            nulls += 1;
+            continue;
+        }
+        lengths.push(value.chars().count() as f64);
+        if kind.is_numeric() {
+            if let Some(n) = parse_number(value) {
+                numbers.push(n);
+            }
+        } else {
+            min = Some(min.map_or(value, |m| m.min(value)));
+            max = Some(max.map_or(value, |m| m.max(value)));
+        }
+    }
+
+    let mut top_values = counts
+        .iter()
+        .map(|(v, c)| (v.to_string(), *c))
+        .collect::<Vec<_>>();
+    top_values.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    top_values.truncate(TOP_N);
+
+    let value_histogram = histogram(&numbers, HISTOGRAM_BINS);
+    // Lengths are whole numbers, so never split them finer than one character per bin.
+    let length_span = lengths.iter().copied().fold(0.0, f64::max)
+        - lengths.iter().copied().fold(f64::INFINITY, f64::min);
+    let length_bins = (length_span.max(0.0) as usize + 1).min(HISTOGRAM_BINS);
+    let length_histogram = histogram(&lengths, length_bins);
+    let numeric = numeric_summary(&mut numbers);
+    let (min, max) = match &numeric {
+        Some(n) => (Some(n.min.to_string()), Some(n.max.to_string())),
+        None => (min.map(str::to_string), max.map(str::to_string)),
+    };
+
+    ColumnProfile {
+        column,
+        kind,
+        count: data.len(),
+        nulls,
+        distinct: counts.len(),
+        top_values,
+        min,
+        max,
+        numeric,
+        histogram: value_histogram,
+        lengths: length_histogram,
+    }
+}
@@ -0,0 +1,295 @@
+use csv::{Reader, ReaderBuilder, StringRecord};
+use encoding_rs::{Encoding, UTF_8};
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::time::SystemTime;
+
+use crate::readers::split_sheet;
+
+/// How much of a file is looked at to guess its dialect.
+const SNIFF_BYTES: u64 = 64 * 1024;
+const SNIFF_LINES: usize = 20;
+const DELIMITERS: [u8; 4] = [b',', b';', b'\t', b'|'];
+
+/// How a file was written, so saving it again keeps the same format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dialect {
+    pub delimiter: u8,
+    /// Every field is quoted, not only the ones that need it
+    pub quote_all: bool,
+    pub crlf: bool,
+    /// Starts with a UTF-8 byte order mark
+    pub bom: bool,
+    /// The first row names the columns
+    pub headers: bool,
+    pub encoding: &'static Encoding,
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Dialect {
+            delimiter: b',',
+            quote_all: false,
+            crlf: false,
+            bom: false,
+            headers: true,
+            encoding: UTF_8,
+        }
+    }
+}
+
+impl Dialect {
+    pub fn reader(&self) -> ReaderBuilder {
+        let mut builder = ReaderBuilder::new();
+        builder.delimiter(self.delimiter).has_headers(self.headers);
+        builder
+    }
+
+    /// `bytes` as UTF-8 text, decoded from the file's encoding.
+    pub fn decode<'a>(&self, bytes: &'a [u8]) -> Cow<'a, str> {
+        self.encoding.decode_without_bom_handling(bytes).0
+    }
+}
+
+/// Dialect settings given on the command line, which win over what is sniffed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DialectOverrides {
+    pub delimiter: Option<u8>,
+    pub encoding: Option<&'static Encoding>,
+    pub no_header: bool,
+}
+
+impl DialectOverrides {
+    pub fn apply(&self, dialect: Dialect) -> Dialect {
+        Dialect {
+            delimiter: self.delimiter.unwrap_or(dialect.delimiter),
+            encoding: self.encoding.unwrap_or(dialect.encoding),
+            headers: dialect.headers && !self.no_header,
+            ..dialect
+        }
+    }
+}
+
+/// Content hash and modification time of a file, taken when it was loaded or
+/// last saved, to notice when something else changed it on disk.
+#[derive(Debug, Clone)]
+pub struct FileStamp {
+    pub modified: Option<SystemTime>,
+    /// Bytes of the file that have been read into rows; follow mode picks up
+    /// from here
+    pub len: u64,
+    pub sha256: [u8; 32],
+    /// Hash of the first `hashed` bytes, still open for bytes appended later
+    hasher: Sha256,
+    hashed: u64,
+}
+
+impl FileStamp {
+    /// A worksheet is stamped by its workbook.
+    pub fn of(path: &str) -> std::io::Result<FileStamp> {
+        let mut file = File::open(split_sheet(path).0)?;
+        let metadata = file.metadata()?;
+        let mut hasher = Sha256::new();
+        let hashed = std::io::copy(&mut file, &mut hasher)?;
+        Ok(FileStamp {
+            modified: metadata.modified().ok(),
+            len: hashed,
+            sha256: hasher.clone().finalize().into(),
+            hasher,
+            hashed,
+        })
+    }
+
+    /// The stamp of `path` after more was appended to it, hashing only the bytes
+    /// past what this stamp hashed. A file that got shorter is hashed again.
+    pub fn extend(&self, path: &str) -> std::io::Result<FileStamp> {
+        let mut file = File::open(split_sheet(path).0)?;
+        let metadata = file.metadata()?;
+        if metadata.len() < self.hashed {
+            return FileStamp::of(path);
+        }
+        file.seek(SeekFrom::Start(self.hashed))?;
+        let mut hasher = self.hasher.clone();
+        let hashed = self.hashed + std::io::copy(&mut file, &mut hasher)?;
+        Ok(FileStamp {
+            modified: metadata.modified().ok(),
+            len: self.len,
+            sha256: hasher.clone().finalize().into(),
+            hasher,
+            hashed,
+        })
+    }
+
+    /// The same stamp for a file read into rows up to byte `len`.
+    pub fn read_to(self, len: u64) -> FileStamp {
+        FileStamp { len, ..self }
+    }
+
+    /// Whether the file at `path` no longer matches this stamp. The hash is only
+    /// compared when the modification time moved, so a mere touch doesn't count.
+    /// A file that is gone doesn't count either; saving just recreates it.
+    pub fn changed_on_disk(&self, path: &str) -> bool {
+        let Ok(modified) = std::fs::metadata(split_sheet(path).0).and_then(|m| m.modified()) else {
+            return false;
+        };
+        if self.modified == Some(modified) {
+            return false;
+        }
+        FileStamp::of(path).is_ok_and(|now| now.sha256 != self.sha256)
+    }
+}
+
+/// Split a line on `delimiter`, ignoring delimiters inside quoted fields.
+fn split_fields(line: &[u8], delimiter: u8) -> Vec<&[u8]> {
+    let mut fields = Vec::new();
+    let mut in_quotes = false;
+    let mut start = 0;
+    for (i, &b) in line.iter().enumerate() {
+        if b == b'"' {
+            in_quotes = !in_quotes;
+        } else if b == delimiter && !in_quotes {
+            fields.push(&line[start..i]);
+            start = i + 1;
+        }
+    }
+    fields.push(&line[start..]);
+    fields
+}
+
+fn sample_lines(bytes: &[u8]) -> Vec<&[u8]> {
+    let mut lines = bytes
+        .split(|&b| b == b'\n')
+        .map(|l| l.strip_suffix(b"\r").unwrap_or(l))
+        .filter(|l| !l.is_empty())
+        .take(SNIFF_LINES)
+        .collect::<Vec<_>>();
+    // The last line may be cut off by the sniff window.
+    if lines.len() > 1 {
+        lines.pop();
+    }
+    lines
+}
+
+/// The delimiter that splits every line into the same, largest number of fields.
+fn find_delimiter(lines: &[&[u8]]) -> Option<u8> {
+    DELIMITERS
+        .iter()
+        .filter_map(|&d| {
+            let counts = lines
+                .iter()
+                .map(|l| split_fields(l, d).len())
+                .collect::<Vec<_>>();
+            let first = *counts.first()?;
+            (first > 1 && counts.iter().all(|&c| c == first)).then_some((d, first))
+        })
+        .max_by_key(|&(_, fields)| fields)
+        .map(|(d, _)| d)
+}
+
+/// Whether the start of a file reads as delimiter-separated values at all.
+pub fn has_delimiter(bytes: &[u8]) -> bool {
+    find_delimiter(&sample_lines(bytes)).is_some()
+}
+
+/// Guess delimiter, quoting, line endings and BOM from the start of a file's contents.
+pub fn sniff_bytes(bytes: &[u8]) -> Dialect {
+    let bom = bytes.starts_with(&[0xEF, 0xBB, 0xBF]);
+    let bytes = if bom { &bytes[3..] } else { bytes };
+    let crlf = bytes
+        .iter()
+        .position(|&b| b == b'\n')
+        .is_some_and(|i| i > 0 && bytes[i - 1] == b'\r');
+
+    let lines = sample_lines(bytes);
+    let delimiter = find_delimiter(&lines).unwrap_or(b',');
+
+    let quote_all = !lines.is_empty()
+        && lines.iter().all(|l| {
+            split_fields(l, delimiter)
+                .iter()
+                .all(|f| f.starts_with(b"\"") && f.ends_with(b"\""))
+        });
+
+    Dialect {
+        delimiter,
+        quote_all,
+        crlf,
+        bom,
+        ..Dialect::default()
+    }
+}
+
+/// The part of a file that dialect and format guesses look at.
+pub fn read_head(path: &str) -> std::io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    File::open(path)?
+        .take(SNIFF_BYTES)
+        .read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Worksheets and database tables are read as typed cells, without a dialect.
+pub fn sniff_dialect(path: &str) -> Dialect {
+    if split_sheet(path).1.is_some() {
+        return Dialect::default();
+    }
+    match read_head(path) {
+        Ok(bytes) => sniff_bytes(&bytes),
+        Err(e) => {
+            eprintln!("Could not sniff {}: {}", path, e);
+            Dialect::default()
+        }
+    }
+}
+
+/// Open a delimited file for reading in `dialect`. Files in another encoding than
+/// UTF-8 are decoded as a whole up front. Without a header row, the headers are
+/// the first row, which is also read as a record.
+pub fn iterate_csv(
+    path: &str,
+    dialect: &Dialect,
+) -> csv::Result<(Reader<Box<dyn Read + Send>>, StringRecord)> {
+    let mut file = File::open(path)?;
+    let input: Box<dyn Read + Send> = if dialect.encoding == UTF_8 {
+        Box::new(file)
+    } else {
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        Box::new(Cursor::new(dialect.decode(&bytes).into_owned()))
+    };
+    let mut rdr = dialect.reader().from_reader(input);
+
+    let headers = rdr.headers()?.clone();
+
+    Ok((rdr, headers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn extending_a_stamp_hashes_like_stamping_the_whole_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("log.csv");
+        let path = path.to_str().unwrap();
+        std::fs::write(path, "a,b\n1,2\n").unwrap();
+        let stamp = FileStamp::of(path).unwrap().read_to(4);
+
+        let mut file = std::fs::OpenOptions::new().append(true).open(path).unwrap();
+        file.write_all(b"3,4\n").unwrap();
+        let extended = stamp.extend(path).unwrap();
+        assert_eq!(extended.sha256, FileStamp::of(path).unwrap().sha256);
+        assert_eq!(extended.len, 4);
+        assert!(!extended.changed_on_disk(path));
+
+        std::fs::write(path, "x\n").unwrap();
+        assert_eq!(
+            extended.extend(path).unwrap().sha256,
+            FileStamp::of(path).unwrap().sha256
+        );
+    }
+}
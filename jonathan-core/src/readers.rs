@@ -0,0 +1,426 @@
+use csv::{ReaderBuilder, StringRecord};
+use encoding_rs::UTF_8;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::columnar;
+use crate::read_csv::{Dialect, has_delimiter, iterate_csv, read_head};
+use crate::spreadsheet;
+use crate::sqlite;
+use crate::types::{FileHeader, SheetVec};
+
+/// Extensions offered by the open dialogs.
+pub const OPENABLE_EXTENSIONS: [&str; 22] = [
+    "csv", "tsv", "tab", "txt", "dat", "prn", "fwf", "jsonl", "ndjson", "parquet", "pq", "arrow",
+    "feather", "ipc", "xlsx", "xlsm", "xlsb", "xls", "ods", "sqlite", "sqlite3", "db",
+];
+/// Workbook extensions; a worksheet is opened as `book.xlsx#Sheet name`.
+const SPREADSHEET_EXTENSIONS: [&str; 5] = ["xlsx", "xlsm", "xlsb", "xls", "ods"];
+/// SQLite extensions; a table or view is opened as `shop.db#orders`.
+const DATABASE_EXTENSIONS: [&str; 3] = ["sqlite", "sqlite3", "db"];
+/// Lines looked at to infer fixed-width columns, and shown when drawing them.
+pub const FIXED_WIDTH_SAMPLE: usize = 50;
+
+/// How a file's rows are laid out on disk.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FileFormat {
+    /// CSV, TSV and other delimiter-separated text, see `Dialect`
+    #[default]
+    Delimited,
+    /// One JSON object per line; nested objects become `parent.child` columns
+    JsonLines,
+    /// Columns at fixed character positions, given as the index each column starts
+    /// at. The first line holds the column names.
+    FixedWidth(Vec<usize>),
+    /// Apache Parquet; row groups are read as they scroll into view
+    Parquet,
+    /// Arrow IPC file (Feather v2); record batches are read as they scroll into view
+    ArrowIpc,
+    /// One worksheet of an Excel or OpenDocument workbook, see `split_sheet`
+    Spreadsheet,
+    /// A table or view of a SQLite database, see `split_sheet`, or the rows of a
+    /// custom `SELECT` on it
+    Sqlite(Option<String>),
+}
+
+impl FileFormat {
+    pub fn label(&self) -> &'static str {
+        match self {
+            FileFormat::Delimited => "CSV",
+            FileFormat::JsonLines => "JSON Lines",
+            FileFormat::FixedWidth(_) => "fixed-width text",
+            FileFormat::Parquet => "Parquet",
+            FileFormat::ArrowIpc => "Arrow IPC",
+            FileFormat::Spreadsheet => "spreadsheet",
+            FileFormat::Sqlite(_) => "SQLite",
+        }
+    }
+
+    /// Whether the file is made of row groups that can be read one at a time.
+    pub fn columnar(&self) -> bool {
+        matches!(self, FileFormat::Parquet | FileFormat::ArrowIpc)
+    }
+
+    /// Whether rows written to the end of the file can be read on their own, for
+    /// follow mode.
+    pub fn appendable(&self) -> bool {
+        matches!(
+            self,
+            FileFormat::Delimited | FileFormat::JsonLines | FileFormat::FixedWidth(_)
+        )
+    }
+
+    /// Whether edits can be saved back into the file itself. The other formats are
+    /// saved as a new CSV or Parquet file; SQLite tables get their edits as `UPDATE`s.
+    pub fn writable(&self) -> bool {
+        *self == FileFormat::Delimited
+    }
+}
+
+/// Guess the format from the extension, and for plain text files from whether
+/// the contents split on a delimiter or line up in columns.
+pub fn detect_format(path: &str) -> FileFormat {
+    let (path, _) = split_sheet(path);
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "jsonl" | "ndjson" => return FileFormat::JsonLines,
+        "parquet" | "pq" => return FileFormat::Parquet,
+        "arrow" | "feather" | "ipc" => return FileFormat::ArrowIpc,
+        e if SPREADSHEET_EXTENSIONS.contains(&e) => return FileFormat::Spreadsheet,
+        e if DATABASE_EXTENSIONS.contains(&e) => return FileFormat::Sqlite(None),
+        "txt" | "dat" | "prn" | "fwf" => {}
+        _ => return FileFormat::Delimited,
+    }
+
+    let Ok(bytes) = read_head(path) else {
+        return FileFormat::Delimited;
+    };
+    if has_delimiter(&bytes) {
+        return FileFormat::Delimited;
+    }
+    let text = String::from_utf8_lossy(&bytes);
+    let lines = text.lines().take(FIXED_WIDTH_SAMPLE).collect::<Vec<_>>();
+    let bounds = infer_column_starts(&lines);
+    if bounds.len() > 1 {
+        FileFormat::FixedWidth(bounds)
+    } else {
+        FileFormat::Delimited
+    }
+}
+
+/// The workbook or database path and the worksheet or table of `book.xlsx#Sheet
+/// name` or `shop.db#orders`. Any other file is its own path, without a sheet.
+pub fn split_sheet(file: &str) -> (&str, Option<&str>) {
+    let lower = file.to_ascii_lowercase();
+    SPREADSHEET_EXTENSIONS
+        .iter()
+        .chain(&DATABASE_EXTENSIONS)
+        .filter_map(|e| lower.find(&format!(".{e}#")).map(|i| i + e.len() + 1))
+        .min()
+        .map_or((file, None), |end| (&file[..end], Some(&file[end + 1..])))
+}
+
+pub fn sheet_file(path: &str, sheet: &str) -> String {
+    format!("{path}#{sheet}")
+}
+
+/// Columns of a fixed-width text start where a run of blanks, shared by every
+/// line, ends.
+pub fn infer_column_starts(lines: &[&str]) -> Vec<usize> {
+    let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    let mut blank = vec![true; width];
+    for line in lines {
+        for (i, c) in line.chars().enumerate() {
+            if !c.is_whitespace() {
+                blank[i] = false;
+            }
+        }
+    }
+    let mut starts = vec![0];
+    starts.extend((1..width).filter(|&i| blank[i - 1] && !blank[i]));
+    starts
+}
+
+/// Cut a line at the column starts, trimming the padding.
+pub fn split_fixed_width(line: &str, starts: &[usize]) -> StringRecord {
+    let offsets = line
+        .char_indices()
+        .map(|(offset, _)| offset)
+        .chain(std::iter::once(line.len()))
+        .collect::<Vec<_>>();
+    let at = |char_index: usize| offsets[char_index.min(offsets.len() - 1)];
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, &start)| {
+            let end = starts.get(i + 1).map_or(line.len(), |&end| at(end));
+            line[at(start)..end.max(at(start))].trim()
+        })
+        .collect()
+}
+
+pub fn headers_from_names(names: &[String]) -> Vec<FileHeader> {
+    names
+        .iter()
+        .map(|name| FileHeader {
+            name: name.clone(),
+            visible: true,
+            ..FileHeader::default()
+        })
+        .collect()
+}
+
+/// Columns that can be told without reading the whole file. Empty for JSON Lines
+/// and worksheets, whose columns are only known once their rows have been read.
+/// Parquet and Arrow columns come with the types of their schema. Delimited files
+/// without a header row get numbered columns.
+pub fn column_headers(
+    path: &str,
+    format: &FileFormat,
+    dialect: &Dialect,
+) -> csv::Result<Vec<FileHeader>> {
+    match format {
+        FileFormat::Delimited => {
+            let (_, headers) = iterate_csv(path, dialect)?;
+            let names = if dialect.headers {
+                headers.iter().map(str::to_string).collect::<Vec<_>>()
+            } else {
+                (1..=headers.len()).map(|i| format!("column_{i}")).collect()
+            };
+            Ok(headers_from_names(&names))
+        }
+        FileFormat::JsonLines => {
+            File::open(path)?;
+            Ok(Vec::new())
+        }
+        FileFormat::FixedWidth(starts) => {
+            let bytes = read_head(path)?;
+            let text = dialect.decode(&bytes);
+            let first = text.lines().next().unwrap_or_default();
+            let names = split_fixed_width(first, starts)
+                .iter()
+                .map(str::to_string)
+                .collect::<Vec<_>>();
+            Ok(headers_from_names(&names))
+        }
+        FileFormat::Parquet | FileFormat::ArrowIpc => Ok(columnar::layout(path, format)?.headers),
+        FileFormat::Spreadsheet => {
+            let (path, sheet) = split_sheet(path);
+            let sheet = sheet.unwrap_or_default();
+            if !spreadsheet::sheet_names(path)?.iter().any(|s| s == sheet) {
+                return Err(std::io::Error::other(format!("no sheet named {sheet}")).into());
+            }
+            Ok(Vec::new())
+        }
+        FileFormat::Sqlite(query) => {
+            let (path, name) = split_sheet(path);
+            let source = sqlite::source(path, name.unwrap_or_default(), query.as_deref())?;
+            sqlite::column_headers(path, &source)
+        }
+    }
+}
+
+/// Read the rows of a file, stopping at byte `limit`. Returns the columns when
+/// reading is what determines them (JSON Lines, worksheets), the rows, and how
+/// many bytes were turned into rows.
+pub fn read_rows(
+    path: &str,
+    format: &FileFormat,
+    dialect: &Dialect,
+    limit: u64,
+) -> csv::Result<(Option<Vec<FileHeader>>, SheetVec, u64)> {
+    if *format == FileFormat::Delimited && dialect.encoding != UTF_8 {
+        // Positions in the decoded text aren't the file's, so it's read whole.
+        let (reader, _) = iterate_csv(path, dialect)?;
+        let rows = reader
+            .into_records()
+            .filter_map(|record| record.ok())
+            .collect();
+        return Ok((None, rows, std::fs::metadata(path)?.len()));
+    }
+    if *format == FileFormat::Delimited {
+        let (mut reader, _) = iterate_csv(path, dialect)?;
+        let mut end = reader.position().byte();
+        let mut rows = Vec::new();
+        let mut records = reader.records();
+        while let Some(record) = records.next() {
+            let position = records.reader().position().byte();
+            if position > limit {
+                break;
+            }
+            end = position;
+            if let Ok(record) = record {
+                rows.push(record);
+            }
+        }
+        return Ok((None, rows, end));
+    }
+    if format.columnar() {
+        let layout = columnar::layout(path, format)?;
+        let mut rows = Vec::new();
+        for group in 0..layout.group_rows.len() {
+            rows.extend(columnar::read_group(path, format, group)?);
+        }
+        return Ok((None, rows, std::fs::metadata(path)?.len()));
+    }
+    if let FileFormat::Sqlite(query) = format {
+        let (path, name) = split_sheet(path);
+        let source = sqlite::source(path, name.unwrap_or_default(), query.as_deref())?;
+        let rows = sqlite::read_source(path, &source)?;
+        return Ok((None, rows, std::fs::metadata(path)?.len()));
+    }
+    if *format == FileFormat::Spreadsheet {
+        let (path, sheet) = split_sheet(path);
+        let (headers, rows) = spreadsheet::read_sheet(path, sheet.unwrap_or_default())?;
+        return Ok((Some(headers), rows, std::fs::metadata(path)?.len()));
+    }
+
+    let mut bytes = Vec::new();
+    File::open(path)?.take(limit).read_to_end(&mut bytes)?;
+    let text = dialect.decode(&bytes);
+    let end = bytes.len() as u64;
+
+    match format {
+        FileFormat::JsonLines => {
+            let mut names = Vec::new();
+            let rows = json_rows(&text, &mut names, true);
+            Ok((Some(headers_from_names(&names)), rows, end))
+        }
+        FileFormat::FixedWidth(starts) => {
+            let rows = text
+                .lines()
+                .skip(1)
+                .filter(|l| !l.trim().is_empty())
+                .map(|l| split_fixed_width(l, starts))
+                .collect();
+            Ok((None, rows, end))
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// Rows appended to `path` after byte `offset`, and the offset to continue from.
+/// A trailing line without its newline is left for the next call, as the writer
+/// may still be in the middle of it. JSON keys that aren't among `names` are
+/// dropped, since the columns of a followed file stay fixed.
+pub fn read_appended(
+    path: &str,
+    offset: u64,
+    format: &FileFormat,
+    dialect: &Dialect,
+    names: &[String],
+) -> std::io::Result<(SheetVec, u64)> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+
+    let complete = bytes.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+    let bytes = &bytes[..complete];
+    let rows = match format {
+        FileFormat::Delimited => ReaderBuilder::new()
+            .has_headers(false)
+            .delimiter(dialect.delimiter)
+            .from_reader(dialect.decode(bytes).as_bytes())
+            .records()
+            .filter_map(|record| record.ok())
+            .collect(),
+        FileFormat::JsonLines => {
+            let mut names = names.to_vec();
+            json_rows(&dialect.decode(bytes), &mut names, false)
+        }
+        FileFormat::FixedWidth(starts) => dialect
+            .decode(bytes)
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| split_fixed_width(l, starts))
+            .collect(),
+        // Appending to these rewrites more than their end, so they're reloaded.
+        FileFormat::Parquet
+        | FileFormat::ArrowIpc
+        | FileFormat::Spreadsheet
+        | FileFormat::Sqlite(_) => Vec::new(),
+    };
+
+    Ok((rows, offset + complete as u64))
+}
+
+/// Parse JSON Lines into rows over `names`, adding a column for every new key
+/// when `add_columns` is set. Lines that aren't valid JSON are skipped.
+fn json_rows(text: &str, names: &mut Vec<String>, add_columns: bool) -> SheetVec {
+    let mut index = names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.clone(), i))
+        .collect::<HashMap<_, _>>();
+    let mut rows = Vec::new();
+    let mut invalid = 0;
+
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let Ok(value) = serde_json::from_str::<Value>(line) else {
+            invalid += 1;
+            continue;
+        };
+        let mut fields = Vec::new();
+        match &value {
+            Value::Object(_) => flatten_json("", &value, &mut fields),
+            _ => flatten_json("value", &value, &mut fields),
+        }
+
+        let mut row = vec![String::new(); names.len()];
+        for (key, text) in fields {
+            let col = match index.get(&key) {
+                Some(&col) => col,
+                None if add_columns => {
+                    names.push(key.clone());
+                    index.insert(key, names.len() - 1);
+                    row.push(String::new());
+                    names.len() - 1
+                }
+                None => continue,
+            };
+            row[col] = text;
+        }
+        rows.push(row);
+    }
+
+    if invalid > 0 {
+        eprintln!(
+            "JSON Lines: skipped {} line(s) that aren't valid JSON",
+            invalid
+        );
+    }
+    rows.into_iter()
+        .map(|mut row| {
+            row.resize(names.len(), String::new());
+            StringRecord::from(row)
+        })
+        .collect()
+}
+
+/// Nested objects become `parent.child` keys; arrays are kept as JSON text.
+fn flatten_json(key: &str, value: &Value, fields: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(map) if !map.is_empty() || key.is_empty() => {
+            for (child, value) in map {
+                let key = if key.is_empty() {
+                    child.clone()
+                } else {
+                    format!("{key}.{child}")
+                };
+                flatten_json(&key, value, fields);
+            }
+        }
+        Value::String(s) => fields.push((key.to_string(), s.clone())),
+        Value::Null => fields.push((key.to_string(), String::new())),
+        other => fields.push((key.to_string(), other.to_string())),
+    }
+}
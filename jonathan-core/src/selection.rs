@@ -0,0 +1,417 @@
+use std::collections::{BTreeSet, HashSet};
+
+#[derive(Default, Clone)]
+pub struct SelectionState {
+    pub selected_cells: HashSet<(u64, usize)>,
+    /// Whole rows selected from the row gutter
+    pub selected_rows: BTreeSet<u64>,
+    /// Whole (visible) columns selected from the column headers
+    pub selected_cols: BTreeSet<usize>,
+    /// Fixed corner for range operations (keyboard nav, shift+click, drag)
+    pub anchor_cell: Option<(u64, usize)>,
+    /// Movable corner of the selection rectangle
+    pub selection_end: Option<(u64, usize)>,
+    /// Cell where a drag-select started
+    pub drag_origin: Option<(u64, usize)>,
+    /// Bumped on every change of the selected set, so derived data (stats) can tell it is stale
+    pub generation: u64,
+}
+
+impl SelectionState {
+    /// The current movable corner: `selection_end` if set, otherwise `anchor_cell`.
+    pub fn cursor(&self) -> Option<(u64, usize)> {
+        self.selection_end.or(self.anchor_cell)
+    }
+
+    pub fn contains(&self, row: u64, col: usize) -> bool {
+        self.selected_cells.contains(&(row, col))
+            || self.selected_rows.contains(&row)
+            || self.selected_cols.contains(&col)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.selected_cells.is_empty()
+            && self.selected_rows.is_empty()
+            && self.selected_cols.is_empty()
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag_origin.is_some()
+    }
+
+    pub fn clear(&mut self) {
+        self.generation += 1;
+        self.selected_cells.clear();
+        self.selected_rows.clear();
+        self.selected_cols.clear();
+    }
+
+    /// Clear everything and select a single cell, resetting the anchor.
+    pub fn select_single(&mut self, row: u64, col: usize) {
+        self.clear();
+        self.selected_cells.insert((row, col));
+        self.anchor_cell = Some((row, col));
+        self.selection_end = None;
+    }
+
+    /// Toggle a cell in/out of the selection; updates anchor but keeps other cells.
+    pub fn toggle(&mut self, row: u64, col: usize) {
+        self.generation += 1;
+        if self.selected_cells.contains(&(row, col)) {
+            self.selected_cells.remove(&(row, col));
+        } else {
+            self.selected_cells.insert((row, col));
+        }
+        self.anchor_cell = Some((row, col));
+    }
+
+    /// Fill the rectangle from `anchor_cell` to `(row, col)` and update `selection_end`.
+    /// Falls back to `select_single` if there is no anchor yet.
+    pub fn extend_to(&mut self, row: u64, col: usize) {
+        if let Some((anchor_row, anchor_col)) = self.anchor_cell {
+            self.fill_rect(anchor_row, anchor_col, row, col);
+            self.selection_end = Some((row, col));
+        } else {
+            self.select_single(row, col);
+        }
+    }
+
+    /// Clear everything and select a whole row.
+    pub fn select_row(&mut self, row: u64) {
+        self.clear();
+        self.selected_rows.insert(row);
+        self.anchor_cell = Some((row, self.cursor().map_or(0, |(_, c)| c)));
+        self.selection_end = None;
+    }
+
+    /// Toggle a whole row in/out of the selection, keeping everything else.
+    pub fn toggle_row(&mut self, row: u64) {
+        self.generation += 1;
+        if !self.selected_rows.remove(&row) {
+            self.selected_rows.insert(row);
+        }
+        self.anchor_cell = Some((row, self.cursor().map_or(0, |(_, c)| c)));
+        self.selection_end = None;
+    }
+
+    /// Select all rows between the anchor row and `row`.
+    pub fn extend_rows_to(&mut self, row: u64) {
+        let Some((anchor_row, col)) = self.anchor_cell else {
+            self.select_row(row);
+            return;
+        };
+        self.clear();
+        self.selected_rows
+            .extend(anchor_row.min(row)..=anchor_row.max(row));
+        self.selection_end = Some((row, col));
+    }
+
+    /// Clear everything and select a whole column.
+    pub fn select_col(&mut self, col: usize) {
+        self.clear();
+        self.selected_cols.insert(col);
+        self.anchor_cell = Some((self.cursor().map_or(0, |(r, _)| r), col));
+        self.selection_end = None;
+    }
+
+    /// Toggle a whole column in/out of the selection, keeping everything else.
+    pub fn toggle_col(&mut self, col: usize) {
+        self.generation += 1;
+        if !self.selected_cols.remove(&col) {
+            self.selected_cols.insert(col);
+        }
+        self.anchor_cell = Some((self.cursor().map_or(0, |(r, _)| r), col));
+        self.selection_end = None;
+    }
+
+    /// Select all columns between the anchor column and `col`.
+    pub fn extend_cols_to(&mut self, col: usize) {
+        let Some((row, anchor_col)) = self.anchor_cell else {
+            self.select_col(col);
+            return;
+        };
+        self.clear();
+        self.selected_cols
+            .extend(anchor_col.min(col)..=anchor_col.max(col));
+        self.selection_end = Some((row, col));
+    }
+
+    /// Select every cell of a view with `num_cols` visible columns.
+    pub fn select_all(&mut self, num_cols: usize) {
+        self.clear();
+        self.selected_cols.extend(0..num_cols);
+        self.anchor_cell = Some((0, 0));
+        self.selection_end = None;
+    }
+
+    /// Clear everything and select the given whole rows.
+    pub fn select_rows(&mut self, rows: impl IntoIterator<Item = u64>) {
+        self.clear();
+        self.selected_rows.extend(rows);
+        self.anchor_cell = self.selected_rows.first().map(|&r| (r, 0));
+        self.selection_end = None;
+    }
+
+    /// Bounding rectangle `(min_row, max_row, min_col, max_col)` of the selection,
+    /// clamped to a view of `num_rows` x `num_cols`.
+    pub fn bounds(&self, num_rows: u64, num_cols: usize) -> Option<(u64, u64, usize, usize)> {
+        if num_rows == 0 || num_cols == 0 {
+            return None;
+        }
+        let rows = self
+            .selected_cells
+            .iter()
+            .map(|&(r, _)| r)
+            .chain(self.selected_rows.iter().copied())
+            .filter(|&r| r < num_rows);
+        let cols = self
+            .selected_cells
+            .iter()
+            .map(|&(_, c)| c)
+            .chain(self.selected_cols.iter().copied())
+            .filter(|&c| c < num_cols);
+
+        // Whole columns span every row, whole rows span every column.
+        let (min_row, max_row) = if self.selected_cols.iter().any(|&c| c < num_cols) {
+            (0, num_rows - 1)
+        } else {
+            (rows.clone().min()?, rows.max()?)
+        };
+        let (min_col, max_col) = if self.selected_rows.iter().any(|&r| r < num_rows) {
+            (0, num_cols - 1)
+        } else {
+            (cols.clone().min()?, cols.max()?)
+        };
+        Some((min_row, max_row, min_col, max_col))
+    }
+
+    /// Every selected cell of a `num_rows` x `num_cols` view, in row-major order.
+    pub fn cells(&self, num_rows: u64, num_cols: usize) -> impl Iterator<Item = (u64, usize)> + '_ {
+        self.bounds(num_rows, num_cols)
+            .into_iter()
+            .flat_map(move |(min_row, max_row, min_col, max_col)| {
+                (min_row..=max_row).flat_map(move |r| (min_col..=max_col).map(move |c| (r, c)))
+            })
+            .filter(|&(r, c)| self.contains(r, c))
+    }
+
+    /// Follow rows that moved because the view was rebuilt: `moved` gives the new
+    /// position of a row, or `None` when it's no longer shown and is dropped.
+    pub fn move_rows(&mut self, moved: impl Fn(u64) -> Option<u64>) {
+        self.generation += 1;
+        self.selected_cells = self
+            .selected_cells
+            .iter()
+            .filter_map(|&(row, col)| Some((moved(row)?, col)))
+            .collect();
+        self.selected_rows = self
+            .selected_rows
+            .iter()
+            .filter_map(|&row| moved(row))
+            .collect();
+        for corner in [
+            &mut self.anchor_cell,
+            &mut self.selection_end,
+            &mut self.drag_origin,
+        ] {
+            *corner = corner.and_then(|(row, col)| Some((moved(row)?, col)));
+        }
+    }
+
+    pub fn start_drag(&mut self, row: u64, col: usize) {
+        self.drag_origin = Some((row, col));
+        self.anchor_cell = Some((row, col));
+        self.selection_end = None;
+        self.clear();
+        self.selected_cells.insert((row, col));
+    }
+
+    /// Extend the drag rectangle from `drag_origin` to `(row, col)`.
+    pub fn update_drag(&mut self, row: u64, col: usize) {
+        if let Some((origin_row, origin_col)) = self.drag_origin {
+            self.fill_rect(origin_row, origin_col, row, col);
+            self.selection_end = Some((row, col));
+        }
+    }
+
+    pub fn end_drag(&mut self) {
+        self.drag_origin = None;
+    }
+
+    fn fill_rect(&mut self, r1: u64, c1: usize, r2: u64, c2: usize) {
+        let row_min = r1.min(r2);
+        let row_max = r1.max(r2);
+        let col_min = c1.min(c2);
+        let col_max = c1.max(c2);
+        let cells = (row_min..=row_max)
+            .flat_map(|r| (col_min..=col_max).map(move |c| (r, c)))
+            .collect::<HashSet<_>>();
+        // A drag fills the same rectangle every frame until the pointer moves on.
+        if cells == self.selected_cells
+            && self.selected_rows.is_empty()
+            && self.selected_cols.is_empty()
+        {
+            return;
+        }
+        self.clear();
+        self.selected_cells = cells;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(selection: &SelectionState, num_rows: u64, num_cols: usize) -> Vec<(u64, usize)> {
+        selection.cells(num_rows, num_cols).collect()
+    }
+
+    #[test]
+    fn select_single_replaces_the_selection() {
+        let mut selection = SelectionState::default();
+        selection.select_row(4);
+        selection.select_single(1, 2);
+        assert_eq!(cells(&selection, 10, 10), [(1, 2)]);
+        assert_eq!(selection.cursor(), Some((1, 2)));
+    }
+
+    #[test]
+    fn toggle_adds_and_removes_one_cell() {
+        let mut selection = SelectionState::default();
+        selection.select_single(0, 0);
+        selection.toggle(2, 1);
+        assert_eq!(cells(&selection, 10, 10), [(0, 0), (2, 1)]);
+        selection.toggle(2, 1);
+        assert_eq!(cells(&selection, 10, 10), [(0, 0)]);
+        assert_eq!(selection.anchor_cell, Some((2, 1)));
+    }
+
+    #[test]
+    fn extend_to_fills_the_rectangle_from_the_anchor() {
+        let mut selection = SelectionState::default();
+        selection.select_single(3, 2);
+        selection.extend_to(1, 0);
+        assert_eq!(selection.bounds(10, 10), Some((1, 3, 0, 2)));
+        assert_eq!(cells(&selection, 10, 10).len(), 9);
+        assert_eq!(selection.cursor(), Some((1, 0)));
+        // Extending again starts from the same anchor, not from the last corner.
+        selection.extend_to(3, 3);
+        assert_eq!(cells(&selection, 10, 10), [(3, 2), (3, 3)]);
+    }
+
+    #[test]
+    fn extend_to_without_anchor_selects_one_cell() {
+        let mut selection = SelectionState::default();
+        selection.extend_to(5, 5);
+        assert_eq!(cells(&selection, 10, 10), [(5, 5)]);
+        assert_eq!(selection.anchor_cell, Some((5, 5)));
+    }
+
+    #[test]
+    fn whole_rows_and_columns_span_the_view() {
+        let mut selection = SelectionState::default();
+        selection.select_row(2);
+        assert_eq!(selection.bounds(5, 3), Some((2, 2, 0, 2)));
+        assert_eq!(cells(&selection, 5, 3), [(2, 0), (2, 1), (2, 2)]);
+
+        selection.select_col(1);
+        assert_eq!(selection.bounds(3, 4), Some((0, 2, 1, 1)));
+        assert!(selection.contains(100, 1));
+    }
+
+    #[test]
+    fn extend_rows_and_columns_from_the_anchor() {
+        let mut selection = SelectionState::default();
+        selection.select_single(4, 1);
+        selection.extend_rows_to(2);
+        assert_eq!(selection.selected_rows, [2, 3, 4].into());
+        selection.select_single(0, 3);
+        selection.extend_cols_to(1);
+        assert_eq!(selection.selected_cols, [1, 2, 3].into());
+    }
+
+    #[test]
+    fn bounds_and_cells_are_clamped_to_the_view() {
+        let mut selection = SelectionState::default();
+        selection.select_single(1, 1);
+        selection.extend_to(8, 8);
+        assert_eq!(selection.bounds(3, 2), Some((1, 2, 1, 1)));
+        assert_eq!(cells(&selection, 3, 2), [(1, 1), (2, 1)]);
+        assert_eq!(selection.bounds(0, 2), None);
+
+        selection.select_single(7, 7);
+        assert_eq!(selection.bounds(3, 3), None);
+        assert!(cells(&selection, 3, 3).is_empty());
+    }
+
+    #[test]
+    fn select_all_covers_every_cell() {
+        let mut selection = SelectionState::default();
+        selection.select_all(2);
+        assert_eq!(cells(&selection, 2, 2), [(0, 0), (0, 1), (1, 0), (1, 1)]);
+    }
+
+    #[test]
+    fn drag_fills_from_where_it_started() {
+        let mut selection = SelectionState::default();
+        selection.start_drag(2, 2);
+        assert!(selection.is_dragging());
+        selection.update_drag(0, 1);
+        selection.update_drag(3, 2);
+        selection.end_drag();
+        assert!(!selection.is_dragging());
+        assert_eq!(cells(&selection, 10, 10), [(2, 2), (3, 2)]);
+        // Moving after the drag ended changes nothing.
+        selection.update_drag(9, 9);
+        assert_eq!(cells(&selection, 10, 10).len(), 2);
+    }
+
+    #[test]
+    fn dragging_over_the_same_rectangle_keeps_the_generation() {
+        let mut selection = SelectionState::default();
+        selection.start_drag(0, 0);
+        selection.update_drag(2, 2);
+        let generation = selection.generation;
+        selection.update_drag(2, 2);
+        assert_eq!(selection.generation, generation);
+        selection.update_drag(2, 3);
+        assert!(selection.generation > generation);
+    }
+
+    #[test]
+    fn move_rows_follows_and_drops_rows() {
+        let mut selection = SelectionState::default();
+        selection.select_rows([1, 2]);
+        selection.toggle(3, 1);
+        selection.toggle_col(4);
+        // Row 1 is now at 5, row 3 at 0 and row 2 is gone.
+        selection.move_rows(|row| match row {
+            1 => Some(5),
+            3 => Some(0),
+            _ => None,
+        });
+        assert_eq!(selection.selected_rows, [5].into());
+        assert_eq!(selection.selected_cells, [(0, 1)].into());
+        assert_eq!(selection.selected_cols, [4].into());
+        assert_eq!(selection.anchor_cell, Some((0, 4)));
+    }
+
+    #[test]
+    fn changes_bump_the_generation() {
+        let mut selection = SelectionState::default();
+        let mut last = selection.generation;
+        for change in [
+            |s: &mut SelectionState| s.select_single(0, 0),
+            |s: &mut SelectionState| s.toggle(1, 1),
+            |s: &mut SelectionState| s.extend_to(2, 2),
+            |s: &mut SelectionState| s.toggle_row(3),
+            |s: &mut SelectionState| s.toggle_col(3),
+            |s: &mut SelectionState| s.move_rows(Some),
+            |s: &mut SelectionState| s.clear(),
+        ] {
+            change(&mut selection);
+            assert!(selection.generation > last);
+            last = selection.generation;
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use calamine::{Data, Reader, open_workbook_auto};
+use csv::StringRecord;
+use std::io;
+
+use crate::profile::ColumnType;
+use crate::types::{FileHeader, SheetVec};
+
+/// Worksheets of an Excel or OpenDocument workbook, in workbook order.
+pub fn sheet_names(path: &str) -> csv::Result<Vec<String>> {
+    let workbook = open_workbook_auto(path).map_err(io::Error::other)?;
+    Ok(workbook.sheet_names())
+}
+
+/// Read a worksheet. The first row holds the column names; a column gets the
+/// type its cells are stored as when they all agree.
+pub fn read_sheet(path: &str, sheet: &str) -> csv::Result<(Vec<FileHeader>, SheetVec)> {
+    let mut workbook = open_workbook_auto(path).map_err(io::Error::other)?;
+    let range = workbook.worksheet_range(sheet).map_err(io::Error::other)?;
+    let mut rows = range.rows();
+
+    let mut headers = rows
+        .next()
+        .unwrap_or_default()
+        .iter()
+        .enumerate()
+        .map(|(i, cell)| FileHeader {
+            name: match cell_text(cell) {
+                name if name.is_empty() => format!("column_{}", i + 1),
+                name => name,
+            },
+            visible: true,
+            ..FileHeader::default()
+        })
+        .collect::<Vec<_>>();
+    let mut kinds = vec![ColumnType::Empty; headers.len()];
+
+    let data = rows
+        .map(|row| {
+            for (kind, cell) in kinds.iter_mut().zip(row) {
+                *kind = merge_type(*kind, cell_type(cell));
+            }
+            row.iter().map(cell_text).collect::<StringRecord>()
+        })
+        .collect();
+
+    for (header, kind) in headers.iter_mut().zip(kinds) {
+        header.kind = Some(kind);
+    }
+    Ok((headers, data))
+}
+
+/// Dates without a time of day are written as just the date.
+fn cell_text(cell: &Data) -> String {
+    match cell {
+        Data::Empty => String::new(),
+        Data::DateTime(dt) if !dt.is_duration() => match dt.as_datetime() {
+            Some(dt) if dt.time() == chrono::NaiveTime::MIN => dt.format("%Y-%m-%d").to_string(),
+            Some(dt) => dt.format("%Y-%m-%d %H:%M:%S").to_string(),
+            None => cell.to_string(),
+        },
+        _ => cell.to_string(),
+    }
+}
+
+fn cell_type(cell: &Data) -> ColumnType {
+    match cell {
+        Data::Empty => ColumnType::Empty,
+        Data::Int(_) => ColumnType::Integer,
+        // Spreadsheets store every number as a float
+        Data::Float(f) if f.fract() == 0.0 && f.abs() < 1e15 => ColumnType::Integer,
+        Data::Float(_) => ColumnType::Float,
+        Data::Bool(_) => ColumnType::Boolean,
+        Data::DateTime(dt) if !dt.is_duration() => ColumnType::Date,
+        Data::DateTimeIso(_) => ColumnType::Date,
+        _ => ColumnType::Text,
+    }
+}
+
+fn merge_type(a: ColumnType, b: ColumnType) -> ColumnType {
+    match (a, b) {
+        (ColumnType::Empty, kind) | (kind, ColumnType::Empty) => kind,
+        (a, b) if a == b => a,
+        (ColumnType::Integer, ColumnType::Float) | (ColumnType::Float, ColumnType::Integer) => {
+            ColumnType::Float
+        }
+        _ => ColumnType::Text,
+    }
+}
@@ -0,0 +1,278 @@
+use csv::StringRecord;
+use rusqlite::functions::FunctionFlags;
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OpenFlags, params_from_iter};
+use std::io;
+
+use crate::formats::sql_ident;
+use crate::profile::ColumnType;
+use crate::types::{ColumnId, Facets, FileHeader, SheetVec, SortOrder};
+
+/// Rows of a database file: a table or view by name, or a custom `SELECT`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SqlSource {
+    pub select: String,
+    /// Table whose rows can be updated, when the first column is its rowid
+    pub table: Option<String>,
+    pub columns: Vec<String>,
+}
+
+/// A cell edit waiting to be saved: rowid, column, new value.
+pub type SqlEdit = (i64, ColumnId, String);
+
+fn open(path: &str, flags: OpenFlags) -> csv::Result<Connection> {
+    let conn = Connection::open_with_flags(path, flags | OpenFlags::SQLITE_OPEN_NO_MUTEX)
+        .map_err(io::Error::other)?;
+    Ok(conn)
+}
+
+fn read_only(path: &str) -> csv::Result<Connection> {
+    open(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+}
+
+/// Tables and views of a database, tables first.
+pub fn table_names(path: &str) -> csv::Result<Vec<String>> {
+    let conn = read_only(path)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT name FROM sqlite_schema WHERE type IN ('table', 'view') \
+             AND name NOT LIKE 'sqlite_%' ORDER BY type, name",
+        )
+        .map_err(io::Error::other)?;
+    let names = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(io::Error::other)?;
+    Ok(names)
+}
+
+/// What to run for the table or view `name`, or for `query` when given. Tables
+/// are read with their rowid up front, so edits can be written back.
+pub fn source(path: &str, name: &str, query: Option<&str>) -> csv::Result<SqlSource> {
+    let conn = read_only(path)?;
+    let (select, table) = match query {
+        Some(query) => (query.trim().trim_end_matches(';').to_string(), None),
+        None => {
+            let with_rowid = format!("SELECT rowid, * FROM {}", sql_ident(name));
+            // Views and WITHOUT ROWID tables have no rowid to select.
+            if conn.prepare(&with_rowid).is_ok() {
+                (with_rowid, Some(name.to_string()))
+            } else {
+                (format!("SELECT * FROM {}", sql_ident(name)), None)
+            }
+        }
+    };
+    let stmt = conn.prepare(&select).map_err(io::Error::other)?;
+    let columns = stmt.column_names().iter().map(|c| c.to_string()).collect();
+    Ok(SqlSource {
+        select,
+        table,
+        columns,
+    })
+}
+
+/// Columns of `source`, typed by their declared types. The rowid column of a
+/// table is hidden.
+pub fn column_headers(path: &str, source: &SqlSource) -> csv::Result<Vec<FileHeader>> {
+    let conn = read_only(path)?;
+    let stmt = conn.prepare(&source.select).map_err(io::Error::other)?;
+    Ok(stmt
+        .columns()
+        .iter()
+        .enumerate()
+        .map(|(i, column)| FileHeader {
+            name: column.name().to_string(),
+            visible: !(i == 0 && source.table.is_some()),
+            sort: None,
+            kind: column.decl_type().map(declared_type),
+        })
+        .collect())
+}
+
+pub fn read_source(path: &str, source: &SqlSource) -> csv::Result<SheetVec> {
+    let conn = read_only(path)?;
+    let mut stmt = conn.prepare(&source.select).map_err(io::Error::other)?;
+    query_rows(&mut stmt, &[])
+}
+
+/// Rows of `source` that contain `filter` in some column and match `facets`,
+/// sorted by SQLite (so numbers sort as numbers). Cells are compared as they
+/// are shown, so the rows are those the in-memory filter would keep.
+pub fn query_view(
+    path: &str,
+    source: &SqlSource,
+    filter: &str,
+    facets: &Facets,
+    sort: Option<(ColumnId, SortOrder)>,
+) -> csv::Result<SheetVec> {
+    let conn = read_only(path)?;
+    conn.create_scalar_function(
+        "cell_text",
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| Ok(cell_text(ctx.get_raw(0))),
+    )
+    .map_err(io::Error::other)?;
+    // Positional names, so the conditions don't depend on what the columns are called.
+    let names = (0..source.columns.len())
+        .map(|i| format!("c{i}"))
+        .collect::<Vec<_>>();
+    let text = |col: usize| format!("cell_text(c{col})");
+    let mut conditions = Vec::new();
+    let mut params = Vec::new();
+
+    if !filter.is_empty() && !names.is_empty() {
+        params.push(filter.to_string());
+        let any = (0..names.len())
+            .map(|col| format!("instr({}, ?1) > 0", text(col)))
+            .collect::<Vec<_>>();
+        conditions.push(format!("({})", any.join(" OR ")));
+    }
+    for (col, values) in facets
+        .iter()
+        .filter(|(col, v)| !v.is_empty() && **col < names.len())
+    {
+        let placeholders = values
+            .iter()
+            .map(|value| {
+                params.push(value.clone());
+                format!("?{}", params.len())
+            })
+            .collect::<Vec<_>>();
+        conditions.push(format!("{} IN ({})", text(*col), placeholders.join(", ")));
+    }
+
+    let mut sql = format!(
+        "WITH v({}) AS ({}) SELECT * FROM v",
+        names.join(", "),
+        source.select
+    );
+    if !conditions.is_empty() {
+        sql += &format!(" WHERE {}", conditions.join(" AND "));
+    }
+    if let Some((col, order)) = sort.filter(|(col, _)| *col < names.len()) {
+        let direction = if order == SortOrder::Asc {
+            "ASC"
+        } else {
+            "DESC"
+        };
+        sql += &format!(" ORDER BY c{col} {direction}");
+    }
+
+    let mut stmt = conn.prepare(&sql).map_err(io::Error::other)?;
+    query_rows(&mut stmt, &params)
+}
+
+fn query_rows(stmt: &mut rusqlite::Statement, params: &[String]) -> csv::Result<SheetVec> {
+    let count = stmt.column_count();
+    let mut rows = stmt
+        .query(params_from_iter(params))
+        .map_err(io::Error::other)?;
+    let mut data = Vec::new();
+    while let Some(row) = rows.next().map_err(io::Error::other)? {
+        let record = (0..count)
+            .map(|i| row.get_ref(i).map(cell_text).unwrap_or_default())
+            .collect::<StringRecord>();
+        data.push(record);
+    }
+    Ok(data)
+}
+
+fn cell_text(value: ValueRef) -> String {
+    match value {
+        ValueRef::Null => String::new(),
+        ValueRef::Integer(i) => i.to_string(),
+        ValueRef::Real(f) => format!("{f:?}"),
+        ValueRef::Text(text) => String::from_utf8_lossy(text).into_owned(),
+        ValueRef::Blob(blob) => format!("[{} bytes]", blob.len()),
+    }
+}
+
+/// SQLite's type affinity rules, plus the usual names for booleans and dates.
+fn declared_type(declared: &str) -> ColumnType {
+    let declared = declared.to_ascii_uppercase();
+    if declared.contains("INT") {
+        ColumnType::Integer
+    } else if ["CHAR", "CLOB", "TEXT"]
+        .iter()
+        .any(|t| declared.contains(t))
+    {
+        ColumnType::Text
+    } else if declared.contains("BOOL") {
+        ColumnType::Boolean
+    } else if declared.contains("DATE") || declared.contains("TIME") {
+        ColumnType::Date
+    } else if ["REAL", "FLOA", "DOUB", "NUMERIC", "DECIMAL"]
+        .iter()
+        .any(|t| declared.contains(t))
+    {
+        ColumnType::Float
+    } else {
+        ColumnType::Text
+    }
+}
+
+/// Write cell edits of a table as `UPDATE`s keyed by rowid, in one transaction.
+/// Empty cells become NULL. With no edits there is nothing to do, whatever the
+/// source.
+pub fn write_edits(path: &str, source: &SqlSource, edits: &[SqlEdit]) -> csv::Result<()> {
+    if edits.is_empty() {
+        return Ok(());
+    }
+    let Some(table) = &source.table else {
+        return Err(io::Error::other("only tables can be updated").into());
+    };
+    let mut conn = open(path, OpenFlags::SQLITE_OPEN_READ_WRITE)?;
+    let tx = conn.transaction().map_err(io::Error::other)?;
+    for (rowid, col, value) in edits {
+        let Some(column) = source.columns.get(*col).filter(|_| *col > 0) else {
+            continue;
+        };
+        let sql = format!(
+            "UPDATE {} SET {} = ?1 WHERE rowid = ?2",
+            sql_ident(table),
+            sql_ident(column)
+        );
+        let value = (!value.is_empty()).then_some(value.as_str());
+        tx.execute(&sql, rusqlite::params![value, rowid])
+            .map_err(io::Error::other)?;
+    }
+    tx.commit().map_err(io::Error::other)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::filter_data;
+
+    #[test]
+    fn sql_filter_keeps_the_rows_the_in_memory_filter_keeps() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("shop.db");
+        let path = path.to_str().unwrap();
+        Connection::open(path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE items (name TEXT, price REAL, stock INTEGER, photo BLOB);
+                 INSERT INTO items VALUES ('apple', 0.1 + 0.2, 7, NULL),
+                     ('pear', 2.0, 12, x'0102'), ('plum', NULL, 3, NULL);",
+            )
+            .unwrap();
+        let source = source(path, "items", None).unwrap();
+        let all = read_source(path, &source).unwrap();
+
+        let price = Facets::from([(2, ["2.0".to_string()].into())]);
+        // "1" is only in the rowid of the first row, "0000" only in how the
+        // price of apples is shown.
+        for filter in ["", "p", "1", "3", "0000", "2.0", "bytes", "apple"] {
+            for facets in [Facets::new(), price.clone()] {
+                assert_eq!(
+                    query_view(path, &source, filter, &facets, None).unwrap(),
+                    filter_data(all.clone(), filter.to_string(), &facets),
+                    "filter {filter:?}, facets {facets:?}"
+                );
+            }
+        }
+    }
+}
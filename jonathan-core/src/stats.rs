@@ -0,0 +1,77 @@
+use std::collections::HashSet;
+
+use crate::selection::SelectionState;
+use crate::types::SheetVec;
+
+/// Selections with more cells than this are aggregated on a worker thread.
+pub const SYNC_STATS_LIMIT: u64 = 20_000;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SelectionStats {
+    pub count: usize,
+    pub non_empty: usize,
+    /// Cells that parse as a number; sum/min/max only cover these
+    pub numeric: usize,
+    pub sum: f64,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub distinct: usize,
+}
+
+impl SelectionStats {
+    pub fn average(&self) -> Option<f64> {
+        (self.numeric > 0).then(|| self.sum / self.numeric as f64)
+    }
+}
+
+pub fn parse_number(value: &str) -> Option<f64> {
+    value.trim().parse::<f64>().ok().filter(|n| n.is_finite())
+}
+
+pub fn aggregate<'a>(values: impl Iterator<Item = &'a str>) -> SelectionStats {
+    let mut stats = SelectionStats::default();
+    let mut distinct = HashSet::new();
+
+    for value in values {
+        stats.count += 1;
+        if !value.is_empty() {
+            stats.non_empty += 1;
+        }
+        if let Some(n) = parse_number(value) {
+            stats.numeric += 1;
+            stats.sum += n;
+            stats.min = Some(stats.min.map_or(n, |m| m.min(n)));
+            stats.max = Some(stats.max.map_or(n, |m| m.max(n)));
+        }
+        distinct.insert(value);
+    }
+
+    stats.distinct = distinct.len();
+    stats
+}
+
+/// Aggregate the selected cells of a view. Selection columns are visible column
+/// indices, mapped to data columns through `visible_col_indices`.
+pub fn selection_stats(
+    data: &SheetVec,
+    selection: &SelectionState,
+    visible_col_indices: &[usize],
+) -> SelectionStats {
+    let values = selection
+        .cells(data.len() as u64, visible_col_indices.len())
+        .map(|(r, c)| {
+            data.get(r as usize)
+                .and_then(|row| row.get(visible_col_indices[c]))
+                .unwrap_or("")
+        });
+    aggregate(values)
+}
+
+/// Short human form for the status bar: integers without decimals, others to 4 places.
+pub fn format_number(n: f64) -> String {
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        format!("{}", n as i64)
+    } else {
+        format!("{:.4}", n)
+    }
+}
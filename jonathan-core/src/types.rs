@@ -0,0 +1,50 @@
+use csv::StringRecord;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::profile::ColumnType;
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct FileHeader {
+    pub name: String,
+    pub visible: bool,
+    pub sort: Option<SortOrder>,
+    /// Value type the file declares (Parquet, Arrow and SQLite columns)
+    #[serde(skip)]
+    pub kind: Option<ColumnType>,
+}
+
+pub type ColumnId = usize;
+pub type Filter = String;
+pub type Filename = String;
+/// Exact-match value sets per column; a row passes when every listed column holds one of its values.
+pub type Facets = BTreeMap<ColumnId, BTreeSet<String>>;
+/// Per low-cardinality column: every value with the number of rows it would match.
+pub type FacetCounts = Vec<(ColumnId, Vec<(String, usize)>)>;
+
+pub type SheetVec = Vec<StringRecord>;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum SortOrder {
+    Asc,
+    Dsc,
+}
+
+/// Data column index for each visible column, in display order.
+pub fn visible_col_indices(headers: &[FileHeader]) -> Vec<usize> {
+    headers
+        .iter()
+        .enumerate()
+        .filter(|(_, h)| h.visible)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Column named `name`, or failing that named so in another case.
+pub fn column_named(headers: &[FileHeader], name: &str) -> Option<ColumnId> {
+    headers.iter().position(|h| h.name == name).or_else(|| {
+        headers
+            .iter()
+            .position(|h| h.name.eq_ignore_ascii_case(name))
+    })
+}
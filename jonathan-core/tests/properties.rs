@@ -0,0 +1,194 @@
+//! Properties of reading, writing, filtering, sorting and selecting that must
+//! hold for any table, checked on generated ones.
+
+use csv::StringRecord;
+use jonathan_core::data::{csv_quote, edit_record, filter_data, matches_text, sort_data};
+use jonathan_core::read_csv::Dialect;
+use jonathan_core::readers::{FileFormat, read_rows};
+use jonathan_core::selection::SelectionState;
+use jonathan_core::{Facets, FileHeader, SheetVec, SortOrder};
+use proptest::prelude::*;
+
+/// Cell text with the characters that need quoting, plus some beyond ASCII.
+fn cell() -> impl Strategy<Value = String> {
+    "[a-c1-3 ,;|\t\"'\r\n#é漢🙂]{0,6}"
+}
+
+/// Rows of the same width, with at least one column.
+fn table() -> impl Strategy<Value = (Vec<String>, SheetVec)> {
+    (1..5usize).prop_flat_map(|width| {
+        (
+            prop::collection::vec(cell(), width),
+            prop::collection::vec(prop::collection::vec(cell(), width), 0..8),
+        )
+            .prop_map(|(headers, rows)| {
+                let rows = rows
+                    .into_iter()
+                    // A lone empty field is written as an empty line, which
+                    // reads back as no row at all.
+                    .filter(|row| row.len() > 1 || !row[0].is_empty())
+                    .map(StringRecord::from)
+                    .collect();
+                (headers, rows)
+            })
+    })
+}
+
+fn dialect() -> impl Strategy<Value = Dialect> {
+    (
+        prop::sample::select(vec![b',', b';', b'\t', b'|']),
+        any::<bool>(),
+        any::<bool>(),
+        any::<bool>(),
+        any::<bool>(),
+    )
+        .prop_map(|(delimiter, quote_all, crlf, bom, headers)| Dialect {
+            delimiter,
+            quote_all,
+            crlf,
+            bom,
+            headers,
+            ..Dialect::default()
+        })
+}
+
+fn file_headers(names: &[String]) -> Vec<FileHeader> {
+    names
+        .iter()
+        .map(|name| FileHeader {
+            name: name.clone(),
+            visible: true,
+            ..FileHeader::default()
+        })
+        .collect()
+}
+
+fn column(rows: &SheetVec, col: usize) -> Vec<&str> {
+    rows.iter()
+        .map(|r| r.get(col).unwrap_or_default())
+        .collect()
+}
+
+proptest! {
+    #[test]
+    fn written_rows_read_back_the_same((headers, rows) in table(), dialect in dialect()) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("t.csv");
+        let path = path.to_str().unwrap();
+        // A header row that is one empty name reads back as no row either.
+        let keep_header = !dialect.headers || headers.len() > 1 || !headers[0].is_empty();
+        prop_assume!(keep_header);
+
+        jonathan_core::data::write_csv(path, &file_headers(&headers), &rows, &dialect).unwrap();
+        let (_, read, _) = read_rows(path, &FileFormat::Delimited, &dialect, u64::MAX).unwrap();
+        prop_assert_eq!(read, rows);
+    }
+
+    #[test]
+    fn csv_quote_reads_back_as_one_field(value in cell()) {
+        let line = format!("{},next\n", csv_quote(&value));
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(line.as_bytes());
+        let record = reader.records().next().unwrap().unwrap();
+        prop_assert_eq!(&record[0], value.as_str());
+        prop_assert_eq!(&record[1], "next");
+    }
+
+    #[test]
+    fn sort_orders_a_permutation((_, rows) in table(), col in 0..5usize) {
+        let sorted = sort_data(rows.clone(), (col, SortOrder::Asc));
+        let values = column(&sorted, col);
+        prop_assert!(values.windows(2).all(|w| w[0] <= w[1]));
+
+        let mut before = rows.iter().map(|r| r.iter().collect::<Vec<_>>()).collect::<Vec<_>>();
+        let mut after = sorted.iter().map(|r| r.iter().collect::<Vec<_>>()).collect::<Vec<_>>();
+        before.sort();
+        after.sort();
+        prop_assert_eq!(before, after);
+
+        // Sorting is stable, so sorting again changes nothing.
+        prop_assert_eq!(sort_data(sorted.clone(), (col, SortOrder::Asc)), sorted.clone());
+
+        let descending = sort_data(rows, (col, SortOrder::Dsc));
+        let mut reversed = column(&descending, col);
+        reversed.reverse();
+        prop_assert_eq!(reversed, values);
+    }
+
+    #[test]
+    fn filter_keeps_matching_rows_in_order((_, rows) in table(), filter in "[a-c1-3]{0,2}") {
+        let kept = filter_data(rows.clone(), filter.clone(), &Facets::new());
+        let expected = rows
+            .iter()
+            .filter(|r| r.iter().any(|c| c.contains(filter.as_str())))
+            .cloned()
+            .collect::<SheetVec>();
+        prop_assert!(kept.iter().all(|r| matches_text(r, &filter)));
+        prop_assert_eq!(kept, expected);
+    }
+
+    #[test]
+    fn facets_keep_rows_with_a_checked_value(
+        (_, rows) in table(),
+        col in 0..4usize,
+        checked in prop::collection::btree_set(cell(), 1..3),
+    ) {
+        let facets = Facets::from([(col, checked.iter().cloned().collect())]);
+        let kept = filter_data(rows.clone(), String::new(), &facets);
+        let expected = rows
+            .iter()
+            .filter(|r| checked.contains(r.get(col).unwrap_or_default()))
+            .count();
+        prop_assert_eq!(kept.len(), expected);
+    }
+
+    #[test]
+    fn edit_changes_one_cell((_, mut rows) in table(), row in 0..8usize, col in 0..5usize, value in cell()) {
+        let before = rows.clone();
+        let exists = before.get(row).is_some_and(|r| col < r.len());
+        prop_assert_eq!(edit_record(&mut rows, row, col, &value), exists);
+        for (r, (old, new)) in before.iter().zip(&rows).enumerate() {
+            for (c, (old, new)) in old.iter().zip(new).enumerate() {
+                if exists && (r, c) == (row, col) {
+                    prop_assert_eq!(new, value.as_str());
+                } else {
+                    prop_assert_eq!(new, old);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn extend_to_selects_the_rectangle(
+        anchor in (0..20u64, 0..20usize),
+        end in (0..20u64, 0..20usize),
+    ) {
+        let mut selection = SelectionState::default();
+        selection.select_single(anchor.0, anchor.1);
+        selection.extend_to(end.0, end.1);
+        let (rows, cols) = (anchor.0.min(end.0)..=anchor.0.max(end.0), anchor.1.min(end.1)..=anchor.1.max(end.1));
+        for r in 0..20 {
+            for c in 0..20 {
+                prop_assert_eq!(selection.contains(r, c), rows.contains(&r) && cols.contains(&c));
+            }
+        }
+        prop_assert_eq!(
+            selection.bounds(20, 20),
+            Some((*rows.start(), *rows.end(), *cols.start(), *cols.end()))
+        );
+    }
+
+    #[test]
+    fn toggling_twice_restores_the_selection(
+        start in (0..10u64, 0..10usize),
+        cell in (0..10u64, 0..10usize),
+    ) {
+        let mut selection = SelectionState::default();
+        selection.select_single(start.0, start.1);
+        let before = selection.selected_cells.clone();
+        selection.toggle(cell.0, cell.1);
+        selection.toggle(cell.0, cell.1);
+        prop_assert_eq!(selection.selected_cells, before);
+    }
+}
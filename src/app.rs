@@ -1,145 +1,1894 @@
-use egui::Key;
+use csv::StringRecord;
 use egui_dock::{DockArea, Style};
-use std::path::PathBuf;
+use jonathan_core::columnar::{layout, read_group, write_parquet};
+use jonathan_core::data::{
+    backup_file, edit_record, extend_view, facet_counts, rows_at, sort_row_numbers,
+    view_row_numbers, write_csv,
+};
+use jonathan_core::export::{ExportSpec, export};
+use jonathan_core::pivot::{PivotSource, PivotSpec, pivot};
+use jonathan_core::profile::profile_column;
+use jonathan_core::read_csv::{Dialect, FileStamp, sniff_dialect};
+use jonathan_core::readers::{
+    FileFormat, column_headers, detect_format, read_appended, read_rows, sheet_file, split_sheet,
+};
+use jonathan_core::spreadsheet::sheet_names;
+use jonathan_core::sqlite::{self, query_view, table_names};
+use jonathan_core::stats::{SYNC_STATS_LIMIT, selection_stats};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::thread;
 
-use crate::data::{edit_record, filter_data, sort_data, write_csv};
-use crate::menu::OPEN_FILE_ID;
-use crate::read_csv::open_csv_file;
-use crate::types::{CsvTabViewer, MyApp, SheetTab, SortOrder, UiMessage, active_sheet_data};
+use crate::chart::{ChartTab, chart_data};
+use crate::commands::Action;
+use crate::menu::{self, OPEN_FILE_ID};
+use crate::recent::RECENT_FILES_KEY;
+use crate::session::SESSION_KEY;
+use crate::types::{
+    ClosePrompt, CsvTabViewer, Facets, FileHeader, Filename, LazyFile, MyApp, SheetTab, SheetVec,
+    SortOrder, UiMessage, active_sheet_data, filter_active, visible_col_indices,
+};
+use crate::ui::close_prompt::{self, CloseChoice};
+use crate::ui::command_palette::{self, CommandPalette};
 use crate::ui::drop::preview_files_being_dropped;
+use crate::ui::overwrite_prompt;
+use crate::ui::sheet_picker::{self, SheetPicker};
+use crate::ui::status_bar::{self, StatusInfo};
 
 #[cfg(target_os = "macos")]
 use muda::MenuEvent;
 
 impl MyApp {
     pub fn load_file(&mut self, ctx: &egui::Context, file_name: String, tab_id: Option<usize>) {
+        let format = self
+            .file_formats
+            .get(&file_name)
+            .cloned()
+            .unwrap_or_else(|| detect_format(&file_name));
+        if matches!(format, FileFormat::Spreadsheet | FileFormat::Sqlite(None))
+            && split_sheet(&file_name).1.is_none()
+        {
+            let database = format != FileFormat::Spreadsheet;
+            self.pick_sheets(ctx, file_name, tab_id, database);
+            return;
+        }
+        let dialect = self.file_dialect(&file_name);
+        let headers = match column_headers(&file_name, &format, &dialect) {
+            Ok(headers) => headers,
+            Err(e) => {
+                eprintln!("Failed to open {}: {:?}", file_name, e);
+                let short_name = file_name.split('/').next_back().unwrap_or(&file_name);
+                crate::toast::show(ctx, format!("Could not open {short_name}"));
+                return;
+            }
+        };
         self.picked_path = Some(file_name.clone());
 
-        self.files_list.push(file_name.clone());
+        if !self.files_list.contains(&file_name) {
+            self.files_list.push(file_name.clone());
+            self.watcher.watch(&file_name);
+        }
+        // The text of a query isn't kept with a recent file, so it couldn't be
+        // opened again from there.
+        if !self.stdin_files.contains(&file_name) && !matches!(format, FileFormat::Sqlite(Some(_)))
+        {
+            self.recent_files.touch(&file_name);
+            menu::update_recent_menu(&self.recent_files);
+        }
+
+        self.dialects.insert(file_name.clone(), dialect);
+        self.file_formats.insert(file_name.clone(), format.clone());
+
+        for tab in self.tree.iter_all_tabs_mut() {
+            let sheet_tab = tab.1;
+            sheet_tab.columns.insert(file_name.clone(), headers.clone());
+
+            if let Some(tab_id) = tab_id {
+                if sheet_tab.id == tab_id {
+                    sheet_tab.chosen_file = file_name.clone();
+                    self.filters
+                        .insert((file_name.clone(), tab_id), "".to_string());
+                }
+            }
+        }
+
+        self.loading = true;
+
+        ctx.send_viewport_cmd(egui::ViewportCommand::Title(file_name.clone()));
+
+        self.read_master(ctx, file_name, format);
+    }
+
+    /// How `filename` is written: sniffed, unless the command line said otherwise.
+    fn file_dialect(&self, filename: &str) -> Dialect {
+        let dialect = sniff_dialect(filename);
+        match self.dialect_overrides.get(filename) {
+            Some(overrides) => overrides.apply(dialect),
+            None => dialect,
+        }
+    }
+
+    /// Open the only worksheet of a workbook, or ask which ones to open. For a
+    /// database, ask which tables and views to open or which query to run.
+    fn pick_sheets(
+        &mut self,
+        ctx: &egui::Context,
+        path: String,
+        tab_id: Option<usize>,
+        database: bool,
+    ) {
+        let sheets = if database {
+            table_names(&path)
+        } else {
+            sheet_names(&path)
+        };
+        match sheets {
+            Ok(sheets) if sheets.len() == 1 && !database => {
+                self.load_file(ctx, sheet_file(&path, &sheets[0]), tab_id);
+            }
+            Ok(sheets) if database || !sheets.is_empty() => {
+                self.sheet_picker = Some(SheetPicker::new(path, sheets, tab_id, database));
+            }
+            result => {
+                eprintln!("Failed to open {}: {:?}", path, result);
+                let short_name = path.split('/').next_back().unwrap_or(&path);
+                crate::toast::show(ctx, format!("Could not open {short_name}"));
+            }
+        }
+    }
+
+    /// Open the rows of `query` on the database at `path` as `path#Query n`.
+    fn open_query(
+        &mut self,
+        ctx: &egui::Context,
+        path: String,
+        query: String,
+        tab_id: Option<usize>,
+    ) {
+        let Some(file_name) = (1..)
+            .map(|n| sheet_file(&path, &format!("Query {n}")))
+            .find(|file| !self.files_list.contains(file))
+        else {
+            return;
+        };
+        self.file_formats
+            .insert(file_name.clone(), FileFormat::Sqlite(Some(query)));
+        self.load_file(ctx, file_name, tab_id);
+    }
+
+    /// Filter and sort a tab's view of a SQLite table or query in SQLite itself.
+    /// Returns false when the view has to be built in memory instead, because the
+    /// file isn't from a database or has unsaved edits.
+    fn query_sql_view(
+        &self,
+        ctx: &egui::Context,
+        filename: &str,
+        tab_id: usize,
+        sort: Option<(usize, SortOrder)>,
+    ) -> bool {
+        let Some(source) = self
+            .sql_sources
+            .get(filename)
+            .filter(|_| !self.dirty_files.contains(filename))
+        else {
+            return false;
+        };
+        let key = (filename.to_string(), tab_id);
+        let filter = self.filters.get(&key).cloned().unwrap_or_default();
+        let facets = self.facets.get(&key).cloned().unwrap_or_default();
+        let path = split_sheet(filename).0.to_string();
+        let source = source.clone();
+        let chan = self.worker_chan.0.clone();
+        let ctx = ctx.clone();
+        let filename = filename.to_string();
+
+        thread::spawn(move || {
+            let view = match query_view(&path, &source, &filter, &facets, sort) {
+                Ok(view) => view,
+                Err(e) => {
+                    eprintln!("Failed to query {}: {:?}", filename, e);
+                    return;
+                }
+            };
+
+            if let Err(e) = chan.send(UiMessage::SetDisplayData(view, None, filename, tab_id)) {
+                eprintln!("Worker: Failed to send view data to UI thread: {:?}", e);
+            }
+
+            ctx.request_repaint();
+        });
+        true
+    }
+
+    fn sort_current_sheet(
+        &mut self,
+        ctx: &egui::Context,
+        filename: String,
+        sort_order: (usize, SortOrder),
+        tab_id: usize,
+    ) {
+        if self.query_sql_view(ctx, &filename, tab_id, Some(sort_order)) {
+            return;
+        }
+        let Some(master_data) = self.sheets_data.get(&filename) else {
+            return;
+        };
+        if master_data.is_empty() {
+            return;
+        }
+        let key = (filename.clone(), tab_id);
+        // The rows the tab shows now; a view SQLite built is filtered again.
+        let rows = self.filtered_rows.get(&key).cloned();
+        let filter = self.filters.get(&key).cloned().unwrap_or_default();
+        let facets = self.facets.get(&key).cloned().unwrap_or_default();
+        let master_clone = master_data.clone();
+        let chan = self.worker_chan.0.clone();
+        let ctx = ctx.clone();
+
+        thread::spawn(move || {
+            let mut rows =
+                rows.unwrap_or_else(|| view_row_numbers(&master_clone, &filter, &facets, None));
+            sort_row_numbers(&master_clone, &mut rows, sort_order);
+            let sorted = rows_at(&master_clone, &rows);
+
+            if let Err(e) = chan.send(UiMessage::SetDisplayData(
+                sorted,
+                Some(rows),
+                filename,
+                tab_id,
+            )) {
+                eprintln!("Worker: Failed to send sorted data to UI thread: {:?}", e);
+            }
+
+            ctx.request_repaint();
+        });
+    }
+
+    fn filter_current_sheet(
+        &mut self,
+        ctx: &egui::Context,
+        filename: String,
+        filter: String,
+        tab_id: usize,
+    ) {
+        let sort = self
+            .tree
+            .iter_all_tabs()
+            .find(|(_, tab)| tab.id == tab_id)
+            .and_then(|(_, tab)| tab.columns.get(&filename))
+            .and_then(|headers| {
+                headers
+                    .iter()
+                    .enumerate()
+                    .find_map(|(col, h)| Some((col, h.sort?)))
+            });
+        if self.query_sql_view(ctx, &filename, tab_id, sort) {
+            return;
+        }
+        let chan = self.worker_chan.0.clone();
+
+        for tab in self.tree.iter_all_tabs_mut() {
+            let sheet_tab = tab.1;
+            let filter = filter.clone();
+
+            if sheet_tab.id == tab_id {
+                if let Some(master_data) = self.sheets_data.get(&filename) {
+                    let master_clone = master_data.clone();
+                    let facets = self
+                        .facets
+                        .get(&(filename.clone(), tab_id))
+                        .cloned()
+                        .unwrap_or_default();
+                    let chan = chan.clone();
+                    let ctx = ctx.clone();
+                    let filename = filename.clone();
+
+                    thread::spawn(move || {
+                        let rows = view_row_numbers(&master_clone, &filter, &facets, sort);
+                        let filtered = rows_at(&master_clone, &rows);
+
+                        if let Err(e) = chan.send(UiMessage::SetDisplayData(
+                            filtered,
+                            Some(rows),
+                            filename,
+                            tab_id,
+                        )) {
+                            eprintln!("Worker: Failed to send filtered data to UI thread: {:?}", e);
+                        }
+
+                        ctx.request_repaint();
+                    });
+                }
+            };
+        }
+    }
+}
+
+impl MyApp {
+    /// Rebuild the filtered/sorted views of the tabs showing `filename` after its
+    /// master data was (re)loaded, e.g. when restoring a session.
+    fn reapply_views(&mut self, ctx: &egui::Context, filename: &str) {
+        let Some(master_data) = self.sheets_data.get(filename) else {
+            return;
+        };
+        let mut unfiltered = Vec::new();
+
+        for (_, tab) in self.tree.iter_all_tabs() {
+            if tab.chosen_file != filename {
+                continue;
+            }
+            let key = (filename.to_string(), tab.id);
+            let filter = self.filters.get(&key).cloned().unwrap_or_default();
+            let facets = self.facets.get(&key).cloned().unwrap_or_default();
+            let sort = tab.columns.get(filename).and_then(|headers| {
+                headers
+                    .iter()
+                    .enumerate()
+                    .find_map(|(col, h)| Some((col, h.sort?)))
+            });
+            if filter.is_empty() && facets.is_empty() && sort.is_none() {
+                unfiltered.push(tab.id);
+                continue;
+            }
+            if self.query_sql_view(ctx, filename, tab.id, sort) {
+                continue;
+            }
+
+            let master_clone = master_data.clone();
+            let chan = self.worker_chan.0.clone();
+            let ctx = ctx.clone();
+            let filename = filename.to_string();
+            let tab_id = tab.id;
+
+            thread::spawn(move || {
+                let rows = view_row_numbers(&master_clone, &filter, &facets, sort);
+                let view = rows_at(&master_clone, &rows);
+
+                if let Err(e) = chan.send(UiMessage::SetDisplayData(
+                    view,
+                    Some(rows),
+                    filename,
+                    tab_id,
+                )) {
+                    eprintln!("Worker: Failed to send view data to UI thread: {:?}", e);
+                }
+
+                ctx.request_repaint();
+            });
+        }
+
+        for tab_id in unfiltered {
+            let key = (filename.to_string(), tab_id);
+            if self.filtered_data.contains_key(&key) {
+                self.move_selection(filename, tab_id, None);
+                self.filtered_data.remove(&key);
+                self.filtered_rows.remove(&key);
+            }
+        }
+    }
+
+    /// Keep a tab's selection on the same rows when its view is replaced by one
+    /// showing the master `rows`, or the master data itself for `None`. Rows that
+    /// are no longer shown are dropped, and so is a selection in a view SQLite
+    /// built, which has no row numbers to follow.
+    fn move_selection(&mut self, filename: &str, tab_id: usize, rows: Option<&[usize]>) {
+        let key = (filename.to_string(), tab_id);
+        // Master row of each row shown until now; `None` when it's the master data.
+        let shown = self
+            .filtered_data
+            .contains_key(&key)
+            .then(|| self.filtered_rows.get(&key));
+        let Some((_, tab)) = self
+            .tree
+            .iter_all_tabs_mut()
+            .find(|(_, tab)| tab.id == tab_id)
+        else {
+            return;
+        };
+        if tab.selection.is_empty() && tab.editing_cell.is_none() {
+            return;
+        }
+        let now = rows.map(|rows| {
+            rows.iter()
+                .enumerate()
+                .map(|(shown, &row)| (row, shown as u64))
+                .collect::<HashMap<_, _>>()
+        });
+        let moved = |position: u64| {
+            let row = match shown {
+                Some(shown) => *shown?.get(position as usize)?,
+                None => position as usize,
+            };
+            match &now {
+                Some(now) => now.get(&row).copied(),
+                None => Some(row as u64),
+            }
+        };
+        tab.selection.move_rows(moved);
+        tab.editing_cell = tab
+            .editing_cell
+            .and_then(|(row, col)| Some((moved(row)?, col)));
+    }
+
+    /// Open the profile panel of a tab on `column` and profile the tab's current view.
+    fn profile_column(
+        &mut self,
+        ctx: &egui::Context,
+        filename: String,
+        tab_id: usize,
+        column: usize,
+    ) {
+        for (_, tab) in self.tree.iter_all_tabs_mut() {
+            if tab.id == tab_id {
+                tab.profile_column = Some((filename.clone(), column));
+                tab.profile = None;
+            }
+        }
+
+        let filter_active = filter_active(
+            &self.global_filter,
+            &self.filters,
+            &self.facets,
+            &filename,
+            tab_id,
+        );
+        let data = active_sheet_data(
+            &self.sheets_data,
+            &self.filtered_data,
+            &filename,
+            tab_id,
+            filter_active,
+        )
+        .clone();
+        let chan = self.worker_chan.0.clone();
+        let ctx = ctx.clone();
+
+        thread::spawn(move || {
+            let profile = profile_column(&data, column);
+
+            if let Err(e) = chan.send(UiMessage::SetProfile(filename, tab_id, profile)) {
+                eprintln!(
+                    "Worker: Failed to send column profile to UI thread: {:?}",
+                    e
+                );
+            }
+
+            ctx.request_repaint();
+        });
+    }
+
+    /// Re-profile open profile panels showing `filename` (optionally only in one tab).
+    fn refresh_profiles(&mut self, ctx: &egui::Context, filename: &str, tab_id: Option<usize>) {
+        let open = self
+            .tree
+            .iter_all_tabs()
+            .filter(|(_, tab)| tab_id.is_none_or(|id| id == tab.id))
+            .filter_map(|(_, tab)| match &tab.profile_column {
+                Some((file, column)) if file == filename => Some((tab.id, *column)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        for (tab_id, column) in open {
+            self.profile_column(ctx, filename.to_string(), tab_id, column);
+        }
+    }
+
+    /// Recount the facet sidebars showing `filename` (optionally only in one tab).
+    fn refresh_facets(&mut self, ctx: &egui::Context, filename: &str, tab_id: Option<usize>) {
+        let Some(master_data) = self.sheets_data.get(filename) else {
+            return;
+        };
+
+        for (_, tab) in self.tree.iter_all_tabs_mut() {
+            if !tab.show_facets
+                || tab.chosen_file != filename
+                || tab_id.is_some_and(|id| id != tab.id)
+            {
+                continue;
+            }
+            tab.facet_counts = None;
+
+            let key = (filename.to_string(), tab.id);
+            let filter = self.filters.get(&key).cloned().unwrap_or_default();
+            let facets = self.facets.get(&key).cloned().unwrap_or_default();
+            let master_clone = master_data.clone();
+            let chan = self.worker_chan.0.clone();
+            let ctx = ctx.clone();
+            let filename = filename.to_string();
+            let tab_id = tab.id;
+
+            thread::spawn(move || {
+                let counts = facet_counts(&master_clone, &filter, &facets);
+
+                if let Err(e) = chan.send(UiMessage::SetFacetCounts(filename, tab_id, counts)) {
+                    eprintln!("Worker: Failed to send facet counts to UI thread: {:?}", e);
+                }
+
+                ctx.request_repaint();
+            });
+        }
+    }
+
+    /// Build a pivot of the tab's current view on a worker thread.
+    fn open_pivot(
+        &mut self,
+        ctx: &egui::Context,
+        filename: String,
+        tab_id: usize,
+        spec: PivotSpec,
+    ) {
+        let key = (filename.clone(), tab_id);
+        let filter_active = filter_active(
+            &self.global_filter,
+            &self.filters,
+            &self.facets,
+            &filename,
+            tab_id,
+        );
+        let data = active_sheet_data(
+            &self.sheets_data,
+            &self.filtered_data,
+            &filename,
+            tab_id,
+            filter_active,
+        )
+        .clone();
+        let headers = self
+            .tree
+            .iter_all_tabs()
+            .find(|(_, tab)| tab.id == tab_id)
+            .and_then(|(_, tab)| tab.columns.get(&filename))
+            .cloned()
+            .unwrap_or_default();
+        let base_filter = self.filters.get(&key).cloned().unwrap_or_default();
+        let base_facets = self.facets.get(&key).cloned().unwrap_or_default();
+        let chan = self.worker_chan.0.clone();
+        let ctx = ctx.clone();
+
+        thread::spawn(move || {
+            let (headers, rows, column_keys) = pivot(&data, &headers, &spec);
+            let source = PivotSource {
+                source_file: filename,
+                spec,
+                base_filter,
+                base_facets,
+                column_keys,
+            };
+
+            if let Err(e) = chan.send(UiMessage::SetPivot(headers, rows, source)) {
+                eprintln!("Worker: Failed to send pivot to UI thread: {:?}", e);
+            }
+
+            ctx.request_repaint();
+        });
+    }
+
+    /// Push a new tab showing `filename` next to the focused one. Column headers
+    /// are copied from the tabs that already know the file.
+    pub fn push_tab(&mut self, filename: Filename) -> usize {
+        let columns = self
+            .tree
+            .iter_all_tabs()
+            .find(|(_, tab)| tab.columns.contains_key(&filename))
+            .map(|(_, tab)| tab.columns.clone())
+            .unwrap_or_default();
+
+        let tab_id = self.counter;
+        self.tree.push_to_focused_leaf(SheetTab {
+            id: tab_id,
+            columns,
+            chosen_file: filename,
+            ..Default::default()
+        });
+        self.counter += 1;
+        tab_id
+    }
+
+    fn set_pivot(&mut self, headers: Vec<FileHeader>, rows: SheetVec, source: PivotSource) {
+        let short_name = source
+            .source_file
+            .split('/')
+            .next_back()
+            .unwrap_or(&source.source_file);
+        let name = format!("Pivot {} of {}", self.counter, short_name);
+
+        self.sheets_data.insert(name.clone(), rows);
+        let tab_id = self.push_tab(name.clone());
+        self.filters.insert((name.clone(), tab_id), "".to_string());
+
+        for (_, tab) in self.tree.iter_all_tabs_mut() {
+            if tab.id == tab_id {
+                tab.columns.insert(name.clone(), headers.clone());
+                tab.read_only = true;
+                tab.pivot = Some(source.clone());
+            }
+        }
+    }
+
+    fn open_drill_down(
+        &mut self,
+        ctx: &egui::Context,
+        filename: Filename,
+        filter: String,
+        facets: Facets,
+    ) {
+        let tab_id = self.push_tab(filename.clone());
+        self.filters
+            .insert((filename.clone(), tab_id), filter.clone());
+        self.facets.insert((filename.clone(), tab_id), facets);
+        self.filter_current_sheet(ctx, filename, filter, tab_id);
+    }
+
+    /// Open a chart tab plotting the current view of `source_tab`.
+    fn open_chart(&mut self, ctx: &egui::Context, filename: Filename, source_tab: usize) {
+        let tab_id = self.push_tab(filename);
+        for (_, tab) in self.tree.iter_all_tabs_mut() {
+            if tab.id == tab_id {
+                tab.read_only = true;
+                tab.chart = Some(ChartTab {
+                    source_tab,
+                    ..Default::default()
+                });
+            }
+        }
+        self.refresh_chart(ctx, tab_id);
+    }
+
+    /// Recompute a chart tab from its source tab's current view on a worker thread.
+    fn refresh_chart(&mut self, ctx: &egui::Context, tab_id: usize) {
+        let Some((filename, source_tab, spec)) = self
+            .tree
+            .iter_all_tabs()
+            .find(|(_, tab)| tab.id == tab_id)
+            .and_then(|(_, tab)| {
+                let chart = tab.chart.as_ref()?;
+                Some((
+                    tab.chosen_file.clone(),
+                    chart.source_tab,
+                    chart.spec.clone(),
+                ))
+            })
+        else {
+            return;
+        };
+        if !self.sheets_data.contains_key(&filename) {
+            return;
+        }
+
+        let filter_active = filter_active(
+            &self.global_filter,
+            &self.filters,
+            &self.facets,
+            &filename,
+            source_tab,
+        );
+        let data = active_sheet_data(
+            &self.sheets_data,
+            &self.filtered_data,
+            &filename,
+            source_tab,
+            filter_active,
+        )
+        .clone();
+        let chan = self.worker_chan.0.clone();
+        let ctx = ctx.clone();
+
+        thread::spawn(move || {
+            let chart = chart_data(&data, &spec);
+
+            if let Err(e) = chan.send(UiMessage::SetChart(tab_id, chart)) {
+                eprintln!("Worker: Failed to send chart to UI thread: {:?}", e);
+            }
+
+            ctx.request_repaint();
+        });
+    }
+
+    /// Recompute the chart tabs plotting `filename` (or only the view of `source_tab`).
+    fn refresh_charts(&mut self, ctx: &egui::Context, filename: &str, source_tab: Option<usize>) {
+        let chart_tabs = self
+            .tree
+            .iter_all_tabs()
+            .filter(|(_, tab)| tab.chosen_file == filename)
+            .filter_map(|(_, tab)| Some((tab.id, tab.chart.as_ref()?.source_tab)))
+            .filter(|(_, source)| source_tab.is_none_or(|id| id == *source))
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        for tab_id in chart_tabs {
+            self.refresh_chart(ctx, tab_id);
+        }
+    }
+
+    /// Drop cached selection aggregates after the underlying data changed.
+    pub fn invalidate_selection_stats(&mut self) {
+        for (_, tab) in self.tree.iter_all_tabs_mut() {
+            tab.selection_stats = None;
+            tab.stats_pending = None;
+        }
+    }
+
+    /// Keep the focused tab's selection aggregates current: small selections are
+    /// aggregated inline, large ones on a worker thread.
+    fn refresh_selection_stats(&mut self, ctx: &egui::Context) {
+        let Some((_, tab)) = self.tree.find_active_focused() else {
+            return;
+        };
+        let generation = tab.selection.generation;
+        let up_to_date = tab
+            .selection_stats
+            .as_ref()
+            .is_some_and(|(g, _)| *g == generation);
+        if up_to_date || tab.stats_pending == Some(generation) {
+            return;
+        }
+        if tab.selection.is_empty() {
+            tab.selection_stats = None;
+            return;
+        }
+
+        let filename = tab.chosen_file.clone();
+        let tab_id = tab.id;
+        let filter_active = filter_active(
+            &self.global_filter,
+            &self.filters,
+            &self.facets,
+            &filename,
+            tab_id,
+        );
+        let data = active_sheet_data(
+            &self.sheets_data,
+            &self.filtered_data,
+            &filename,
+            tab_id,
+            filter_active,
+        );
+        let visible = tab
+            .columns
+            .get(&filename)
+            .map(|c| visible_col_indices(c))
+            .unwrap_or_default();
+
+        let num_cells = tab
+            .selection
+            .bounds(data.len() as u64, visible.len())
+            .map_or(0, |(r1, r2, c1, c2)| (r2 - r1 + 1) * (c2 - c1 + 1) as u64);
+
+        if num_cells <= SYNC_STATS_LIMIT {
+            let stats = selection_stats(data, &tab.selection, &visible);
+            tab.selection_stats = Some((generation, stats));
+            return;
+        }
+
+        // Copying a large view for every step of a drag would stall the UI; the
+        // aggregates follow once the drag ends.
+        if tab.selection.is_dragging() {
+            return;
+        }
+        tab.stats_pending = Some(generation);
+        let data = data.clone();
+        let selection = tab.selection.clone();
+        let chan = self.worker_chan.0.clone();
+        let ctx = ctx.clone();
+
+        thread::spawn(move || {
+            let stats = selection_stats(&data, &selection, &visible);
+
+            if let Err(e) = chan.send(UiMessage::SetSelectionStats(tab_id, generation, stats)) {
+                eprintln!(
+                    "Worker: Failed to send selection stats to UI thread: {:?}",
+                    e
+                );
+            }
+
+            ctx.request_repaint();
+        });
+    }
+
+    fn show_status_bar(&mut self, ctx: &egui::Context) {
+        self.refresh_selection_stats(ctx);
+
+        let Some((_, tab)) = self.tree.find_active_focused() else {
+            return;
+        };
+        let tab: &SheetTab = tab;
+        if tab.chosen_file.is_empty() {
+            return;
+        }
+
+        let filter_active = filter_active(
+            &self.global_filter,
+            &self.filters,
+            &self.facets,
+            &tab.chosen_file,
+            tab.id,
+        );
+        let data = active_sheet_data(
+            &self.sheets_data,
+            &self.filtered_data,
+            &tab.chosen_file,
+            tab.id,
+            filter_active,
+        );
+        let columns = tab.columns.get(&tab.chosen_file);
+        let cursor = tab.selection.cursor().and_then(|(row, col)| {
+            let columns = columns?;
+            let actual_col = *visible_col_indices(columns).get(col)?;
+            let name = columns.get(actual_col)?.name.as_str();
+            Some((
+                row,
+                if name.is_empty() && actual_col == 0 {
+                    "id"
+                } else {
+                    name
+                },
+            ))
+        });
+
+        let generation = tab.selection.generation;
+        status_bar::show(
+            ctx,
+            &StatusInfo {
+                total_rows: self
+                    .sheets_data
+                    .get(&tab.chosen_file)
+                    .map_or(0, |d| d.len()),
+                view_rows: data.len(),
+                filter_active,
+                cursor,
+                stats: tab
+                    .selection_stats
+                    .as_ref()
+                    .filter(|(g, _)| *g == generation)
+                    .map(|(_, stats)| stats),
+                stats_pending: tab.stats_pending == Some(generation),
+            },
+        );
+    }
+
+    /// Read the rows of `file_name` into its master data. Parquet and Arrow files
+    /// are only read as far as they are shown, see `open_lazy`.
+    fn read_master(&mut self, ctx: &egui::Context, file_name: String, format: FileFormat) {
+        if format.columnar() {
+            self.open_lazy(ctx, file_name, format);
+            return;
+        }
+        if let FileFormat::Sqlite(query) = &format {
+            let (path, name) = split_sheet(&file_name);
+            match sqlite::source(path, name.unwrap_or_default(), query.as_deref()) {
+                Ok(source) => {
+                    self.sql_sources.insert(file_name.clone(), source);
+                }
+                Err(e) => {
+                    eprintln!("Failed to read {}: {:?}", file_name, e);
+                    return;
+                }
+            }
+            self.sql_edits.remove(&file_name);
+        }
+        let dialect = self.dialects.get(&file_name).copied().unwrap_or_default();
+        let chan = self.worker_chan.0.clone();
+        let ctx = ctx.clone();
+
+        thread::spawn(move || {
+            let stamp = FileStamp::of(&file_name)
+                .inspect_err(|e| eprintln!("Failed to hash {}: {:?}", file_name, e))
+                .ok();
+
+            // Stop at the length that was hashed, so rows another program appends
+            // meanwhile are left for follow mode instead of being read twice.
+            let limit = stamp.as_ref().map_or(u64::MAX, |stamp| stamp.len);
+            let (headers, master_data, end) = match read_rows(&file_name, &format, &dialect, limit)
+            {
+                Ok(read) => read,
+                Err(e) => {
+                    eprintln!("Failed to read {}: {:?}", file_name, e);
+                    return;
+                }
+            };
+
+            if let Some(headers) = headers
+                && let Err(e) = chan.send(UiMessage::SetHeaders(file_name.clone(), headers))
+            {
+                eprintln!("Worker: Failed to send headers to UI thread: {:?}", e);
+            }
+
+            if let Some(stamp) = stamp {
+                let stamp = stamp.read_to(end);
+                if let Err(e) = chan.send(UiMessage::SetFileStamp(file_name.clone(), stamp)) {
+                    eprintln!("Worker: Failed to send file stamp to UI thread: {:?}", e);
+                }
+            }
+
+            if let Err(e) = chan.send(UiMessage::SetMaster(master_data, file_name.clone())) {
+                eprintln!("Worker: Failed to send page data to UI thread: {:?}", e);
+            }
+
+            ctx.request_repaint();
+        });
+    }
+
+    /// Put the columns a file was read with into every tab, keeping a tab's
+    /// visibility and sort unless the names changed.
+    fn set_headers(&mut self, filename: &str, headers: &[FileHeader]) {
+        for (_, tab) in self.tree.iter_all_tabs_mut() {
+            let Some(columns) = tab.columns.get_mut(filename) else {
+                continue;
+            };
+            if columns.len() != headers.len()
+                || columns.iter().zip(headers).any(|(c, h)| c.name != h.name)
+            {
+                *columns = headers.to_vec();
+            }
+        }
+    }
+
+    /// Put empty rows in place of the rows of a Parquet or Arrow file, and read the
+    /// row groups the tabs show. The rest is read when scrolled to, or all at once
+    /// when something needs every row.
+    fn open_lazy(&mut self, ctx: &egui::Context, file_name: String, format: FileFormat) {
+        let layout = match layout(&file_name, &format) {
+            Ok(layout) => layout,
+            Err(e) => {
+                eprintln!("Failed to read {}: {:?}", file_name, e);
+                return;
+            }
+        };
+        let lazy = LazyFile::new(format, &layout.group_rows);
+        self.sheets_data
+            .insert(file_name.clone(), vec![StringRecord::new(); lazy.rows()]);
+        self.invalidate_selection_stats();
+        // Messages held back for an earlier read of the file are handled once this
+        // one is complete, right away when there is nothing to read.
+        let deferred = self
+            .lazy_files
+            .remove(&file_name)
+            .map(|previous| previous.deferred)
+            .unwrap_or_default();
+        if lazy.groups.is_empty() {
+            for message in deferred {
+                if let Err(e) = self.worker_chan.0.send(message) {
+                    eprintln!("Failed to resend deferred message: {:?}", e);
+                }
+            }
+        } else {
+            self.lazy_files
+                .insert(file_name.clone(), LazyFile { deferred, ..lazy });
+        }
+
+        let chan = self.worker_chan.0.clone();
+        let stamp_file = file_name.clone();
+        thread::spawn(move || match FileStamp::of(&stamp_file) {
+            Ok(stamp) => {
+                if let Err(e) = chan.send(UiMessage::SetFileStamp(stamp_file, stamp)) {
+                    eprintln!("Worker: Failed to send file stamp to UI thread: {:?}", e);
+                }
+            }
+            Err(e) => eprintln!("Failed to hash {}: {:?}", stamp_file, e),
+        });
+
+        // Filtered and sorted views are rebuilt once every row is in.
+        let views = self.tree.iter_all_tabs().any(|(_, tab)| {
+            tab.chosen_file == file_name
+                && (filter_active(
+                    &self.global_filter,
+                    &self.filters,
+                    &self.facets,
+                    &file_name,
+                    tab.id,
+                ) || tab
+                    .columns
+                    .get(&file_name)
+                    .is_some_and(|headers| headers.iter().any(|h| h.sort.is_some())))
+        });
+        if views {
+            self.load_all_row_groups(ctx, &file_name);
+        } else {
+            self.load_visible_row_groups(ctx);
+        }
+    }
+
+    /// Read the row groups that tabs showing a partly read file scroll into view,
+    /// plus the group after, so scrolling on doesn't show empty rows.
+    fn load_visible_row_groups(&mut self, ctx: &egui::Context) {
+        let mut wanted = Vec::new();
+        for (_, tab) in self.tree.iter_all_tabs() {
+            let Some(lazy) = self.lazy_files.get(&tab.chosen_file) else {
+                continue;
+            };
+            if self
+                .filtered_data
+                .contains_key(&(tab.chosen_file.clone(), tab.id))
+            {
+                continue;
+            }
+            let visible = tab
+                .last_visible_rows
+                .as_ref()
+                .map_or(0..1, |rows| rows.start as usize..rows.end as usize + 1);
+            let groups = lazy.missing(visible.start..visible.end.max(visible.start + 1));
+            let next = groups
+                .last()
+                .map(|&g| g + 1)
+                .filter(|&g| g < lazy.groups.len());
+            let groups = groups
+                .into_iter()
+                .chain(next.filter(|&g| !lazy.requested[g]))
+                .collect::<Vec<_>>();
+            if !groups.is_empty() {
+                wanted.push((tab.chosen_file.clone(), groups));
+            }
+        }
+        for (file, groups) in wanted {
+            self.load_row_groups(ctx, &file, groups);
+        }
+    }
+
+    fn load_all_row_groups(&mut self, ctx: &egui::Context, filename: &str) {
+        let Some(lazy) = self.lazy_files.get(filename) else {
+            return;
+        };
+        let groups = lazy.missing(0..lazy.rows().max(1));
+        self.load_row_groups(ctx, filename, groups);
+    }
+
+    fn load_row_groups(&mut self, ctx: &egui::Context, filename: &str, groups: Vec<usize>) {
+        let Some(lazy) = self.lazy_files.get_mut(filename) else {
+            return;
+        };
+        let groups = groups
+            .into_iter()
+            .filter(|&g| !std::mem::replace(&mut lazy.requested[g], true))
+            .collect::<Vec<_>>();
+        if groups.is_empty() {
+            return;
+        }
+        let format = lazy.format.clone();
+        let chan = self.worker_chan.0.clone();
+        let ctx = ctx.clone();
+        let filename = filename.to_string();
+
+        thread::spawn(move || {
+            for group in groups {
+                let rows = match read_group(&filename, &format, group) {
+                    Ok(rows) => rows,
+                    Err(e) => {
+                        eprintln!(
+                            "Failed to read row group {} of {}: {:?}",
+                            group, filename, e
+                        );
+                        Vec::new()
+                    }
+                };
+                if let Err(e) = chan.send(UiMessage::SetRowGroup(filename.clone(), group, rows)) {
+                    eprintln!("Worker: Failed to send row group to UI thread: {:?}", e);
+                    return;
+                }
+                ctx.request_repaint();
+            }
+        });
+    }
+
+    /// Put a row group in place of its empty rows. Once the last group is in, views
+    /// and panels are rebuilt and the messages that waited for it are handled.
+    fn set_row_group(
+        &mut self,
+        ctx: &egui::Context,
+        filename: Filename,
+        group: usize,
+        rows: SheetVec,
+    ) {
+        let Some(lazy) = self.lazy_files.get_mut(&filename) else {
+            return;
+        };
+        // Groups of an earlier read of a reloaded file may still come in.
+        let Some(range) = lazy.groups.get(group).cloned() else {
+            return;
+        };
+        if !lazy.requested[group] || std::mem::replace(&mut lazy.loaded[group], true) {
+            return;
+        }
+        if let Some(data) = self.sheets_data.get_mut(&filename)
+            && range.end <= data.len()
+        {
+            for (slot, row) in data[range].iter_mut().zip(rows) {
+                *slot = row;
+            }
+        }
+        if lazy.loaded.contains(&false) {
+            self.invalidate_selection_stats();
+            return;
+        }
+
+        let deferred = self
+            .lazy_files
+            .remove(&filename)
+            .map(|lazy| lazy.deferred)
+            .unwrap_or_default();
+        self.reapply_views(ctx, &filename);
+        self.invalidate_selection_stats();
+        self.refresh_profiles(ctx, &filename, None);
+        self.refresh_facets(ctx, &filename, None);
+        self.refresh_charts(ctx, &filename, None);
+        for message in deferred {
+            if let Err(e) = self.worker_chan.0.send(message) {
+                eprintln!("Failed to resend deferred message: {:?}", e);
+            }
+        }
+    }
+
+    /// Hold back a message that needs every row of a partly read file, and start
+    /// reading the rest of it. Returns the message when it can be handled now.
+    fn defer_until_loaded(&mut self, ctx: &egui::Context, message: UiMessage) -> Option<UiMessage> {
+        let Some(filename) = message.needs_all_rows().cloned() else {
+            return Some(message);
+        };
+        let Some(lazy) = self.lazy_files.get_mut(&filename) else {
+            return Some(message);
+        };
+        if lazy.deferred.is_empty() {
+            let short_name = filename.split('/').next_back().unwrap_or(&filename);
+            crate::toast::show(ctx, format!("Reading all rows of {short_name}…"));
+        }
+        lazy.deferred.push(message);
+        self.load_all_row_groups(ctx, &filename);
+        None
+    }
+
+    /// Write the master data of `filename` back to disk. Returns whether it worked.
+    /// When the file changed on disk since it was loaded nothing is written yet;
+    /// the user is asked whether to overwrite it first.
+    pub fn save_file(&mut self, ctx: &egui::Context, filename: &str) -> bool {
+        if self.sql_sources.contains_key(filename) {
+            return self.save_sql_edits(ctx, filename);
+        }
+        if !self
+            .file_formats
+            .get(filename)
+            .is_none_or(FileFormat::writable)
+        {
+            return self.save_file_as(ctx, filename);
+        }
+        if self
+            .file_stamps
+            .get(filename)
+            .is_some_and(|stamp| stamp.changed_on_disk(filename))
+        {
+            if !self
+                .overwrite_prompts
+                .iter()
+                .any(|queued| queued == filename)
+            {
+                self.overwrite_prompts.push(filename.to_string());
+            }
+            return false;
+        }
+        self.write_file(ctx, filename)
+    }
 
-        let (mut reader, headers) = open_csv_file(&file_name);
+    fn write_file(&mut self, ctx: &egui::Context, filename: &str) -> bool {
+        let Some(data) = self.sheets_data.get(filename) else {
+            return false;
+        };
+        let headers = self
+            .tree
+            .iter_all_tabs()
+            .find_map(|(_, tab)| tab.columns.get(filename))
+            .cloned()
+            .unwrap_or_default();
+        let short_name = filename.split('/').next_back().unwrap_or(filename);
+        let dialect = self.dialects.get(filename).copied().unwrap_or_default();
 
-        for tab in self.tree.iter_all_tabs_mut() {
-            let sheet_tab = tab.1;
-            sheet_tab.columns.insert(file_name.clone(), headers.clone());
+        if self.keep_backups
+            && let Err(e) = backup_file(filename)
+        {
+            eprintln!("Failed to back up {}: {:?}", filename, e);
+            crate::toast::show(ctx, format!("Could not back up {short_name}, not saved"));
+            return false;
+        }
 
-            if let Some(tab_id) = tab_id {
-                if sheet_tab.id == tab_id {
-                    sheet_tab.chosen_file = file_name.clone();
-                    self.filters
-                        .insert((file_name.clone(), tab_id), "".to_string());
-                }
+        if let Err(e) = write_csv(filename, &headers, data, &dialect) {
+            eprintln!("Failed to save {}: {:?}", filename, e);
+            crate::toast::show(ctx, format!("Could not save {short_name}"));
+            false
+        } else {
+            crate::toast::show(ctx, format!("Saved: {short_name}"));
+            self.dirty_files.remove(filename);
+            self.update_file_stamp(filename);
+            true
+        }
+    }
+
+    /// Write the cell edits of a SQLite table back as `UPDATE`s. Views and queries
+    /// can't be updated, and are saved as a new file instead.
+    fn save_sql_edits(&mut self, ctx: &egui::Context, filename: &str) -> bool {
+        let Some(source) = self.sql_sources.get(filename) else {
+            return false;
+        };
+        if source.table.is_none() {
+            return self.save_file_as(ctx, filename);
+        }
+        let path = split_sheet(filename).0;
+        let short_name = path.split('/').next_back().unwrap_or(path);
+        let edits = self.sql_edits.get(filename).cloned().unwrap_or_default();
+
+        if let Err(e) = sqlite::write_edits(path, source, &edits) {
+            eprintln!("Failed to save {}: {:?}", filename, e);
+            crate::toast::show(ctx, format!("Could not save {short_name}"));
+            return false;
+        }
+        crate::toast::show(
+            ctx,
+            format!("Saved {} edit(s) to {short_name}", edits.len()),
+        );
+        self.sql_edits.remove(filename);
+        self.dirty_files.remove(filename);
+        // Other tables of the database share its stamp.
+        let same_database = self
+            .files_list
+            .iter()
+            .filter(|f| split_sheet(f).0 == path)
+            .cloned()
+            .collect::<Vec<_>>();
+        for file in same_database {
+            self.update_file_stamp(&file);
+        }
+        true
+    }
+
+    fn update_file_stamp(&mut self, filename: &str) {
+        match FileStamp::of(filename) {
+            Ok(stamp) => {
+                self.file_stamps.insert(filename.to_string(), stamp);
             }
+            Err(e) => eprintln!("Failed to hash {}: {:?}", filename, e),
         }
+    }
 
-        self.loading = true;
+    /// Ask for a new path, write `filename` there as CSV or Parquet (by the chosen
+    /// extension) and continue working on the new file. Returns whether the file
+    /// was saved; a partly read Parquet or Arrow file is saved once it's read.
+    pub fn save_file_as(&mut self, ctx: &egui::Context, filename: &str) -> bool {
+        if self.lazy_files.contains_key(filename) {
+            self.defer_until_loaded(ctx, UiMessage::SaveAs(filename.to_string()));
+            return false;
+        }
+        let Some(data) = self.sheets_data.get(filename) else {
+            return false;
+        };
+        let writable = self
+            .file_formats
+            .get(filename)
+            .is_none_or(FileFormat::writable);
+        let (source, sheet) = split_sheet(filename);
+        let path = Path::new(source);
+        let suggested = if writable {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .map(str::to_string)
+        } else {
+            path.file_stem()
+                .and_then(|n| n.to_str())
+                .map(|stem| match sheet {
+                    Some(sheet) => format!("{stem} - {sheet}.csv"),
+                    None => format!("{stem}.csv"),
+                })
+        };
+        let Some(new_path) = rfd::FileDialog::new()
+            .add_filter("csv", &["csv", "tsv", "txt"])
+            .add_filter("Parquet", &["parquet"])
+            .set_file_name(suggested.as_deref().unwrap_or("untitled.csv"))
+            .set_directory(path.parent().unwrap_or(Path::new(".")))
+            .save_file()
+        else {
+            return false;
+        };
+        let new_name = new_path.display().to_string();
+        let headers = self
+            .tree
+            .iter_all_tabs()
+            .find_map(|(_, tab)| tab.columns.get(filename))
+            .cloned()
+            .unwrap_or_default();
+        let dialect = if writable {
+            self.dialects.get(filename).copied().unwrap_or_default()
+        } else {
+            Dialect::default()
+        };
 
-        ctx.send_viewport_cmd(egui::ViewportCommand::Title(file_name.clone()));
+        let format = match detect_format(&new_name) {
+            FileFormat::Parquet => FileFormat::Parquet,
+            _ => FileFormat::Delimited,
+        };
+        let written = if format == FileFormat::Parquet {
+            write_parquet(&new_name, &headers, data)
+        } else {
+            write_csv(&new_name, &headers, data, &dialect)
+        };
+        if let Err(e) = written {
+            eprintln!("Failed to save {}: {:?}", new_name, e);
+            crate::toast::show(ctx, format!("Could not save {new_name}"));
+            return false;
+        }
 
-        let chan = self.worker_chan.0.clone();
-        let ctx = ctx.clone();
+        self.rename_file(filename, &new_name);
+        self.dialects.insert(new_name.clone(), dialect);
+        self.file_formats.insert(new_name.clone(), format);
+        self.dirty_files.remove(&new_name);
+        self.update_file_stamp(&new_name);
+        self.recent_files.touch(&new_name);
+        menu::update_recent_menu(&self.recent_files);
+        ctx.send_viewport_cmd(egui::ViewportCommand::Title(new_name.clone()));
+        let short_name = new_name.split('/').next_back().unwrap_or(&new_name);
+        crate::toast::show(ctx, format!("Saved as: {short_name}"));
+        true
+    }
 
-        thread::spawn(move || {
-            let master_data = reader
-                .records()
-                .filter_map(|record| record.ok())
-                .collect::<Vec<_>>();
+    /// Re-key everything known about `old` (data, views, tabs) to `new`.
+    fn rename_file(&mut self, old: &str, new: &str) {
+        if old == new {
+            return;
+        }
+        if let Some(data) = self.sheets_data.remove(old) {
+            self.sheets_data.insert(new.to_string(), data);
+        }
+        if let Some(dialect) = self.dialects.remove(old) {
+            self.dialects.insert(new.to_string(), dialect);
+        }
+        if let Some(overrides) = self.dialect_overrides.remove(old) {
+            self.dialect_overrides.insert(new.to_string(), overrides);
+        }
+        if let Some(stamp) = self.file_stamps.remove(old) {
+            self.file_stamps.insert(new.to_string(), stamp);
+        }
+        if let Some(format) = self.file_formats.remove(old) {
+            self.file_formats.insert(new.to_string(), format);
+        }
 
-            if let Err(e) = chan.send(UiMessage::SetMaster(master_data, file_name.clone())) {
-                eprintln!("Worker: Failed to send page data to UI thread: {:?}", e);
+        if self.dirty_files.remove(old) {
+            self.dirty_files.insert(new.to_string());
+        }
+        self.sql_sources.remove(old);
+        self.sql_edits.remove(old);
+        self.stale_files.remove(old);
+        self.following.remove(old);
+        self.watcher.unwatch(old);
+        self.watcher.watch(new);
+        let rekey = |key: (Filename, usize)| {
+            if key.0 == old {
+                (new.to_string(), key.1)
+            } else {
+                key
             }
+        };
+        self.filtered_data = self
+            .filtered_data
+            .drain()
+            .map(|(k, v)| (rekey(k), v))
+            .collect();
+        self.filtered_rows = self
+            .filtered_rows
+            .drain()
+            .map(|(k, v)| (rekey(k), v))
+            .collect();
+        self.filters = self.filters.drain().map(|(k, v)| (rekey(k), v)).collect();
+        self.facets = self.facets.drain().map(|(k, v)| (rekey(k), v)).collect();
+        self.files_list.retain(|f| f != new);
+        for file in self.files_list.iter_mut().filter(|f| *f == old) {
+            *file = new.to_string();
+        }
 
-            ctx.request_repaint();
-        });
+        for (_, tab) in self.tree.iter_all_tabs_mut() {
+            if tab.chosen_file == old {
+                tab.chosen_file = new.to_string();
+            }
+            if let Some(headers) = tab.columns.remove(old) {
+                tab.columns.insert(new.to_string(), headers);
+            }
+            if let Some((file, _)) = tab.profile_column.as_mut()
+                && file == old
+            {
+                *file = new.to_string();
+            }
+            if let Some((file, _)) = tab.facet_counts.as_mut()
+                && file == old
+            {
+                *file = new.to_string();
+            }
+            if let Some(pivot) = tab.pivot.as_mut()
+                && pivot.source_file == old
+            {
+                pivot.source_file = new.to_string();
+            }
+        }
     }
 
-    fn sort_current_sheet(
+    /// Write the rows and visible columns of a tab's current view to a new file.
+    fn export_view(
         &mut self,
         ctx: &egui::Context,
-        filename: String,
-        sort_order: (usize, SortOrder),
+        filename: &str,
         tab_id: usize,
+        spec: &ExportSpec,
     ) {
-        let chan = self.worker_chan.0.clone();
+        let Some(headers) = self
+            .tree
+            .iter_all_tabs()
+            .find(|(_, tab)| tab.id == tab_id)
+            .and_then(|(_, tab)| tab.columns.get(filename))
+            .cloned()
+        else {
+            return;
+        };
+        let filter_active = filter_active(
+            &self.global_filter,
+            &self.filters,
+            &self.facets,
+            filename,
+            tab_id,
+        );
+        let data = active_sheet_data(
+            &self.sheets_data,
+            &self.filtered_data,
+            filename,
+            tab_id,
+            filter_active,
+        );
+        let columns = visible_col_indices(&headers);
+        let dialect = self.dialects.get(filename).copied().unwrap_or_default();
 
-        for tab in self.tree.iter_all_tabs_mut() {
-            let sheet_tab = tab.1;
+        let path = Path::new(filename);
+        let stem = path
+            .file_stem()
+            .and_then(|n| n.to_str())
+            .unwrap_or("export");
+        let extension = spec.format.extension();
+        let Some(export_path) = rfd::FileDialog::new()
+            .add_filter(spec.format.label(), &[extension])
+            .set_file_name(format!("{stem} (view).{extension}"))
+            .set_directory(path.parent().unwrap_or(Path::new(".")))
+            .save_file()
+        else {
+            return;
+        };
+        let export_name = export_path.display().to_string();
 
-            if sheet_tab.id == tab_id {
-                let filter_active = self
-                    .filters
-                    .get(&(filename.to_string(), tab_id))
-                    .map_or(false, |f| !f.is_empty());
-
-                let sheet_data = active_sheet_data(
-                    &self.sheets_data,
-                    &self.filtered_data,
-                    &filename,
-                    tab_id,
-                    filter_active,
-                );
+        if let Err(e) = export(&export_name, spec, &headers, data, &columns, &dialect) {
+            eprintln!("Failed to export {}: {:?}", export_name, e);
+            crate::toast::show(ctx, format!("Could not export to {export_name}"));
+        } else {
+            let short_name = export_name.split('/').next_back().unwrap_or(&export_name);
+            crate::toast::show(ctx, format!("Exported {} rows to {short_name}", data.len()));
+        }
+    }
 
-                if !sheet_data.is_empty() {
-                    let master_clone = sheet_data.clone();
-                    let chan = chan.clone();
-                    let ctx = ctx.clone();
-                    let filename = filename.clone();
+    /// Read `filename` from disk again, dropping unsaved edits. Filters, facets and
+    /// sort are re-applied; column settings are kept unless the header changed.
+    pub fn reload_file(&mut self, ctx: &egui::Context, filename: &str) {
+        self.dirty_files.remove(filename);
+        self.stale_files.remove(filename);
+        let format = self.file_formats.get(filename).cloned().unwrap_or_default();
+        let dialect = self.file_dialect(filename);
+        let headers = match column_headers(filename, &format, &dialect) {
+            Ok(headers) => headers,
+            Err(e) => {
+                eprintln!("Failed to reload {}: {:?}", filename, e);
+                return;
+            }
+        };
+        self.dialects.insert(filename.to_string(), dialect);
+        // Columns of JSON Lines and worksheets arrive with the rows.
+        if !matches!(format, FileFormat::JsonLines | FileFormat::Spreadsheet) {
+            self.set_headers(filename, &headers);
+        }
 
-                    thread::spawn(move || {
-                        let sorted = sort_data(master_clone, sort_order);
+        self.read_master(ctx, filename.to_string(), format);
+    }
 
-                        if let Err(e) =
-                            chan.send(UiMessage::SetDisplayData(sorted, filename, tab_id))
-                        {
-                            eprintln!("Worker: Failed to send sorted data to UI thread: {:?}", e);
-                        }
+    /// Delete `rows` of the view of `tab_id` from the file. Views are rebuilt
+    /// and the selections of the file's tabs cleared, as row numbers shift.
+    fn delete_rows(&mut self, ctx: &egui::Context, filename: &str, tab_id: usize, rows: &[u64]) {
+        if self.sql_sources.contains_key(filename) {
+            crate::toast::show(ctx, "Rows of a database can't be deleted");
+            return;
+        }
+        let doomed = rows
+            .iter()
+            .filter_map(|&row| self.master_row(filename, tab_id, row as usize))
+            .collect::<HashSet<usize>>();
+        let Some(master) = self.sheets_data.get_mut(filename) else {
+            return;
+        };
+        let before = master.len();
+        let mut row = 0;
+        master.retain(|_| {
+            row += 1;
+            !doomed.contains(&(row - 1))
+        });
+        let deleted = before - master.len();
+        if deleted == 0 {
+            crate::toast::show(ctx, "No rows were deleted");
+            return;
+        }
 
-                        ctx.request_repaint();
-                    });
-                }
-            };
+        for (_, tab) in self.tree.iter_all_tabs_mut() {
+            if tab.chosen_file == filename {
+                tab.selection.clear();
+                tab.selection.anchor_cell = None;
+                tab.selection.selection_end = None;
+                tab.editing_cell = None;
+            }
         }
+        self.dirty_files.insert(filename.to_string());
+        self.reapply_views(ctx, filename);
+        self.invalidate_selection_stats();
+        self.refresh_profiles(ctx, filename, None);
+        self.refresh_facets(ctx, filename, None);
+        self.refresh_charts(ctx, filename, None);
+        let plural = if deleted == 1 { "" } else { "s" };
+        crate::toast::show(ctx, format!("Deleted {deleted} row{plural}"));
     }
 
-    fn filter_current_sheet(
+    /// Row of the master data shown at `row` of the view of `tab_id`. The rows of
+    /// a view SQLite built are looked up by content, which for a table includes
+    /// the rowid.
+    fn master_row(&self, filename: &str, tab_id: usize, row: usize) -> Option<usize> {
+        let key = (filename.to_string(), tab_id);
+        let Some(view) = self.filtered_data.get(&key) else {
+            return Some(row);
+        };
+        if let Some(rows) = self.filtered_rows.get(&key) {
+            return rows.get(row).copied();
+        }
+        let record = view.get(row)?;
+        self.sheets_data
+            .get(filename)?
+            .iter()
+            .position(|r| r == record)
+    }
+
+    /// Set a cell of the view of `tab_id`. The edit goes into the master data, so
+    /// saving writes it, and into every view showing that row.
+    fn edit_cell(
         &mut self,
         ctx: &egui::Context,
-        filename: String,
-        filter: String,
+        filename: &str,
         tab_id: usize,
+        row: usize,
+        col: usize,
+        value: &str,
     ) {
+        let Some(master_row) = self.master_row(filename, tab_id, row) else {
+            return;
+        };
+        let Some(record) = self.sheets_data.get_mut(filename).and_then(|master| {
+            edit_record(master, master_row, col, value).then(|| master[master_row].clone())
+        }) else {
+            return;
+        };
+        for ((file, tab), view) in self.filtered_data.iter_mut() {
+            if file != filename {
+                continue;
+            }
+            match self.filtered_rows.get(&(file.clone(), *tab)) {
+                Some(rows) => {
+                    for (shown, _) in rows.iter().enumerate().filter(|(_, r)| **r == master_row) {
+                        if let Some(slot) = view.get_mut(shown) {
+                            *slot = record.clone();
+                        }
+                    }
+                }
+                None if *tab == tab_id => {
+                    edit_record(view, row, col, value);
+                }
+                None => {}
+            }
+        }
+
+        // Tables of a database are saved by rowid, the hidden first column.
+        if self
+            .sql_sources
+            .get(filename)
+            .is_some_and(|source| source.table.is_some())
+            && let Some(rowid) = record.get(0).and_then(|rowid| rowid.parse::<i64>().ok())
+        {
+            self.sql_edits
+                .entry(filename.to_string())
+                .or_default()
+                .push((rowid, col, value.to_string()));
+        }
+
+        self.dirty_files.insert(filename.to_string());
+        self.invalidate_selection_stats();
+        self.refresh_profiles(ctx, filename, None);
+        self.refresh_facets(ctx, filename, None);
+        self.refresh_charts(ctx, filename, None);
+    }
+
+    /// Reload `filename`, first asking what to do with its unsaved edits if it
+    /// has any. While another prompt is open the file is left as it is.
+    pub fn request_reload(&mut self, ctx: &egui::Context, filename: &str) {
+        if !self.dirty_files.contains(filename) {
+            self.reload_file(ctx, filename);
+        } else if self.close_prompt.is_none() {
+            self.close_prompt = Some(ClosePrompt::Reload(filename.to_string()));
+        }
+    }
+
+    /// The watcher saw `filename` change: read appended rows when it's followed,
+    /// otherwise offer a reload unless the change is our own save.
+    fn file_changed(&mut self, ctx: &egui::Context, filename: Filename) {
+        if !self.files_list.contains(&filename) {
+            return;
+        }
+        if self.following.contains(&filename) {
+            self.read_appended(ctx, filename);
+            return;
+        }
+        if self.stale_files.contains(&filename) {
+            return;
+        }
+        let changed = self
+            .file_stamps
+            .get(&filename)
+            .is_none_or(|stamp| stamp.changed_on_disk(&filename));
+        if changed {
+            let short_name = filename.split('/').next_back().unwrap_or(&filename);
+            crate::toast::show(ctx, format!("{short_name} changed on disk"));
+            self.stale_files.insert(filename);
+        }
+    }
+
+    pub fn set_follow(&mut self, ctx: &egui::Context, filename: Filename, follow: bool) {
+        if !follow {
+            self.following.remove(&filename);
+            return;
+        }
+        self.following.insert(filename.clone());
+        self.scroll_to_bottom(&filename);
+        if self.stale_files.contains(&filename) {
+            self.read_appended(ctx, filename);
+        }
+    }
+
+    /// Parse the rows appended to a followed file since it was last read. A file
+    /// that got shorter was rewritten rather than appended to, and is reloaded;
+    /// with unsaved edits it is marked changed on disk and the user is asked.
+    fn read_appended(&mut self, ctx: &egui::Context, filename: Filename) {
+        let Some(before) = self.file_stamps.get(&filename).cloned() else {
+            return;
+        };
+        let offset = before.len;
+        if self.tailing.contains(&filename) {
+            return;
+        }
+        self.stale_files.remove(&filename);
+        if std::fs::metadata(&filename).is_ok_and(|m| m.len() < offset) {
+            if self.dirty_files.contains(&filename) {
+                self.stale_files.insert(filename.clone());
+            }
+            self.request_reload(ctx, &filename);
+            return;
+        }
+
+        self.tailing.insert(filename.clone());
+        let dialect = self.dialects.get(&filename).copied().unwrap_or_default();
+        let format = self
+            .file_formats
+            .get(&filename)
+            .cloned()
+            .unwrap_or_default();
+        let names = self
+            .tree
+            .iter_all_tabs()
+            .find_map(|(_, tab)| tab.columns.get(&filename))
+            .map(|headers| headers.iter().map(|h| h.name.clone()).collect::<Vec<_>>())
+            .unwrap_or_default();
         let chan = self.worker_chan.0.clone();
+        let ctx = ctx.clone();
 
-        for tab in self.tree.iter_all_tabs_mut() {
-            let sheet_tab = tab.1;
-            let filter = filter.clone();
+        thread::spawn(move || {
+            // Only the appended bytes are hashed and parsed, however large the file.
+            let appended = before.extend(&filename).and_then(|stamp| {
+                let (rows, end) = read_appended(&filename, offset, &format, &dialect, &names)?;
+                Ok((rows, stamp.read_to(end)))
+            });
+            // On failure the stamp stays as it was, and the next change tries again.
+            let (rows, stamp) = appended.unwrap_or_else(|e| {
+                eprintln!("Failed to read rows appended to {}: {:?}", filename, e);
+                (Vec::new(), before)
+            });
 
-            if sheet_tab.id == tab_id {
-                if let Some(master_data) = self.sheets_data.get(&filename) {
-                    let master_clone = master_data.clone();
-                    let chan = chan.clone();
-                    let ctx = ctx.clone();
-                    let filename = filename.clone();
+            if let Err(e) = chan.send(UiMessage::AppendRows(filename, rows, stamp)) {
+                eprintln!("Worker: Failed to send appended rows to UI thread: {:?}", e);
+            }
 
-                    thread::spawn(move || {
-                        let filtered = filter_data(master_clone, filter);
+            ctx.request_repaint();
+        });
+    }
 
-                        if let Err(e) =
-                            chan.send(UiMessage::SetDisplayData(filtered, filename, tab_id))
-                        {
-                            eprintln!("Worker: Failed to send filtered data to UI thread: {:?}", e);
-                        }
+    fn append_rows(
+        &mut self,
+        ctx: &egui::Context,
+        filename: Filename,
+        rows: SheetVec,
+        stamp: FileStamp,
+    ) {
+        self.tailing.remove(&filename);
+        if !self.files_list.contains(&filename) {
+            return;
+        }
+        let len = stamp.len;
+        self.file_stamps.insert(filename.clone(), stamp);
 
-                        ctx.request_repaint();
-                    });
-                }
+        if rows.is_empty() {
+            return;
+        }
+        let master = self.sheets_data.entry(filename.clone()).or_default();
+        let first = master.len();
+        master.extend(rows);
+        self.extend_views(ctx, &filename, first);
+        self.invalidate_selection_stats();
+        self.refresh_profiles(ctx, &filename, None);
+        self.refresh_facets(ctx, &filename, None);
+        self.refresh_charts(ctx, &filename, None);
+        self.scroll_to_bottom(&filename);
+
+        // More may have been written while these rows were being read. A partial
+        // last line isn't retried until the watcher sees the file change again.
+        if self.following.contains(&filename)
+            && std::fs::metadata(&filename).is_ok_and(|m| m.len() > len)
+        {
+            self.read_appended(ctx, filename);
+        }
+    }
+
+    /// Add the master rows from `first` on to the views of the tabs showing
+    /// `filename`, leaving the rows they already have as they are. A view that
+    /// is still being built is built again.
+    fn extend_views(&mut self, ctx: &egui::Context, filename: &str, first: usize) {
+        let Some(master_data) = self.sheets_data.get(filename) else {
+            return;
+        };
+        let no_facets = Facets::new();
+        let mut rebuild = false;
+        for (_, tab) in self.tree.iter_all_tabs_mut() {
+            if tab.chosen_file != filename {
+                continue;
+            }
+            let key = (filename.to_string(), tab.id);
+            let filter = self.filters.get(&key).map_or("", String::as_str);
+            let facets = self.facets.get(&key).unwrap_or(&no_facets);
+            let sort = tab.columns.get(filename).and_then(|headers| {
+                headers
+                    .iter()
+                    .enumerate()
+                    .find_map(|(col, h)| Some((col, h.sort?)))
+            });
+            let (Some(view), Some(rows)) = (
+                self.filtered_data.get_mut(&key),
+                self.filtered_rows.get_mut(&key),
+            ) else {
+                rebuild |= !filter.is_empty() || !facets.is_empty() || sort.is_some();
+                continue;
             };
+
+            let shown = view.len();
+            let added = extend_view(master_data, view, rows, first, filter, facets, sort);
+            if added.first().is_some_and(|&earlier| earlier < shown) {
+                let moved = |row: u64| {
+                    let shift = added.partition_point(|&earlier| earlier <= row as usize);
+                    Some(row + shift as u64)
+                };
+                tab.selection.move_rows(moved);
+                tab.editing_cell = tab
+                    .editing_cell
+                    .and_then(|(row, col)| Some((moved(row)?, col)));
+            }
+        }
+        if rebuild {
+            self.reapply_views(ctx, filename);
+        }
+    }
+
+    fn scroll_to_bottom(&mut self, filename: &str) {
+        for (_, tab) in self.tree.iter_all_tabs_mut() {
+            if tab.chosen_file == filename && tab.chart.is_none() {
+                tab.scroll_to_bottom = true;
+            }
+        }
+    }
+
+    fn close_tab(&mut self, tab_id: usize) {
+        if let Some(path) = self.tree.find_tab_from(|tab| tab.id == tab_id) {
+            self.tree.remove_tab(path);
+        }
+    }
+
+    /// Act on tabs whose close was held back because their file has unsaved edits:
+    /// close right away when another tab still shows the file, otherwise ask.
+    fn handle_close_requests(&mut self, requests: Vec<(usize, Filename)>) {
+        for (tab_id, filename) in requests {
+            let other_views = self.tree.iter_all_tabs().any(|(_, tab)| {
+                tab.id != tab_id && tab.chart.is_none() && tab.chosen_file == filename
+            });
+            if other_views {
+                self.close_tab(tab_id);
+            } else if self.close_prompt.is_none() {
+                self.close_prompt = Some(ClosePrompt::Tab(tab_id, filename));
+            }
+        }
+    }
+
+    fn handle_close_prompt(&mut self, ctx: &egui::Context) {
+        if ctx.input(|i| i.viewport().close_requested())
+            && !self.exit_confirmed
+            && !self.dirty_files.is_empty()
+        {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            let mut files = self.dirty_files.iter().cloned().collect::<Vec<_>>();
+            files.sort();
+            self.close_prompt = Some(ClosePrompt::App(files));
+        }
+
+        let Some(choice) = self
+            .close_prompt
+            .as_ref()
+            .and_then(|p| close_prompt::show(ctx, p))
+        else {
+            return;
+        };
+        let Some(prompt) = self.close_prompt.take() else {
+            return;
+        };
+
+        match (prompt, choice) {
+            (_, CloseChoice::Cancel) => {}
+            (ClosePrompt::Tab(tab_id, filename), CloseChoice::Save) => {
+                if self.save_file(ctx, &filename) {
+                    self.close_tab(tab_id);
+                }
+            }
+            (ClosePrompt::Tab(tab_id, filename), CloseChoice::Discard) => {
+                self.reload_file(ctx, &filename);
+                self.close_tab(tab_id);
+            }
+            (ClosePrompt::Reload(filename), CloseChoice::Save) => {
+                if self.save_file(ctx, &filename) {
+                    self.reload_file(ctx, &filename);
+                }
+            }
+            (ClosePrompt::Reload(filename), CloseChoice::Discard) => {
+                self.reload_file(ctx, &filename);
+            }
+            (ClosePrompt::App(files), CloseChoice::Save) => {
+                let saved = files.iter().filter(|f| self.save_file(ctx, f)).count();
+                if saved == files.len() {
+                    self.exit_confirmed = true;
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                }
+            }
+            (ClosePrompt::App(_), CloseChoice::Discard) => {
+                self.exit_confirmed = true;
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            }
+        }
+    }
+
+    fn handle_overwrite_prompt(&mut self, ctx: &egui::Context) {
+        let Some(overwrite) = self
+            .overwrite_prompts
+            .first()
+            .and_then(|filename| overwrite_prompt::show(ctx, filename))
+        else {
+            return;
+        };
+        let filename = self.overwrite_prompts.remove(0);
+        if overwrite {
+            self.write_file(ctx, &filename);
         }
     }
-}
 
-impl MyApp {
     fn subsecond_fn(&mut self, ctx: &egui::Context) {
         subsecond::call(|| {
             self.update_inner(ctx);
@@ -150,9 +1899,14 @@ impl MyApp {
         // Handle macOS menu events
         #[cfg(target_os = "macos")]
         if let Ok(event) = MenuEvent::receiver().try_recv() {
-            if event.id.as_ref() == OPEN_FILE_ID {
+            if let Some(path) = event.id.as_ref().strip_prefix(menu::OPEN_RECENT_PREFIX) {
+                let _ = self
+                    .worker_chan
+                    .0
+                    .send(UiMessage::OpenFile(path.to_string(), None));
+            } else if event.id.as_ref() == OPEN_FILE_ID {
                 if let Some(path) = rfd::FileDialog::new()
-                    .add_filter("CSV", &["csv"])
+                    .add_filter("Tables", &jonathan_core::readers::OPENABLE_EXTENSIONS)
                     .pick_file()
                 {
                     if let Some(path_str) = path.to_str() {
@@ -174,12 +1928,148 @@ impl MyApp {
         }
 
         while let Ok(message) = self.worker_chan.1.try_recv() {
+            let Some(message) = self.defer_until_loaded(ctx, message) else {
+                continue;
+            };
             match message {
                 UiMessage::SetMaster(master, file_name) => {
-                    self.sheets_data.insert(file_name, master);
+                    self.sheets_data.insert(file_name.clone(), master);
+                    self.reapply_views(ctx, &file_name);
+                    self.invalidate_selection_stats();
+                    self.refresh_profiles(ctx, &file_name, None);
+                    self.refresh_facets(ctx, &file_name, None);
+                    self.refresh_charts(ctx, &file_name, None);
+                }
+                UiMessage::SetHeaders(file_name, headers) => {
+                    self.set_headers(&file_name, &headers);
+                }
+                UiMessage::SetFixedWidth(file_name, starts) => {
+                    self.file_formats
+                        .insert(file_name.clone(), FileFormat::FixedWidth(starts));
+                    self.reload_file(ctx, &file_name);
+                }
+                UiMessage::SetFileStamp(file_name, stamp) => {
+                    self.file_stamps.insert(file_name, stamp);
+                }
+                UiMessage::FileChanged(file_name) => {
+                    self.file_changed(ctx, file_name);
+                }
+                UiMessage::ReloadFile(file_name) => {
+                    self.request_reload(ctx, &file_name);
                 }
-                UiMessage::SetDisplayData(sorted, file_name, tab_id) => {
-                    self.filtered_data.insert((file_name, tab_id), sorted);
+                UiMessage::SetFollow(file_name, follow) => {
+                    self.set_follow(ctx, file_name, follow);
+                }
+                UiMessage::AppendRows(file_name, rows, stamp) => {
+                    self.append_rows(ctx, file_name, rows, stamp);
+                }
+                UiMessage::SetRowGroup(file_name, group, rows) => {
+                    self.set_row_group(ctx, file_name, group, rows);
+                }
+                UiMessage::SetDisplayData(sorted, rows, file_name, tab_id) => {
+                    // Without row numbers nothing can be followed, as if every row went.
+                    self.move_selection(
+                        &file_name,
+                        tab_id,
+                        Some(rows.as_deref().unwrap_or_default()),
+                    );
+                    let key = (file_name.clone(), tab_id);
+                    match rows {
+                        Some(rows) => self.filtered_rows.insert(key.clone(), rows),
+                        None => self.filtered_rows.remove(&key),
+                    };
+                    self.filtered_data.insert(key, sorted);
+                    if self.following.contains(&file_name) {
+                        self.scroll_to_bottom(&file_name);
+                    }
+                    self.invalidate_selection_stats();
+                    self.refresh_profiles(ctx, &file_name, Some(tab_id));
+                    self.refresh_facets(ctx, &file_name, Some(tab_id));
+                    self.refresh_charts(ctx, &file_name, Some(tab_id));
+                }
+                UiMessage::OpenPivot(filename, tab_id, spec) => {
+                    self.open_pivot(ctx, filename, tab_id, spec);
+                }
+                UiMessage::SetPivot(headers, rows, source) => {
+                    self.set_pivot(headers, rows, source);
+                }
+                UiMessage::OpenDrillDown(filename, filter, facets) => {
+                    self.open_drill_down(ctx, filename, filter, facets);
+                }
+                UiMessage::SaveAs(filename) => {
+                    self.save_file_as(ctx, &filename);
+                }
+                UiMessage::ExportView(filename, tab_id, spec) => {
+                    self.export_view(ctx, &filename, tab_id, &spec);
+                }
+                UiMessage::OpenChart(filename, tab_id) => {
+                    self.open_chart(ctx, filename, tab_id);
+                }
+                UiMessage::RefreshChart(tab_id) => {
+                    self.refresh_chart(ctx, tab_id);
+                }
+                UiMessage::SetChart(tab_id, data) => {
+                    for (_, tab) in self.tree.iter_all_tabs_mut() {
+                        if tab.id == tab_id
+                            && let Some(chart) = tab.chart.as_mut()
+                        {
+                            chart.data = Some(data.clone());
+                        }
+                    }
+                }
+                UiMessage::SelectRows(tab_id, rows) => {
+                    for (_, tab) in self.tree.iter_all_tabs_mut() {
+                        if tab.id == tab_id {
+                            tab.selection.select_rows(rows.iter().copied());
+                        }
+                    }
+                    self.invalidate_selection_stats();
+                }
+                UiMessage::RefreshFacets(filename, tab_id) => {
+                    self.refresh_facets(ctx, &filename, Some(tab_id));
+                }
+                UiMessage::SetFacetCounts(filename, tab_id, counts) => {
+                    for (_, tab) in self.tree.iter_all_tabs_mut() {
+                        if tab.id == tab_id && tab.chosen_file == filename {
+                            tab.facet_counts = Some((filename.clone(), counts.clone()));
+                        }
+                    }
+                }
+                UiMessage::ProfileColumn(filename, tab_id, column) => {
+                    self.profile_column(ctx, filename, tab_id, column);
+                }
+                UiMessage::SetProfile(filename, tab_id, profile) => {
+                    // The profile is of the tab's view, so its type stays out of
+                    // the file's headers.
+                    for (_, tab) in self.tree.iter_all_tabs_mut() {
+                        if tab.id == tab_id
+                            && tab.profile_column == Some((filename.clone(), profile.column))
+                        {
+                            tab.profile = Some(profile.clone());
+                        }
+                    }
+                }
+                UiMessage::FacetSheet(filename, tab_id, column, values) => {
+                    let facets = self.facets.entry((filename.clone(), tab_id)).or_default();
+                    if values.is_empty() {
+                        facets.remove(&column);
+                    } else {
+                        facets.insert(column, values);
+                    }
+                    let filter = self
+                        .filters
+                        .get(&(filename.clone(), tab_id))
+                        .cloned()
+                        .unwrap_or_default();
+                    self.filter_current_sheet(ctx, filename, filter, tab_id);
+                }
+                UiMessage::SetSelectionStats(tab_id, generation, stats) => {
+                    for (_, tab) in self.tree.iter_all_tabs_mut() {
+                        if tab.id == tab_id && tab.selection.generation == generation {
+                            tab.selection_stats = Some((generation, stats.clone()));
+                            tab.stats_pending = None;
+                        }
+                    }
                 }
                 UiMessage::FilterGlobal(filter) => {
                     self.global_filter = filter;
@@ -193,70 +2083,59 @@ impl MyApp {
                     self.sort_current_sheet(ctx, filename, sort_order, tab_id);
                 }
                 UiMessage::OpenFile(file, tab) => self.load_file(ctx, file, tab),
+                UiMessage::OpenQuery(path, query, tab) => self.open_query(ctx, path, query, tab),
+                UiMessage::OpenInNewTab(file) => {
+                    let tab_id = self.push_tab(file.clone());
+                    self.load_file(ctx, file, Some(tab_id));
+                }
+                UiMessage::DeleteRows(filename, tab_id, rows) => {
+                    self.delete_rows(ctx, &filename, tab_id, &rows);
+                }
                 UiMessage::EditCell(filename, tab_id, row_nr, actual_col, new_value) => {
-                    let updated = self
-                        .filtered_data
-                        .get_mut(&(filename.clone(), tab_id))
-                        .map_or(false, |sheet| {
-                            edit_record(sheet, row_nr as usize, actual_col, &new_value)
-                        });
-
-                    if !updated {
-                        if let Some(sheet) = self.sheets_data.get_mut(&filename) {
-                            edit_record(sheet, row_nr as usize, actual_col, &new_value);
-                        }
-                    }
-
-                    self.dirty_files.insert(filename);
+                    self.edit_cell(
+                        ctx,
+                        &filename,
+                        tab_id,
+                        row_nr as usize,
+                        actual_col,
+                        &new_value,
+                    );
                 }
             }
         }
 
         let mut added_nodes = Vec::new();
+        let mut close_requests = Vec::new();
 
         let tabs_no = self.tree.iter_all_tabs().count();
-        let focused_tab = self.tree.find_active_focused().map(|(_, tab)| tab.id);
-
-        let save_file = ctx.input(|i| {
-            i.modifiers.command && i.key_pressed(Key::S)
-        }).then(|| {
-            self.tree.find_active_focused().and_then(|(_, tab)| {
-                let f = tab.chosen_file.clone();
-                if f.is_empty() { None } else { Some(f) }
-            })
-        }).flatten();
-
-        if let Some(filename) = save_file {
-            if let Some(data) = self.sheets_data.get(&filename) {
-                let headers = self.tree.iter_all_tabs()
-                    .find_map(|(_, tab)| tab.columns.get(&filename))
-                    .cloned()
-                    .unwrap_or_default();
-                if let Err(e) = write_csv(&filename, &headers, data) {
-                    eprintln!("Failed to save {}: {:?}", filename, e);
-                } else {
-                    self.dirty_files.remove(&filename);
-                    let short_name = filename.split('/').last().unwrap_or(&filename).to_string();
-                    crate::toast::show(ctx, format!("Saved: {short_name}"));
-                }
+
+        if self.keymap.pressed(ctx, Action::CommandPalette) && self.command_palette.take().is_none()
+        {
+            self.command_palette = Some(CommandPalette::default());
+        }
+        if self.command_palette.is_some() {
+            let entries = self.palette_entries();
+            if let Some(command) = command_palette::show(ctx, &mut self.command_palette, &entries) {
+                self.run_command(ctx, command);
+            }
+        }
+
+        // Keys typed into the palette were taken out of the input above; the
+        // tables handle their own keys.
+        for action in Action::ALL {
+            if action != Action::CommandPalette
+                && !action.in_table()
+                && self.keymap.pressed(ctx, action)
+            {
+                self.run_action(ctx, action);
             }
         }
 
         crate::toast::render(ctx);
 
-        egui::TopBottomPanel::top("top_panel").show(ctx, |_ui| {
-            ctx.input(|input| {
-                if input.key_pressed(Key::X) {
-                    if let Err(e) = &self
-                        .worker_chan
-                        .0
-                        .send(UiMessage::FilterGlobal("".to_string()))
-                    {
-                        eprintln!("Worker: Failed to send page data to UI thread: {:?}", e);
-                    }
-                }
-            });
-        });
+        self.show_status_bar(ctx);
+
+        let recent_before = self.recent_files.entries.clone();
 
         DockArea::new(&mut self.tree)
             .style(Style::from_egui(ctx.style().as_ref()))
@@ -272,13 +2151,31 @@ impl MyApp {
                     sender: &self.worker_chan.0,
                     files_list: &self.files_list,
                     tabs_no,
-                    focused_tab,
                     global_filter: &self.global_filter,
                     filters: &mut self.filters,
+                    facets: &self.facets,
                     dirty_files: &self.dirty_files,
+                    stale_files: &self.stale_files,
+                    file_formats: &self.file_formats,
+                    following: &self.following,
+                    copy_format: &mut self.copy_format,
+                    keep_backups: &mut self.keep_backups,
+                    recent_files: &mut self.recent_files,
+                    close_requests: &mut close_requests,
+                    keymap: &self.keymap,
                 },
             );
 
+        self.load_visible_row_groups(ctx);
+        self.handle_close_requests(close_requests);
+        self.handle_close_prompt(ctx);
+        self.handle_overwrite_prompt(ctx);
+        sheet_picker::show(ctx, &mut self.sheet_picker, &self.worker_chan.0);
+
+        if self.recent_files.entries != recent_before {
+            menu::update_recent_menu(&self.recent_files);
+        }
+
         added_nodes.drain(..).for_each(|(surface, node, filename)| {
             self.tree.set_focused_node_and_surface((surface, node));
 
@@ -329,9 +2226,22 @@ impl MyApp {
 }
 
 impl eframe::App for MyApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, SESSION_KEY, &self.session());
+        eframe::set_value(storage, RECENT_FILES_KEY, &self.recent_files);
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         subsecond::call(|| {
             self.subsecond_fn(ctx);
         });
     }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        for file in &self.stdin_files {
+            if let Err(e) = std::fs::remove_file(file) {
+                eprintln!("Failed to delete {}: {:?}", file, e);
+            }
+        }
+    }
 }
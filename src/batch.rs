@@ -0,0 +1,155 @@
+use jonathan_core::columnar::write_parquet;
+use jonathan_core::export::{ExportFormat, ExportSpec};
+use jonathan_core::readers::{FileFormat, detect_format, sheet_file, split_sheet};
+use jonathan_core::spreadsheet::sheet_names;
+use jonathan_core::sqlite::table_names;
+use jonathan_core::{Dataset, View};
+use std::io;
+use std::path::Path;
+
+use crate::cli::{QueryArgs, ReadArgs, find_column, read_stdin, sort_column, view_filters};
+use crate::types::{ColumnId, visible_col_indices};
+
+/// `jonathan query`: read a file, keep the rows and columns asked for and write
+/// them out, without a window. Rows are picked and sorted exactly as a tab would
+/// show them.
+pub fn run(args: &QueryArgs) -> Result<(), String> {
+    with_input(&args.input, |input| query(input, args))
+}
+
+/// Run `f` on `input`, or for `-` on a temporary copy of standard input.
+pub fn with_input<T>(input: &str, f: impl FnOnce(&str) -> Result<T, String>) -> Result<T, String> {
+    if input != "-" {
+        return f(input);
+    }
+    let input = read_stdin().map_err(|e| format!("cannot read standard input: {e}"))?;
+    let result = f(&input);
+    if let Err(e) = std::fs::remove_file(&input) {
+        eprintln!("Failed to remove {}: {:?}", input, e);
+    }
+    result
+}
+
+/// A file read whole as the viewer reads it; a workbook from its first worksheet.
+pub fn load(input: &str, read: &ReadArgs) -> Result<Dataset, String> {
+    let input = choose_sheet(input, &detect_format(input))?;
+    Dataset::open_with(&input, read.overrides()).map_err(|e| format!("cannot read {input}: {e}"))
+}
+
+fn query(input: &str, args: &QueryArgs) -> Result<(), String> {
+    let dataset = load(input, &args.read)?;
+    let headers = &dataset.headers;
+
+    let (filter, facets) = view_filters(&args.view.filter, headers)?;
+    let sort = args
+        .view
+        .sort
+        .as_deref()
+        .map(|spec| sort_column(spec, headers))
+        .transpose()?;
+    let view = View {
+        filter,
+        facets,
+        sort,
+    };
+
+    let columns = if args.columns.is_empty() {
+        visible_col_indices(headers)
+    } else {
+        args.columns
+            .iter()
+            .map(|name| find_column(headers, name))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+    write_rows(args, &dataset, &view, &columns)
+}
+
+/// A workbook is read from its first worksheet unless one is named as in
+/// `book.xlsx#Sheet`; a database needs its table named.
+fn choose_sheet(input: &str, format: &FileFormat) -> Result<String, String> {
+    if split_sheet(input).1.is_some() {
+        return Ok(input.to_string());
+    }
+    match format {
+        FileFormat::Spreadsheet => {
+            let sheets = sheet_names(input).map_err(|e| format!("cannot open {input}: {e}"))?;
+            let first = sheets
+                .first()
+                .ok_or_else(|| format!("{input} has no worksheets"))?;
+            Ok(sheet_file(input, first))
+        }
+        FileFormat::Sqlite(_) => {
+            let tables = table_names(input).map_err(|e| format!("cannot open {input}: {e}"))?;
+            Err(format!(
+                "name the table to read as {}; {input} has: {}",
+                sheet_file(input, "TABLE"),
+                tables.join(", ")
+            ))
+        }
+        _ => Ok(input.to_string()),
+    }
+}
+
+/// Write to `--out`, or standard output, in `--format`, else the format the
+/// extension of `--out` names, else CSV in the dialect of the input.
+fn write_rows(
+    args: &QueryArgs,
+    dataset: &Dataset,
+    view: &View,
+    columns: &[ColumnId],
+) -> Result<(), String> {
+    let extension = args
+        .out
+        .as_deref()
+        .and_then(|out| Path::new(out).extension())
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    let name = args.format.clone().or(extension).unwrap_or_default();
+
+    if name == "parquet" || name == "pq" {
+        let Some(out) = &args.out else {
+            return Err("Parquet can only be written to a file, see --out".to_string());
+        };
+        let headers = columns
+            .iter()
+            .map(|&col| dataset.headers[col].clone())
+            .collect::<Vec<_>>();
+        let rows = view
+            .rows(dataset)
+            .iter()
+            .map(|record| {
+                columns
+                    .iter()
+                    .map(|&col| record.get(col).unwrap_or_default())
+                    .collect()
+            })
+            .collect();
+        return write_parquet(out, &headers, &rows).map_err(|e| format!("cannot write {out}: {e}"));
+    }
+
+    let format = match ExportFormat::ALL
+        .into_iter()
+        .find(|f| f.extension() == name)
+    {
+        Some(format) => format,
+        None if args.format.is_some() => return Err(format!("unknown format `{name}`")),
+        None => ExportFormat::Csv,
+    };
+    let table_name = Path::new(split_sheet(&dataset.path).0)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("data");
+    let spec = ExportSpec {
+        format,
+        table_name: table_name.to_string(),
+        ..ExportSpec::default()
+    };
+    match &args.out {
+        Some(out) => view
+            .export(dataset, out, &spec, columns)
+            .map_err(|e| format!("cannot write {out}: {e}")),
+        None => view
+            .write(dataset, &mut io::stdout().lock(), &spec, columns)
+            .map_err(|e| format!("cannot write to standard output: {e}")),
+    }
+}
@@ -0,0 +1,205 @@
+use jonathan_core::profile::{HISTOGRAM_BINS, histogram, is_null_like, parse_date};
+use jonathan_core::stats::parse_number;
+use std::collections::HashMap;
+
+use crate::types::{ColumnId, SheetVec, TabId};
+
+/// Value-count charts keep the most frequent values only.
+pub const MAX_BARS: usize = 30;
+/// Scatter and line charts draw at most this many points (every point can still be brushed).
+pub const MAX_DRAWN_POINTS: usize = 20_000;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum ChartKind {
+    /// Numeric `x` column split into equal-width bins
+    #[default]
+    Histogram,
+    /// Most frequent values of `x`
+    ValueCounts,
+    /// Numeric `x` against numeric `y`
+    Scatter,
+    /// Numeric `y` over date column `x`
+    Line,
+}
+
+impl ChartKind {
+    pub const ALL: [ChartKind; 4] = [
+        ChartKind::Histogram,
+        ChartKind::ValueCounts,
+        ChartKind::Scatter,
+        ChartKind::Line,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ChartKind::Histogram => "Histogram",
+            ChartKind::ValueCounts => "Value counts",
+            ChartKind::Scatter => "Scatter",
+            ChartKind::Line => "Line over date",
+        }
+    }
+
+    /// Whether the chart plots a second column.
+    pub fn uses_y(&self) -> bool {
+        matches!(self, ChartKind::Scatter | ChartKind::Line)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChartSpec {
+    pub kind: ChartKind,
+    pub x: ColumnId,
+    pub y: ColumnId,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChartBar {
+    pub label: String,
+    pub start: f64,
+    pub end: f64,
+    /// Rows of the source view counted in this bar
+    pub rows: Vec<u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChartData {
+    pub spec: ChartSpec,
+    pub bars: Vec<ChartBar>,
+    /// Scatter/line points, with `point_rows[i]` the source view row of `points[i]`
+    pub points: Vec<[f64; 2]>,
+    pub point_rows: Vec<u64>,
+    /// Rows left out because a value was missing or didn't parse
+    pub skipped: usize,
+}
+
+/// State of a dock tab that charts another tab's current view.
+#[derive(Debug, Clone, Default)]
+pub struct ChartTab {
+    pub source_tab: TabId,
+    pub spec: ChartSpec,
+    /// Latest result for `spec`, `None` while being computed
+    pub data: Option<ChartData>,
+    /// Plot coordinates where the current brush drag started
+    pub brush_start: Option<[f64; 2]>,
+}
+
+impl ChartData {
+    /// Source rows of the bars overlapping `[min_x, max_x]`.
+    pub fn rows_in_bars(&self, min_x: f64, max_x: f64) -> Vec<u64> {
+        self.bars
+            .iter()
+            .filter(|b| b.end >= min_x && b.start <= max_x)
+            .flat_map(|b| b.rows.iter().copied())
+            .collect()
+    }
+
+    /// Source rows of the points inside the rectangle spanned by `a` and `b`.
+    pub fn rows_in_rect(&self, a: [f64; 2], b: [f64; 2]) -> Vec<u64> {
+        let (min_x, max_x) = (a[0].min(b[0]), a[0].max(b[0]));
+        let (min_y, max_y) = (a[1].min(b[1]), a[1].max(b[1]));
+        self.points
+            .iter()
+            .zip(&self.point_rows)
+            .filter(|(p, _)| (min_x..=max_x).contains(&p[0]) && (min_y..=max_y).contains(&p[1]))
+            .map(|(_, row)| *row)
+            .collect()
+    }
+}
+
+fn numbers(data: &SheetVec, column: ColumnId) -> (Vec<f64>, Vec<u64>) {
+    data.iter()
+        .enumerate()
+        .filter_map(|(row, record)| {
+            parse_number(record.get(column).unwrap_or_default()).map(|n| (n, row as u64))
+        })
+        .unzip()
+}
+
+fn histogram_bars(data: &SheetVec, column: ColumnId) -> Vec<ChartBar> {
+    let (values, rows) = numbers(data, column);
+    let bins = histogram(&values, HISTOGRAM_BINS);
+    let mut bars = bins
+        .iter()
+        .map(|bin| ChartBar {
+            label: format!("{} – {}", bin.start, bin.end),
+            start: bin.start,
+            end: bin.end,
+            rows: Vec::with_capacity(bin.count),
+        })
+        .collect::<Vec<_>>();
+    for (value, row) in values.iter().zip(rows) {
+        // Same bucketing as `histogram`; the last bin is closed on the right.
+        if let Some(i) = bars.iter().position(|b| *value < b.end) {
+            bars[i].rows.push(row);
+        } else if let Some(last) = bars.last_mut() {
+            last.rows.push(row);
+        }
+    }
+    bars
+}
+
+fn value_count_bars(data: &SheetVec, column: ColumnId) -> (Vec<ChartBar>, usize) {
+    let mut rows_by_value: HashMap<&str, Vec<u64>> = HashMap::new();
+    for (row, record) in data.iter().enumerate() {
+        rows_by_value
+            .entry(record.get(column).unwrap_or_default())
+            .or_default()
+            .push(row as u64);
+    }
+    let mut values = rows_by_value.into_iter().collect::<Vec<_>>();
+    values.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(b.0)));
+    let skipped = values.iter().skip(MAX_BARS).map(|(_, r)| r.len()).sum();
+
+    let bars = values
+        .into_iter()
+        .take(MAX_BARS)
+        .enumerate()
+        .map(|(i, (value, rows))| ChartBar {
+            label: value.to_string(),
+            start: i as f64 - 0.5,
+            end: i as f64 + 0.5,
+            rows,
+        })
+        .collect();
+    (bars, skipped)
+}
+
+/// Compute what a chart of `data` (a tab's current view) shows.
+pub fn chart_data(data: &SheetVec, spec: &ChartSpec) -> ChartData {
+    let mut out = ChartData {
+        spec: spec.clone(),
+        ..Default::default()
+    };
+
+    match spec.kind {
+        ChartKind::Histogram => {
+            out.bars = histogram_bars(data, spec.x);
+            out.skipped = data.len() - out.bars.iter().map(|b| b.rows.len()).sum::<usize>();
+        }
+        ChartKind::ValueCounts => {
+            (out.bars, out.skipped) = value_count_bars(data, spec.x);
+        }
+        ChartKind::Scatter | ChartKind::Line => {
+            let x_value = |value: &str| match spec.kind {
+                ChartKind::Line => parse_date(value).map(|d| d.and_utc().timestamp() as f64),
+                _ => parse_number(value),
+            };
+            let mut points = data
+                .iter()
+                .enumerate()
+                .filter_map(|(row, record)| {
+                    let x = record.get(spec.x).filter(|v| !is_null_like(v))?;
+                    let y = record.get(spec.y).filter(|v| !is_null_like(v))?;
+                    Some(([x_value(x)?, parse_number(y)?], row as u64))
+                })
+                .collect::<Vec<_>>();
+            if spec.kind == ChartKind::Line {
+                points.sort_by(|a, b| a.0[0].total_cmp(&b.0[0]));
+            }
+            out.skipped = data.len() - points.len();
+            (out.points, out.point_rows) = points.into_iter().unzip();
+        }
+    }
+
+    out
+}
@@ -0,0 +1,251 @@
+use clap::{Parser, Subcommand};
+use eframe::egui;
+use encoding_rs::Encoding;
+use jonathan_core::read_csv::DialectOverrides;
+use jonathan_core::types::column_named;
+use std::io::{self, Read};
+
+use crate::types::{ColumnId, Facets, FileHeader, Filter, MyApp, SortOrder, TabId};
+
+/// Command line of the viewer.
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about = "View and edit CSV and other tabular files",
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    #[command(flatten)]
+    pub open: Args,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Filter, sort and pick columns of a file without opening a window, and write
+    /// the rows out in any export format
+    Query(QueryArgs),
+}
+
+/// Opening files in the viewer.
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// Files to open, each in its own tab; `-` reads standard input
+    pub files: Vec<String>,
+    #[command(flatten)]
+    pub read: ReadArgs,
+    #[command(flatten)]
+    pub view: ViewArgs,
+    /// Row of the first file to scroll to, counting from 1
+    #[arg(short, long)]
+    pub row: Option<u64>,
+    /// Show the first file as a table in the terminal instead of opening a window
+    #[arg(long)]
+    pub tui: bool,
+}
+
+/// `jonathan query`.
+#[derive(clap::Args, Debug)]
+pub struct QueryArgs {
+    /// File to read; `-` reads standard input
+    pub input: String,
+    #[command(flatten)]
+    pub read: ReadArgs,
+    #[command(flatten)]
+    pub view: ViewArgs,
+    /// Columns to write, in this order (default: all)
+    #[arg(short, long, value_delimiter = ',', value_name = "COLUMN,…")]
+    pub columns: Vec<String>,
+    /// File to write (default: standard output)
+    #[arg(short, long)]
+    pub out: Option<String>,
+    /// csv, tsv, json, jsonl, md, html, sql or parquet (default: from the
+    /// extension of `--out`, else csv)
+    #[arg(long)]
+    pub format: Option<String>,
+}
+
+/// How to read delimited text, when guessing gets it wrong.
+#[derive(clap::Args, Debug)]
+pub struct ReadArgs {
+    /// Field delimiter, instead of guessing it (`,`, `;`, `tab`, …)
+    #[arg(short, long, value_parser = parse_delimiter)]
+    pub delimiter: Option<u8>,
+    /// Text encoding, e.g. `latin1` or `windows-1252` (default: UTF-8)
+    #[arg(short, long, value_parser = parse_encoding)]
+    pub encoding: Option<&'static Encoding>,
+    /// The first row is data, not column names
+    #[arg(long)]
+    pub no_header: bool,
+}
+
+/// Which rows to show, in which order.
+#[derive(clap::Args, Debug)]
+pub struct ViewArgs {
+    /// Keep rows containing TEXT, or whose COLUMN is VALUE; repeatable
+    #[arg(short, long, value_name = "TEXT|COLUMN=VALUE")]
+    pub filter: Vec<String>,
+    /// Sort by a column
+    #[arg(short, long, value_name = "COLUMN[:asc|:desc]")]
+    pub sort: Option<String>,
+}
+
+impl ReadArgs {
+    /// Nothing when no option was given.
+    pub fn overrides(&self) -> Option<DialectOverrides> {
+        let given = self.delimiter.is_some() || self.encoding.is_some() || self.no_header;
+        given.then_some(DialectOverrides {
+            delimiter: self.delimiter,
+            encoding: self.encoding,
+            no_header: self.no_header,
+        })
+    }
+}
+
+fn parse_delimiter(value: &str) -> Result<u8, String> {
+    match value {
+        "tab" | "\\t" => Ok(b'\t'),
+        _ if value.len() == 1 && value.is_ascii() => Ok(value.as_bytes()[0]),
+        _ => Err("expected a single ASCII character or `tab`".to_string()),
+    }
+}
+
+fn parse_encoding(value: &str) -> Result<&'static Encoding, String> {
+    Encoding::for_label(value.as_bytes()).ok_or_else(|| format!("unknown encoding `{value}`"))
+}
+
+/// Save standard input to a temporary file, so it can be opened like any other.
+/// JSON Lines are told apart from delimited text by their first character.
+pub fn read_stdin() -> io::Result<String> {
+    let mut bytes = Vec::new();
+    io::stdin().read_to_end(&mut bytes)?;
+    let extension = match bytes.iter().find(|b| !b.is_ascii_whitespace()) {
+        Some(b'{') => "jsonl",
+        _ => "csv",
+    };
+    let path =
+        std::env::temp_dir().join(format!("jonathan-stdin-{}.{extension}", std::process::id()));
+    std::fs::write(&path, bytes)?;
+    Ok(path.display().to_string())
+}
+
+/// Column named `name`, see `column_named`.
+pub fn find_column(headers: &[FileHeader], name: &str) -> Result<ColumnId, String> {
+    column_named(headers, name).ok_or_else(|| format!("no column named `{name}`"))
+}
+
+/// The text filter and facets of `--filter`s. `COLUMN=VALUE` checks VALUE in the
+/// facet of COLUMN when there is such a column, just like the facet sidebar;
+/// anything else is the text filter, of which there can be one.
+pub fn view_filters(
+    filters: &[String],
+    headers: &[FileHeader],
+) -> Result<(Filter, Facets), String> {
+    let mut text = None;
+    let mut facets = Facets::new();
+    for filter in filters {
+        let facet = filter
+            .split_once('=')
+            .and_then(|(name, value)| Some((find_column(headers, name).ok()?, value)));
+        match facet {
+            Some((col, value)) => {
+                facets.entry(col).or_default().insert(value.to_string());
+            }
+            None if text.is_none() => text = Some(filter.clone()),
+            None => {
+                return Err(format!(
+                    "only one text filter is allowed, got `{filter}` too"
+                ));
+            }
+        }
+    }
+    Ok((text.unwrap_or_default(), facets))
+}
+
+/// Column and order of `--sort`.
+pub fn sort_column(spec: &str, headers: &[FileHeader]) -> Result<(ColumnId, SortOrder), String> {
+    let (name, order) = match spec.rsplit_once(':') {
+        Some((name, order)) if order.eq_ignore_ascii_case("asc") => (name, SortOrder::Asc),
+        Some((name, order)) if order.eq_ignore_ascii_case("desc") => (name, SortOrder::Dsc),
+        _ => (spec, SortOrder::Asc),
+    };
+    Ok((find_column(headers, name)?, order))
+}
+
+impl MyApp {
+    /// Open the files given on the command line, each in its own tab, and show the
+    /// first one filtered, sorted and scrolled as asked.
+    pub fn open_args(&mut self, ctx: &egui::Context, args: &Args) {
+        let overrides = args.read.overrides();
+        let mut first = None;
+        for file in &args.files {
+            let file = if file == "-" {
+                match read_stdin() {
+                    Ok(path) => {
+                        self.stdin_files.insert(path.clone());
+                        path
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to read standard input: {:?}", e);
+                        continue;
+                    }
+                }
+            } else {
+                std::path::absolute(file).map_or(file.clone(), |p| p.display().to_string())
+            };
+            if let Some(overrides) = overrides {
+                self.dialect_overrides.insert(file.clone(), overrides);
+            }
+
+            let empty_tab = self.tree.iter_all_tabs().find_map(|(_, tab)| {
+                let empty =
+                    tab.chosen_file.is_empty() && tab.chart.is_none() && tab.pivot.is_none();
+                empty.then_some(tab.id)
+            });
+            let tab_id = empty_tab.unwrap_or_else(|| self.push_tab(file.clone()));
+            self.load_file(ctx, file.clone(), Some(tab_id));
+            first.get_or_insert((file, tab_id));
+        }
+
+        if let Some((file, tab_id)) = first {
+            self.apply_view_args(&file, tab_id, args);
+        }
+    }
+
+    fn apply_view_args(&mut self, file: &str, tab_id: TabId, args: &Args) {
+        let Some((_, tab)) = self
+            .tree
+            .iter_all_tabs_mut()
+            .find(|(_, tab)| tab.id == tab_id)
+        else {
+            return;
+        };
+        let Some(headers) = tab.columns.get_mut(file) else {
+            return;
+        };
+        let key = (file.to_string(), tab_id);
+
+        if !args.view.filter.is_empty() {
+            match view_filters(&args.view.filter, headers) {
+                Ok((filter, facets)) => {
+                    self.filters.insert(key.clone(), filter);
+                    if !facets.is_empty() {
+                        tab.show_facets = true;
+                        self.facets.insert(key, facets);
+                    }
+                }
+                Err(e) => eprintln!("Ignoring --filter: {e}"),
+            }
+        }
+        if let Some(spec) = &args.view.sort {
+            match sort_column(spec, headers) {
+                Ok((col, order)) => headers[col].sort = Some(order),
+                Err(e) => eprintln!("Ignoring --sort: {e}"),
+            }
+        }
+        if let Some(row) = args.row {
+            tab.start_row = Some(row.saturating_sub(1));
+        }
+    }
+}
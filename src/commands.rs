@@ -0,0 +1,539 @@
+//! Everything the viewer can do by name, as listed in the command palette. A new
+//! feature adds an `Action` to `Action::ALL` (or, when it works on a column, a
+//! tab or a file, a `Command` and the entries offering it) and handles it in
+//! `MyApp::run_command`.
+
+use egui::{Id, KeyboardShortcut};
+use jonathan_core::pivot::PivotSpec;
+
+use crate::keymap::Keymap;
+use crate::tabs::open_file_dialog;
+use crate::types::{
+    ColumnId, Filename, MyApp, SheetTab, SortOrder, TabId, UiMessage, active_sheet_data,
+    filter_active, visible_col_indices,
+};
+use crate::ui::command_palette::CommandPalette;
+use crate::ui::export_dialog;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    CommandPalette,
+    OpenFile,
+    NewTab,
+    Save,
+    SaveAs,
+    Reload,
+    ToggleFollow,
+    /// Focus the filter of the tab
+    Find,
+    ClearFilter,
+    ClearGlobalFilter,
+    /// Filter the tab to the value of the cell at the cursor, as Cmd-click does
+    FilterByCell,
+    SelectAll,
+    /// Delete the rows selected from the row numbers
+    DeleteRows,
+    ShowAllColumns,
+    GoToRow,
+    ExportView,
+    Pivot,
+    Chart,
+    ToggleFacets,
+    /// Write the key bindings file, if there is none yet, and show where it is
+    EditKeyBindings,
+    ReloadKeyBindings,
+    // Moving the cursor of the table; with Shift held they extend the selection.
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    PageUp,
+    PageDown,
+    /// Edit the cell at the cursor
+    EditCell,
+}
+
+impl Action {
+    pub const ALL: [Action; 28] = [
+        Action::CommandPalette,
+        Action::OpenFile,
+        Action::NewTab,
+        Action::Save,
+        Action::SaveAs,
+        Action::Reload,
+        Action::ToggleFollow,
+        Action::Find,
+        Action::ClearFilter,
+        Action::ClearGlobalFilter,
+        Action::FilterByCell,
+        Action::SelectAll,
+        Action::DeleteRows,
+        Action::ShowAllColumns,
+        Action::GoToRow,
+        Action::ExportView,
+        Action::Pivot,
+        Action::Chart,
+        Action::ToggleFacets,
+        Action::EditKeyBindings,
+        Action::ReloadKeyBindings,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::PageUp,
+        Action::PageDown,
+        Action::EditCell,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::CommandPalette => "Show all commands",
+            Action::OpenFile => "Open file…",
+            Action::NewTab => "New tab",
+            Action::Save => "Save",
+            Action::SaveAs => "Save as…",
+            Action::Reload => "Reload file from disk",
+            Action::ToggleFollow => "Follow file (read appended rows)",
+            Action::Find => "Find in tab",
+            Action::ClearFilter => "Clear filter",
+            Action::ClearGlobalFilter => "Clear global filter",
+            Action::FilterByCell => "Filter by the value of this cell",
+            Action::SelectAll => "Select all",
+            Action::DeleteRows => "Delete selected rows",
+            Action::ShowAllColumns => "Show all columns",
+            Action::GoToRow => "Go to row…",
+            Action::ExportView => "Export view…",
+            Action::Pivot => "Pivot…",
+            Action::Chart => "Chart…",
+            Action::ToggleFacets => "Show or hide facets",
+            Action::EditKeyBindings => "Edit key bindings…",
+            Action::ReloadKeyBindings => "Reload key bindings",
+            Action::MoveUp => "Move up",
+            Action::MoveDown => "Move down",
+            Action::MoveLeft => "Move left",
+            Action::MoveRight => "Move right",
+            Action::PageUp => "Page up",
+            Action::PageDown => "Page down",
+            Action::EditCell => "Edit cell",
+        }
+    }
+
+    /// What the action is called in the key bindings file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::CommandPalette => "command_palette",
+            Action::OpenFile => "open_file",
+            Action::NewTab => "new_tab",
+            Action::Save => "save",
+            Action::SaveAs => "save_as",
+            Action::Reload => "reload",
+            Action::ToggleFollow => "toggle_follow",
+            Action::Find => "find",
+            Action::ClearFilter => "clear_filter",
+            Action::ClearGlobalFilter => "clear_global_filter",
+            Action::FilterByCell => "filter_by_cell",
+            Action::SelectAll => "select_all",
+            Action::DeleteRows => "delete_rows",
+            Action::ShowAllColumns => "show_all_columns",
+            Action::GoToRow => "go_to_row",
+            Action::ExportView => "export_view",
+            Action::Pivot => "pivot",
+            Action::Chart => "chart",
+            Action::ToggleFacets => "toggle_facets",
+            Action::EditKeyBindings => "edit_key_bindings",
+            Action::ReloadKeyBindings => "reload_key_bindings",
+            Action::MoveUp => "move_up",
+            Action::MoveDown => "move_down",
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::PageUp => "page_up",
+            Action::PageDown => "page_down",
+            Action::EditCell => "edit_cell",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|action| action.name() == name)
+    }
+
+    /// Whether the table handles the action itself, so it is only run by keys.
+    pub fn in_table(&self) -> bool {
+        matches!(
+            self,
+            Action::MoveUp
+                | Action::MoveDown
+                | Action::MoveLeft
+                | Action::MoveRight
+                | Action::PageUp
+                | Action::PageDown
+                | Action::EditCell
+        )
+    }
+}
+
+/// What picking an entry of the palette does.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Run(Action),
+    SortBy(ColumnId, SortOrder),
+    HideColumn(ColumnId),
+    ShowColumn(ColumnId),
+    ShowOnlyColumn(ColumnId),
+    ProfileColumn(ColumnId),
+    /// Scroll to and select a row of the view, counted from 0
+    GoToRow(u64),
+    SwitchTab(TabId),
+    OpenRecent(String),
+}
+
+/// One line of the palette.
+pub struct Entry {
+    pub label: String,
+    pub shortcut: Option<KeyboardShortcut>,
+    pub command: Command,
+}
+
+impl Entry {
+    fn new(label: String, command: Command) -> Entry {
+        Entry {
+            label,
+            shortcut: None,
+            command,
+        }
+    }
+}
+
+fn file_name(path: &str) -> &str {
+    path.split('/').next_back().unwrap_or(path)
+}
+
+impl MyApp {
+    /// Commands the palette lists: every action, then what can be done to the
+    /// columns of the focused tab, the other tabs and recent files.
+    pub fn palette_entries(&mut self) -> Vec<Entry> {
+        let mut entries = Action::ALL
+            .iter()
+            .filter(|&&action| action != Action::CommandPalette && !action.in_table())
+            .map(|&action| Entry {
+                label: action.label().to_string(),
+                shortcut: self.keymap.shortcut(action),
+                command: Command::Run(action),
+            })
+            .collect::<Vec<_>>();
+
+        if let Some((_, tab)) = self.tree.find_active_focused()
+            && tab.chart.is_none()
+            && let Some(headers) = tab.columns.get(&tab.chosen_file)
+        {
+            for (col, header) in headers.iter().enumerate() {
+                let name = if header.name.is_empty() && col == 0 {
+                    "id"
+                } else {
+                    &header.name
+                };
+                entries.extend([
+                    Entry::new(
+                        format!("Sort by {name} ascending"),
+                        Command::SortBy(col, SortOrder::Asc),
+                    ),
+                    Entry::new(
+                        format!("Sort by {name} descending"),
+                        Command::SortBy(col, SortOrder::Dsc),
+                    ),
+                    if header.visible {
+                        Entry::new(format!("Hide column {name}"), Command::HideColumn(col))
+                    } else {
+                        Entry::new(format!("Show column {name}"), Command::ShowColumn(col))
+                    },
+                    Entry::new(
+                        format!("Show only column {name}"),
+                        Command::ShowOnlyColumn(col),
+                    ),
+                    Entry::new(
+                        format!("Profile column {name}"),
+                        Command::ProfileColumn(col),
+                    ),
+                ]);
+            }
+        }
+
+        for (_, tab) in self.tree.iter_all_tabs() {
+            let file = file_name(&tab.chosen_file);
+            let title = match (file, tab.chart.is_some()) {
+                ("", _) => "empty".to_string(),
+                (file, true) => format!("chart of {file}"),
+                (file, false) => file.to_string(),
+            };
+            entries.push(Entry::new(
+                format!("Switch to tab {}: {title}", tab.id),
+                Command::SwitchTab(tab.id),
+            ));
+        }
+
+        for entry in &self.recent_files.entries {
+            if !self.files_list.contains(&entry.path) {
+                entries.push(Entry::new(
+                    format!("Open recent {}", entry.file_name()),
+                    Command::OpenRecent(entry.path.clone()),
+                ));
+            }
+        }
+        entries
+    }
+
+    /// The focused tab and its file, unless it is a chart.
+    fn focused_table(&mut self) -> Option<(TabId, Filename)> {
+        self.tree
+            .find_active_focused()
+            .filter(|(_, tab)| tab.chart.is_none())
+            .map(|(_, tab)| (tab.id, tab.chosen_file.clone()))
+    }
+
+    fn tab_mut(&mut self, tab_id: TabId) -> Option<&mut SheetTab> {
+        self.tree
+            .iter_all_tabs_mut()
+            .map(|(_, tab)| tab)
+            .find(|tab| tab.id == tab_id)
+    }
+
+    fn send(&self, message: UiMessage) {
+        if let Err(e) = self.worker_chan.0.send(message) {
+            eprintln!("Failed to send command: {:?}", e);
+        }
+    }
+
+    /// Do what a palette entry says, to the focused tab where it works on one.
+    pub fn run_command(&mut self, ctx: &egui::Context, command: Command) {
+        if let Command::Run(action) = command {
+            self.run_action(ctx, action);
+            return;
+        }
+        if let Command::SwitchTab(tab_id) = command {
+            if let Some(path) = self.tree.find_tab_from(|tab| tab.id == tab_id) {
+                self.tree.set_active_tab(path);
+                self.tree.set_focused_node_and_surface((path.0, path.1));
+            }
+            return;
+        }
+        let Some((tab_id, file)) = self.focused_table() else {
+            return;
+        };
+        if let Command::OpenRecent(path) = command {
+            self.send(UiMessage::OpenFile(path, Some(tab_id)));
+            return;
+        }
+        if file.is_empty() {
+            return;
+        }
+
+        let active = filter_active(
+            &self.global_filter,
+            &self.filters,
+            &self.facets,
+            &file,
+            tab_id,
+        );
+        let rows = active_sheet_data(
+            &self.sheets_data,
+            &self.filtered_data,
+            &file,
+            tab_id,
+            active,
+        )
+        .len() as u64;
+        let Some(tab) = self.tab_mut(tab_id) else {
+            return;
+        };
+        let Some(headers) = tab.columns.get_mut(&file) else {
+            return;
+        };
+
+        match command {
+            Command::SortBy(col, order) => {
+                for (i, header) in headers.iter_mut().enumerate() {
+                    header.sort = (i == col).then_some(order);
+                }
+                self.send(UiMessage::SortSheet(file, (col, order), tab_id));
+            }
+            Command::HideColumn(col) | Command::ShowColumn(col) => {
+                if let Some(header) = headers.get_mut(col) {
+                    header.visible = matches!(command, Command::ShowColumn(_));
+                }
+            }
+            Command::ShowOnlyColumn(col) => {
+                for (i, header) in headers.iter_mut().enumerate() {
+                    header.visible = i == col;
+                }
+            }
+            Command::ProfileColumn(col) => {
+                self.send(UiMessage::ProfileColumn(file, tab_id, col));
+            }
+            Command::GoToRow(row) => {
+                tab.start_row = Some(row.min(rows.saturating_sub(1)));
+            }
+            Command::Run(_) | Command::SwitchTab(_) | Command::OpenRecent(_) => {}
+        }
+    }
+
+    /// Run `action`, on the focused tab where it works on one.
+    pub fn run_action(&mut self, ctx: &egui::Context, action: Action) {
+        match action {
+            Action::CommandPalette => {
+                self.command_palette = Some(CommandPalette::default());
+                return;
+            }
+            Action::GoToRow => {
+                self.command_palette = Some(CommandPalette::go_to_row());
+                return;
+            }
+            Action::ClearGlobalFilter => {
+                self.send(UiMessage::FilterGlobal(String::new()));
+                return;
+            }
+            Action::NewTab => {
+                self.push_tab(String::new());
+                return;
+            }
+            Action::EditKeyBindings => {
+                self.edit_key_bindings(ctx);
+                return;
+            }
+            Action::ReloadKeyBindings => {
+                if let Some(path) = &self.keymap_path {
+                    self.keymap = Keymap::load(path);
+                    crate::toast::show(ctx, "Key bindings reloaded");
+                    self.keymap.report_conflicts(ctx);
+                }
+                return;
+            }
+            _ => {}
+        }
+
+        let Some((tab_id, file)) = self.focused_table() else {
+            return;
+        };
+        if action == Action::OpenFile {
+            open_file_dialog(&self.worker_chan.0, &tab_id);
+            return;
+        }
+        if file.is_empty() {
+            return;
+        }
+        let format = self.file_formats.get(&file).cloned();
+        let cursor_cell = self.cursor_cell(&file, tab_id);
+        let Some(tab) = self.tab_mut(tab_id) else {
+            return;
+        };
+        let read_only = tab.read_only;
+
+        match action {
+            Action::Save if !read_only => {
+                self.save_file(ctx, &file);
+            }
+            Action::SaveAs if !read_only => {
+                self.save_file_as(ctx, &file);
+            }
+            Action::Reload if !read_only => self.request_reload(ctx, &file),
+            Action::ToggleFollow if !read_only && format.is_none_or(|f| f.appendable()) => {
+                let follow = !self.following.contains(&file);
+                self.set_follow(ctx, file, follow);
+            }
+            Action::Find => {
+                ctx.memory_mut(|m| m.request_focus(Id::new(("filter_input", tab_id))));
+            }
+            Action::ClearFilter => {
+                ctx.memory_mut(|m| m.surrender_focus(Id::new(("filter_input", tab_id))));
+                self.send(UiMessage::FilterSheet(file, String::new(), tab_id, None));
+            }
+            Action::FilterByCell => {
+                if let Some(value) = cursor_cell {
+                    self.send(UiMessage::FilterSheet(file, value, tab_id, None));
+                }
+            }
+            Action::SelectAll => {
+                let columns = tab
+                    .columns
+                    .get(&file)
+                    .map_or(0, |headers| visible_col_indices(headers).len());
+                tab.selection.select_all(columns);
+                self.invalidate_selection_stats();
+            }
+            Action::DeleteRows if !read_only => {
+                let rows = tab
+                    .selection
+                    .selected_rows
+                    .iter()
+                    .copied()
+                    .collect::<Vec<_>>();
+                if rows.is_empty() {
+                    crate::toast::show(ctx, "Select rows by their numbers to delete them");
+                } else {
+                    self.send(UiMessage::DeleteRows(file, tab_id, rows));
+                }
+            }
+            Action::ShowAllColumns => {
+                for header in tab.columns.get_mut(&file).into_iter().flatten() {
+                    header.visible = true;
+                }
+            }
+            Action::ExportView => {
+                tab.export_dialog = Some(export_dialog::spec_for(&file));
+            }
+            Action::Pivot => tab.pivot_builder = Some(PivotSpec::default()),
+            Action::Chart => self.send(UiMessage::OpenChart(file, tab_id)),
+            Action::ToggleFacets => {
+                tab.show_facets = !tab.show_facets;
+                if tab.show_facets {
+                    tab.facet_counts = None;
+                    self.send(UiMessage::RefreshFacets(file, tab_id));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Write the key bindings file, unless there is one already, and copy its
+    /// path for opening it in an editor.
+    fn edit_key_bindings(&self, ctx: &egui::Context) {
+        let Some(path) = &self.keymap_path else {
+            crate::toast::show(ctx, "No folder to keep key bindings in");
+            return;
+        };
+        if !path.exists()
+            && let Err(e) = self.keymap.save(path)
+        {
+            eprintln!("Failed to write key bindings: {:?}", e);
+            crate::toast::show(ctx, format!("Could not write {}", path.display()));
+            return;
+        }
+        ctx.copy_text(path.display().to_string());
+        crate::toast::show(
+            ctx,
+            format!(
+                "Key bindings are in {} (path copied); reload them after editing",
+                path.display()
+            ),
+        );
+    }
+
+    /// Text of the cell at the selection cursor of a tab.
+    fn cursor_cell(&mut self, file: &str, tab_id: TabId) -> Option<String> {
+        let active = filter_active(
+            &self.global_filter,
+            &self.filters,
+            &self.facets,
+            file,
+            tab_id,
+        );
+        let (_, tab) = self
+            .tree
+            .iter_all_tabs()
+            .find(|(_, tab)| tab.id == tab_id)?;
+        let (row, col) = tab.selection.cursor()?;
+        let col = *visible_col_indices(tab.columns.get(file)?).get(col)?;
+        active_sheet_data(&self.sheets_data, &self.filtered_data, file, tab_id, active)
+            .get(row as usize)?
+            .get(col)
+            .map(str::to_string)
+    }
+}
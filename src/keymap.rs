@@ -0,0 +1,445 @@
+//! Which keys run which `Action`. Every shortcut is looked up here, so the same
+//! key can't be claimed twice without it being noticed. The defaults depend on
+//! the platform; `keybindings.json` in the app's storage directory replaces the
+//! keys of the actions it lists:
+//!
+//! ```json
+//! [
+//!   { "action": "go_to_row", "keys": "Ctrl+G" },
+//!   { "action": "clear_global_filter", "keys": "Cmd+Shift+X", "when": "anywhere" },
+//!   { "action": "reload", "keys": null }
+//! ]
+//! ```
+
+use eframe::egui;
+use egui::{Event, Key, KeyboardShortcut, Modifiers};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::commands::Action;
+
+/// Name of the key bindings file in the app's storage directory.
+pub const KEYMAP_FILE: &str = "keybindings.json";
+
+/// Where the key bindings file of the app called `app_id` is.
+pub fn path(app_id: &str) -> Option<PathBuf> {
+    eframe::storage_dir(app_id).map(|dir| dir.join(KEYMAP_FILE))
+}
+
+/// When a binding applies.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyContext {
+    Anywhere,
+    /// Only while no text field has the keyboard, so the key can still be typed
+    Grid,
+    /// Only while typing into a text field
+    Text,
+}
+
+impl KeyContext {
+    fn applies(self, typing: bool) -> bool {
+        match self {
+            KeyContext::Anywhere => true,
+            KeyContext::Grid => !typing,
+            KeyContext::Text => typing,
+        }
+    }
+
+    fn overlaps(self, other: KeyContext) -> bool {
+        self == KeyContext::Anywhere || other == KeyContext::Anywhere || self == other
+    }
+}
+
+/// Where an action's keys apply unless the key bindings file says otherwise.
+fn default_context(action: Action) -> KeyContext {
+    match action {
+        Action::ClearGlobalFilter
+        | Action::SelectAll
+        | Action::FilterByCell
+        | Action::DeleteRows => KeyContext::Grid,
+        _ if action.in_table() => KeyContext::Grid,
+        _ => KeyContext::Anywhere,
+    }
+}
+
+/// Moving the cursor with Shift held extends the selection instead.
+fn shift_extends(action: Action) -> bool {
+    action.in_table() && action != Action::EditCell
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Binding {
+    pub action: Action,
+    pub shortcut: KeyboardShortcut,
+    pub context: KeyContext,
+}
+
+impl Binding {
+    fn new(action: Action, modifiers: Modifiers, key: Key) -> Binding {
+        Binding {
+            action,
+            shortcut: KeyboardShortcut::new(modifiers, key),
+            context: default_context(action),
+        }
+    }
+
+    /// Whether `modifiers` held with the key run the binding.
+    fn matches(&self, modifiers: Modifiers) -> bool {
+        modifiers.matches_exact(self.shortcut.modifiers)
+            || (shift_extends(self.action)
+                && modifiers.shift
+                && Modifiers {
+                    shift: false,
+                    ..modifiers
+                }
+                .matches_exact(self.shortcut.modifiers))
+    }
+}
+
+/// One entry of the key bindings file; `keys: null` unbinds the action.
+#[derive(Serialize, Deserialize)]
+struct FileEntry {
+    action: String,
+    keys: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    when: Option<KeyContext>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    pub bindings: Vec<Binding>,
+}
+
+impl Keymap {
+    /// The bindings of this platform, before the key bindings file.
+    pub fn defaults() -> Keymap {
+        let mac = cfg!(target_os = "macos");
+        let cmd = Modifiers::COMMAND;
+        let none = Modifiers::NONE;
+        let mut bindings = vec![
+            Binding::new(Action::CommandPalette, cmd | Modifiers::SHIFT, Key::P),
+            Binding::new(Action::OpenFile, cmd, Key::O),
+            Binding::new(Action::NewTab, cmd, Key::T),
+            Binding::new(Action::Save, cmd, Key::S),
+            Binding::new(Action::SaveAs, cmd | Modifiers::SHIFT, Key::S),
+            Binding::new(Action::Find, cmd, Key::F),
+            Binding::new(Action::ClearFilter, none, Key::Escape),
+            Binding::new(Action::GoToRow, cmd, if mac { Key::L } else { Key::G }),
+            Binding::new(Action::ClearGlobalFilter, none, Key::X),
+            Binding::new(Action::SelectAll, cmd, Key::A),
+            Binding::new(Action::DeleteRows, none, Key::Delete),
+            Binding::new(Action::MoveUp, none, Key::ArrowUp),
+            Binding::new(Action::MoveDown, none, Key::ArrowDown),
+            Binding::new(Action::MoveLeft, none, Key::ArrowLeft),
+            Binding::new(Action::MoveRight, none, Key::ArrowRight),
+            Binding::new(Action::PageUp, none, Key::PageUp),
+            Binding::new(Action::PageDown, none, Key::PageDown),
+            Binding::new(Action::EditCell, none, Key::Enter),
+        ];
+        if mac {
+            bindings.push(Binding::new(Action::DeleteRows, cmd, Key::Backspace));
+        } else {
+            bindings.push(Binding::new(Action::CommandPalette, none, Key::F1));
+            bindings.push(Binding::new(Action::EditCell, none, Key::F2));
+        }
+        Keymap { bindings }
+    }
+
+    /// The defaults with the bindings of the file at `path`, if there is one.
+    pub fn load(path: &Path) -> Keymap {
+        let mut keymap = Keymap::defaults();
+        match std::fs::read_to_string(path) {
+            Ok(text) => {
+                if let Err(e) = keymap.apply(&text) {
+                    eprintln!("Failed to read key bindings from {}: {e}", path.display());
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => eprintln!(
+                "Failed to read key bindings from {}: {:?}",
+                path.display(),
+                e
+            ),
+        }
+        keymap
+    }
+
+    /// Replace the bindings of the actions listed in `text`, the contents of a
+    /// key bindings file. Entries that can't be understood are reported and
+    /// left out.
+    fn apply(&mut self, text: &str) -> Result<(), serde_json::Error> {
+        let entries: Vec<FileEntry> = serde_json::from_str(text)?;
+        let mut replaced = HashSet::new();
+        let mut bindings = Vec::new();
+        for entry in entries {
+            let Some(action) = Action::from_name(&entry.action) else {
+                eprintln!("Unknown action in key bindings: {}", entry.action);
+                continue;
+            };
+            let Some(keys) = entry.keys else {
+                replaced.insert(action);
+                continue;
+            };
+            let Some(shortcut) = parse_keys(&keys) else {
+                eprintln!("Unknown keys for {} in key bindings: {keys}", entry.action);
+                continue;
+            };
+            replaced.insert(action);
+            bindings.push(Binding {
+                action,
+                shortcut,
+                context: entry.when.unwrap_or(default_context(action)),
+            });
+        }
+        bindings.extend(
+            self.bindings
+                .drain(..)
+                .filter(|binding| !replaced.contains(&binding.action)),
+        );
+        self.bindings = bindings;
+        Ok(())
+    }
+
+    /// Write all bindings to `path`, as a key bindings file to start editing from.
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let entries = self
+            .bindings
+            .iter()
+            .map(|binding| FileEntry {
+                action: binding.action.name().to_string(),
+                keys: Some(format_keys(&binding.shortcut)),
+                when: (binding.context != default_context(binding.action))
+                    .then_some(binding.context),
+            })
+            .collect::<Vec<_>>();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&entries)?)
+    }
+
+    /// First shortcut of `action`, as shown next to it in menus and the palette.
+    pub fn shortcut(&self, action: Action) -> Option<KeyboardShortcut> {
+        self.bindings
+            .iter()
+            .find(|binding| binding.action == action)
+            .map(|binding| binding.shortcut)
+    }
+
+    /// Whether a key of `action` was pressed this frame, where it applies. The
+    /// key is left in the input, so a focused text field still sees it.
+    pub fn pressed(&self, ctx: &egui::Context, action: Action) -> bool {
+        let typing = ctx.wants_keyboard_input();
+        ctx.input(|i| {
+            i.events.iter().any(|event| match event {
+                Event::Key {
+                    key,
+                    pressed: true,
+                    modifiers,
+                    ..
+                } => self.triggered(action, *key, *modifiers, typing),
+                _ => false,
+            })
+        })
+    }
+
+    fn triggered(&self, action: Action, key: Key, modifiers: Modifiers, typing: bool) -> bool {
+        self.bindings.iter().any(|binding| {
+            binding.action == action
+                && binding.shortcut.logical_key == key
+                && binding.context.applies(typing)
+                && binding.matches(modifiers)
+        })
+    }
+
+    /// Keys bound to different actions that can be pressed at the same time.
+    pub fn conflicts(&self) -> Vec<String> {
+        let mut conflicts = Vec::new();
+        for (i, a) in self.bindings.iter().enumerate() {
+            for b in &self.bindings[i + 1..] {
+                let ignore_shift = shift_extends(a.action) || shift_extends(b.action);
+                let same_modifiers =
+                    same_keys(a.shortcut.modifiers, b.shortcut.modifiers, ignore_shift);
+                if a.action != b.action
+                    && a.shortcut.logical_key == b.shortcut.logical_key
+                    && same_modifiers
+                    && a.context.overlaps(b.context)
+                {
+                    conflicts.push(format!(
+                        "{} is bound to both \"{}\" and \"{}\"",
+                        format_keys(&a.shortcut),
+                        a.action.label(),
+                        b.action.label()
+                    ));
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Print the conflicts and show the first one.
+    pub fn report_conflicts(&self, ctx: &egui::Context) {
+        let conflicts = self.conflicts();
+        for conflict in &conflicts {
+            eprintln!("Key binding conflict: {conflict}");
+        }
+        if let Some(first) = conflicts.first() {
+            crate::toast::show(ctx, format!("Key binding conflict: {first}"));
+        }
+    }
+}
+
+/// Whether the modifiers are held down with the same keys on this platform,
+/// where Cmd and Ctrl are one key except on macOS.
+fn same_keys(a: Modifiers, b: Modifiers, ignore_shift: bool) -> bool {
+    let keys = |m: Modifiers| {
+        let cmd = m.command || m.mac_cmd;
+        if cfg!(target_os = "macos") {
+            (m.alt, m.shift && !ignore_shift, cmd, m.ctrl)
+        } else {
+            (m.alt, m.shift && !ignore_shift, cmd || m.ctrl, false)
+        }
+    };
+    keys(a) == keys(b)
+}
+
+/// Keys written as `Cmd+Shift+S`: modifiers (Cmd or Command, Ctrl or Control,
+/// Alt or Option, Shift) and the name of one key, in any case.
+pub fn parse_keys(text: &str) -> Option<KeyboardShortcut> {
+    let mut parts = text.split('+').map(str::trim).collect::<Vec<_>>();
+    let key = parts.pop()?;
+    let key = Key::from_name(key).or_else(|| Key::from_name(&key.to_uppercase()))?;
+    let mut modifiers = Modifiers::NONE;
+    for part in parts {
+        match part.to_lowercase().as_str() {
+            "cmd" | "command" => modifiers.command = true,
+            "ctrl" | "control" => modifiers.ctrl = true,
+            "alt" | "option" => modifiers.alt = true,
+            "shift" => modifiers.shift = true,
+            _ => return None,
+        }
+    }
+    Some(KeyboardShortcut::new(modifiers, key))
+}
+
+/// `shortcut` as `parse_keys` reads it.
+pub fn format_keys(shortcut: &KeyboardShortcut) -> String {
+    let modifiers = shortcut.modifiers;
+    let mut text = String::new();
+    if modifiers.command || modifiers.mac_cmd {
+        text += "Cmd+";
+    }
+    if modifiers.ctrl {
+        text += "Ctrl+";
+    }
+    if modifiers.alt {
+        text += "Alt+";
+    }
+    if modifiers.shift {
+        text += "Shift+";
+    }
+    text + shortcut.logical_key.name()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keymap(text: &str) -> Keymap {
+        let mut keymap = Keymap::defaults();
+        keymap.apply(text).unwrap();
+        keymap
+    }
+
+    #[test]
+    fn keys_are_parsed_and_written_back() {
+        let shortcut = parse_keys("cmd + shift + s").unwrap();
+        assert_eq!(
+            shortcut,
+            KeyboardShortcut::new(Modifiers::COMMAND | Modifiers::SHIFT, Key::S)
+        );
+        assert_eq!(format_keys(&shortcut), "Cmd+Shift+S");
+        assert_eq!(parse_keys("Esc").unwrap().logical_key, Key::Escape);
+        assert_eq!(parse_keys("Ctrl+Up").unwrap().modifiers, Modifiers::CTRL);
+        assert_eq!(
+            parse_keys(&format_keys(&parse_keys("Option+PageDown").unwrap())),
+            parse_keys("Alt+PageDown")
+        );
+        assert!(parse_keys("Hyper+S").is_none());
+        assert!(parse_keys("Cmd+").is_none());
+    }
+
+    #[test]
+    fn file_replaces_the_keys_of_the_actions_it_lists() {
+        let keymap = keymap(
+            r#"[
+                { "action": "save", "keys": "F5" },
+                { "action": "reload", "keys": null },
+                { "action": "no_such_action", "keys": "F6" },
+                { "action": "find", "keys": "Hyper+F" }
+            ]"#,
+        );
+        assert_eq!(
+            keymap.shortcut(Action::Save),
+            Some(KeyboardShortcut::new(Modifiers::NONE, Key::F5))
+        );
+        assert_eq!(keymap.shortcut(Action::Reload), None);
+        // Keys that can't be read leave the default in place.
+        assert_eq!(
+            keymap.shortcut(Action::Find),
+            Some(KeyboardShortcut::new(Modifiers::COMMAND, Key::F))
+        );
+        assert_eq!(
+            keymap.shortcut(Action::SaveAs),
+            Keymap::defaults().shortcut(Action::SaveAs)
+        );
+        assert!(Keymap::defaults().apply("{").is_err());
+    }
+
+    #[test]
+    fn defaults_have_no_conflicts() {
+        assert_eq!(Keymap::defaults().conflicts(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn conflicts_need_the_same_keys_where_both_apply() {
+        let clash = keymap(r#"[{ "action": "reload", "keys": "Cmd+S" }]"#);
+        assert_eq!(clash.conflicts().len(), 1);
+        // X clears the global filter only outside text fields.
+        assert!(
+            keymap(r#"[{ "action": "chart", "keys": "X", "when": "text" }]"#)
+                .conflicts()
+                .is_empty()
+        );
+        assert_eq!(
+            keymap(r#"[{ "action": "chart", "keys": "X" }]"#)
+                .conflicts()
+                .len(),
+            1
+        );
+        // Shift+Down extends the selection.
+        assert_eq!(
+            keymap(r#"[{ "action": "chart", "keys": "Shift+Down" }]"#)
+                .conflicts()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn bindings_apply_in_their_context() {
+        let keymap = Keymap::defaults();
+        let none = Modifiers::NONE;
+        assert!(keymap.triggered(Action::ClearGlobalFilter, Key::X, none, false));
+        assert!(!keymap.triggered(Action::ClearGlobalFilter, Key::X, none, true));
+        assert!(keymap.triggered(Action::Save, Key::S, Modifiers::COMMAND, true));
+        // Cmd+Shift+S is Save as, not Save.
+        let cmd_shift = Modifiers::COMMAND | Modifiers::SHIFT;
+        assert!(!keymap.triggered(Action::Save, Key::S, cmd_shift, false));
+        assert!(keymap.triggered(Action::SaveAs, Key::S, cmd_shift, false));
+        assert!(keymap.triggered(Action::MoveDown, Key::ArrowDown, Modifiers::SHIFT, false));
+        assert!(!keymap.triggered(Action::MoveDown, Key::ArrowDown, Modifiers::ALT, false));
+    }
+}
@@ -5,21 +5,51 @@ use std::collections::HashMap;
 use std::sync::mpsc;
 
 mod app;
-mod data;
+mod batch;
+mod chart;
+mod cli;
+mod commands;
+mod keymap;
 mod menu;
 mod new_table;
-mod read_csv;
+mod recent;
+mod session;
 mod tabs;
 mod toast;
+mod tui;
 mod types;
 mod ui;
+mod watch;
 
+use clap::Parser;
+use cli::{Cli, Command};
 use eframe::egui;
+use jonathan_core::formats::ClipboardFormat;
+use keymap::Keymap;
+use recent::RECENT_FILES_KEY;
+use session::{SESSION_KEY, Session};
 use std::collections::HashSet;
 use std::sync::Arc;
 use types::{MyApp, Ping, SheetTab, UiMessage};
+use watch::FileWatcher;
 
 fn main() -> eframe::Result {
+    let cli = Cli::parse();
+    if let Some(Command::Query(query)) = &cli.command {
+        if let Err(e) = batch::run(query) {
+            eprintln!("jonathan: {e}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    let args = cli.open;
+    if args.tui {
+        if let Err(e) = tui::run(&args) {
+            eprintln!("jonathan: {e}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
     dioxus_devtools::connect_subsecond();
 
     subsecond::call(|| {
@@ -53,13 +83,21 @@ fn main() -> eframe::Result {
                     Box::leak(Box::new(menu));
                 }
 
-                Ok(Box::new(MyApp {
+                let watcher = FileWatcher::spawn(cc.egui_ctx.clone(), worker_chan.0.clone());
+                let keymap_path = keymap::path(title);
+                let keymap = keymap_path
+                    .as_deref()
+                    .map_or_else(Keymap::defaults, Keymap::load);
+                keymap.report_conflicts(&cc.egui_ctx);
+
+                let mut app = MyApp {
                     worker_chan,
                     ui_chan,
                     picked_path: None,
                     loading: false,
                     sheets_data: HashMap::new(),
                     filtered_data: HashMap::new(),
+                    filtered_rows: HashMap::new(),
                     tree: DockState::new(vec![SheetTab {
                         id: 1,
                         ..Default::default()
@@ -68,8 +106,44 @@ fn main() -> eframe::Result {
                     files_list: vec![],
                     global_filter: "".to_string(),
                     filters: HashMap::new(),
+                    facets: HashMap::new(),
                     dirty_files: HashSet::new(),
-                }))
+                    dialects: HashMap::new(),
+                    dialect_overrides: HashMap::new(),
+                    file_formats: HashMap::new(),
+                    file_stamps: HashMap::new(),
+                    keep_backups: false,
+                    copy_format: ClipboardFormat::default(),
+                    recent_files: cc
+                        .storage
+                        .and_then(|storage| eframe::get_value(storage, RECENT_FILES_KEY))
+                        .unwrap_or_default(),
+                    close_prompt: None,
+                    exit_confirmed: false,
+                    overwrite_prompts: Vec::new(),
+                    watcher,
+                    stdin_files: HashSet::new(),
+                    stale_files: HashSet::new(),
+                    following: HashSet::new(),
+                    tailing: HashSet::new(),
+                    lazy_files: HashMap::new(),
+                    sheet_picker: None,
+                    command_palette: None,
+                    keymap,
+                    keymap_path,
+                    sql_sources: HashMap::new(),
+                    sql_edits: HashMap::new(),
+                };
+
+                if let Some(storage) = cc.storage
+                    && let Some(session) = eframe::get_value::<Session>(storage, SESSION_KEY)
+                {
+                    app.restore_session(&cc.egui_ctx, session);
+                }
+                app.open_args(&cc.egui_ctx, &args);
+                menu::update_recent_menu(&app.recent_files);
+
+                Ok(Box::new(app))
             }),
         )
     })
@@ -1,7 +1,17 @@
 // src/menu.rs
 use muda::{Menu, MenuId, MenuItem, PredefinedMenuItem, Submenu};
+use std::cell::RefCell;
+
+use crate::recent::RecentFiles;
 
 pub const OPEN_FILE_ID: &str = "open_file";
+/// Ids of "Open Recent" items are this prefix followed by the file path.
+pub const OPEN_RECENT_PREFIX: &str = "open_recent:";
+
+thread_local! {
+    // Kept so the submenu can be refilled whenever the recent files change
+    static RECENT_MENU: RefCell<Option<Submenu>> = const { RefCell::new(None) };
+}
 
 pub fn build_menu() -> Menu {
     let menu = Menu::new();
@@ -27,10 +37,13 @@ pub fn build_menu() -> Menu {
     app_menu.append(&PredefinedMenuItem::quit(None)).unwrap();
 
     // File menu
-    let open_item = MenuItem::with_id(MenuId::new(OPEN_FILE_ID), "Open CSV...", true, None);
+    let open_item = MenuItem::with_id(MenuId::new(OPEN_FILE_ID), "Open...", true, None);
 
     let file_menu = Submenu::new("File", true);
     file_menu.append(&open_item).unwrap();
+    let recent_menu = Submenu::new("Open Recent", true);
+    file_menu.append(&recent_menu).unwrap();
+    RECENT_MENU.with(|m| *m.borrow_mut() = Some(recent_menu));
     file_menu.append(&PredefinedMenuItem::separator()).unwrap();
     file_menu
         .append(&PredefinedMenuItem::close_window(None))
@@ -54,3 +67,28 @@ pub fn build_menu() -> Menu {
 
     menu
 }
+
+/// Refill the "Open Recent" submenu. Does nothing until `build_menu` has run.
+pub fn update_recent_menu(recent: &RecentFiles) {
+    RECENT_MENU.with(|menu| {
+        let menu = menu.borrow();
+        let Some(menu) = menu.as_ref() else {
+            return;
+        };
+        while menu.remove_at(0).is_some() {}
+        for entry in &recent.entries {
+            let label = if entry.pinned {
+                format!("📌 {}", entry.path)
+            } else {
+                entry.path.clone()
+            };
+            let item = MenuItem::with_id(
+                MenuId::new(format!("{OPEN_RECENT_PREFIX}{}", entry.path)),
+                label,
+                entry.size().is_some(),
+                None,
+            );
+            menu.append(&item).unwrap();
+        }
+    });
+}
@@ -1,10 +1,16 @@
 use std::{collections::BTreeMap, sync::mpsc::Sender};
 
 use egui::{Align2, Color32, Context, Id, Margin, NumExt as _, Sense, TextFormat};
+use jonathan_core::formats::{self, ClipboardFormat};
+use jonathan_core::pivot::PivotSource;
 
-use crate::data::csv_quote;
+use crate::commands::Action;
+use crate::keymap::Keymap;
 use crate::types::{FileHeader, Filename, SelectionState, SheetVec, SortOrder, TabId, UiMessage};
 
+/// Number of table columns taken by the row-number gutter, in front of the data columns.
+const GUTTER_COLS: usize = 1;
+
 pub struct Table<'a> {
     pub data: &'a SheetVec,
     pub num_columns: usize,
@@ -27,6 +33,16 @@ pub struct Table<'a> {
     pub edit_buffer: &'a mut String,
     pub selection: &'a mut SelectionState,
     pub last_visible_rows: &'a mut Option<std::ops::Range<u64>>,
+    /// Format used by Cmd+C; "Copy as…" in the cell context menu picks another one.
+    pub copy_format: &'a mut ClipboardFormat,
+    pub pending_copy: Option<ClipboardFormat>,
+    pub read_only: bool,
+    /// Source of a pivot tab; double-clicking an aggregate opens the rows behind it
+    pub pivot: Option<&'a PivotSource>,
+    /// Scroll to this row this frame
+    pub scroll_to: Option<u64>,
+    /// Keys that move the cursor and start editing
+    pub keymap: &'a Keymap,
 }
 
 impl<'a> Table<'a> {
@@ -116,60 +132,102 @@ impl<'a> Table<'a> {
 
     fn handle_clipboard_copy(&self, ui: &egui::Ui) {
         let copy_requested = ui.input(|i| i.events.iter().any(|e| matches!(e, egui::Event::Copy)));
-        if !copy_requested || self.selection.selected_cells.is_empty() {
+        if copy_requested {
+            self.copy_selection(ui.ctx(), *self.copy_format);
+        }
+    }
+
+    /// Copy the bounding rectangle of the selection; unselected cells inside it are left blank.
+    fn copy_selection(&self, ctx: &Context, format: ClipboardFormat) {
+        if self.selection.is_empty() {
             return;
         }
 
-        let min_row = self
-            .selection
-            .selected_cells
-            .iter()
-            .map(|&(r, _)| r)
-            .min()
-            .unwrap_or(0);
-        let max_row = self
-            .selection
-            .selected_cells
-            .iter()
-            .map(|&(r, _)| r)
-            .max()
-            .unwrap_or(0);
-        let min_col = self
-            .selection
-            .selected_cells
-            .iter()
-            .map(|&(_, c)| c)
-            .min()
-            .unwrap_or(0);
-        let max_col = self
-            .selection
-            .selected_cells
-            .iter()
-            .map(|&(_, c)| c)
-            .max()
-            .unwrap_or(0);
-
-        let mut csv_rows: Vec<String> = Vec::new();
+        let Some((min_row, max_row, min_col, max_col)) =
+            self.selection.bounds(self.num_rows, self.num_columns)
+        else {
+            return;
+        };
+
+        let headers: Vec<&str> = (min_col..=max_col)
+            .map(|c| {
+                let actual_col = self.visible_col_indices.get(c).copied().unwrap_or(c);
+                match self.columns.get(actual_col) {
+                    Some(h) if h.name.is_empty() && actual_col == 0 => "id",
+                    Some(h) => h.name.as_str(),
+                    None => "",
+                }
+            })
+            .collect();
+
+        let mut rows: Vec<Vec<&str>> = Vec::new();
         for r in min_row..=max_row {
-            let mut row_fields: Vec<String> = Vec::new();
+            let mut row_fields: Vec<&str> = Vec::new();
             for c in min_col..=max_col {
-                if self.selection.selected_cells.contains(&(r, c)) {
+                if self.selection.contains(r, c) {
                     let actual_col = self.visible_col_indices.get(c).copied().unwrap_or(c);
                     let value = self
                         .data
                         .get(r as usize)
                         .and_then(|row| row.get(actual_col))
                         .unwrap_or("");
-                    row_fields.push(csv_quote(value));
+                    row_fields.push(value);
                 } else {
-                    row_fields.push(String::new());
+                    row_fields.push("");
                 }
             }
-            csv_rows.push(row_fields.join(","));
+            rows.push(row_fields);
         }
 
-        ui.ctx().copy_text(csv_rows.join("\n"));
-        crate::toast::show(ui.ctx(), "Copied to clipboard");
+        let table_name = std::path::Path::new(&self.filename)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("data");
+
+        ctx.copy_text(formats::render(format, table_name, &headers, &rows));
+        crate::toast::show(ctx, format!("Copied to clipboard as {}", format.label()));
+    }
+
+    /// Menu entry deleting the rows selected from the row numbers.
+    fn delete_rows_ui(&mut self, ui: &mut egui::Ui) {
+        let rows = &self.selection.selected_rows;
+        if self.read_only || rows.is_empty() {
+            return;
+        }
+        let label = match rows.len() {
+            1 => "Delete row".to_string(),
+            n => format!("Delete {n} rows"),
+        };
+        let mut button = egui::Button::new(label);
+        if let Some(shortcut) = self.keymap.shortcut(Action::DeleteRows) {
+            button = button.shortcut_text(ui.ctx().format_shortcut(&shortcut));
+        }
+        if ui.add(button).clicked() {
+            if let Err(e) = self.sender.send(UiMessage::DeleteRows(
+                self.filename.clone(),
+                self.tab_id,
+                rows.iter().copied().collect(),
+            )) {
+                eprintln!("Failed to send DeleteRows: {:?}", e);
+            }
+            ui.close();
+        }
+    }
+
+    fn copy_menu_ui(&mut self, ui: &mut egui::Ui) {
+        ui.menu_button("Copy as…", |ui| {
+            for format in ClipboardFormat::ALL {
+                if ui.button(format.label()).clicked() {
+                    self.pending_copy = Some(format);
+                    ui.close();
+                }
+            }
+        });
+        ui.menu_button("Cmd+C copies as", |ui| {
+            for format in ClipboardFormat::ALL {
+                ui.radio_value(self.copy_format, format, format.label());
+            }
+        });
     }
 
     fn handle_keyboard_navigation(&mut self, ui: &egui::Ui) -> Option<u64> {
@@ -179,12 +237,13 @@ impl<'a> Table<'a> {
         let (anchor_row, anchor_col) = self.selection.anchor_cell?;
 
         let shift = ui.input(|i| i.modifiers.shift);
-        let pressed_up = ui.input(|i| i.key_pressed(egui::Key::ArrowUp));
-        let pressed_down = ui.input(|i| i.key_pressed(egui::Key::ArrowDown));
-        let pressed_left = ui.input(|i| i.key_pressed(egui::Key::ArrowLeft));
-        let pressed_right = ui.input(|i| i.key_pressed(egui::Key::ArrowRight));
-        let pressed_pgup = ui.input(|i| i.key_pressed(egui::Key::PageUp));
-        let pressed_pgdown = ui.input(|i| i.key_pressed(egui::Key::PageDown));
+        let pressed = |action| self.keymap.pressed(ui.ctx(), action);
+        let pressed_up = pressed(Action::MoveUp);
+        let pressed_down = pressed(Action::MoveDown);
+        let pressed_left = pressed(Action::MoveLeft);
+        let pressed_right = pressed(Action::MoveRight);
+        let pressed_pgup = pressed(Action::PageUp);
+        let pressed_pgdown = pressed(Action::PageDown);
 
         let (cur_row, cur_col) = self.selection.cursor().unwrap_or((anchor_row, anchor_col));
 
@@ -239,6 +298,84 @@ impl<'a> Table<'a> {
         Some(target_row)
     }
 
+    fn drill_down(&self, row_nr: u64, col_nr: usize) {
+        let Some(pivot) = self.pivot else {
+            return;
+        };
+        let actual_col = self
+            .visible_col_indices
+            .get(col_nr)
+            .copied()
+            .unwrap_or(col_nr);
+        let Some(facets) = self
+            .data
+            .get(row_nr as usize)
+            .and_then(|row| pivot.drill_down(row, actual_col))
+        else {
+            return;
+        };
+
+        if let Err(e) = self.sender.send(UiMessage::OpenDrillDown(
+            pivot.source_file.clone(),
+            pivot.base_filter.clone(),
+            facets,
+        )) {
+            eprintln!("Failed to send OpenDrillDown: {:?}", e);
+        }
+    }
+
+    /// Row number in the gutter; click selects the row, Cmd toggles, Shift extends.
+    fn gutter_cell_ui(&mut self, ui: &mut egui::Ui, row_nr: u64) {
+        let rect = ui.max_rect();
+        let selected = self.selection.selected_rows.contains(&row_nr);
+        if selected {
+            ui.painter()
+                .rect_filled(rect, 0.0, Color32::from_rgba_unmultiplied(0, 200, 80, 40));
+        }
+
+        ui.painter().text(
+            rect.right_center() - egui::vec2(6.0, 0.0),
+            Align2::RIGHT_CENTER,
+            (row_nr + 1).to_string(),
+            egui::TextStyle::Small.resolve(ui.style()),
+            ui.visuals().weak_text_color(),
+        );
+
+        let response = ui.interact(
+            rect,
+            Id::new(("gutter", row_nr, self.tab_id)),
+            Sense::click(),
+        );
+        if response.clicked() {
+            let modifiers = ui.input(|i| i.modifiers);
+            if modifiers.command {
+                self.selection.toggle_row(row_nr);
+            } else if modifiers.shift {
+                self.selection.extend_rows_to(row_nr);
+            } else {
+                self.selection.select_row(row_nr);
+            }
+        }
+        if response.secondary_clicked() && !selected {
+            self.selection.select_row(row_nr);
+        }
+        response.context_menu(|ui| self.delete_rows_ui(ui));
+    }
+
+    /// Top-left corner of the table; click selects everything.
+    fn gutter_header_ui(&mut self, ui: &mut egui::Ui) {
+        let response = ui
+            .interact(
+                ui.max_rect(),
+                Id::new(("gutter_header", self.tab_id)),
+                Sense::click(),
+            )
+            .on_hover_text("Select all (Cmd+A)");
+        if response.clicked() {
+            self.selection.select_all(self.num_columns);
+        }
+    }
+
     fn draw_selection_border(&self, ui: &egui::Ui, row_nr: u64, col_nr: usize, r: egui::Rect) {
         let is_editing = *self.editing_cell == Some((row_nr, col_nr));
         if !is_editing {
@@ -292,7 +429,10 @@ impl<'a> Table<'a> {
 
         self.handle_clipboard_copy(ui);
 
-        if self.editing_cell.is_none() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+        if !self.read_only
+            && self.editing_cell.is_none()
+            && self.keymap.pressed(ui.ctx(), Action::EditCell)
+        {
             if let Some((row_nr, col_nr)) = self.selection.cursor().or(self.selection.anchor_cell) {
                 let actual_col = self
                     .visible_col_indices
@@ -312,21 +452,26 @@ impl<'a> Table<'a> {
 
         let nav_scroll = self.handle_keyboard_navigation(ui);
         let drag_scroll = self.handle_drag_autoscroll(ui);
-        let scroll_to_row = drag_scroll.or(nav_scroll);
+        let scroll_to_row = drag_scroll.or(nav_scroll).or(self.scroll_to);
 
         let id_salt = Id::new("table_demo");
         let _state_id = egui_table::Table::new().id_salt(id_salt).get_id(ui);
 
+        let digits = self.num_rows.max(1).ilog10() as f32 + 1.0;
+        let gutter_column = egui_table::Column::new(digits * 8.0 + 16.0).resizable(false);
+        let mut columns = vec![gutter_column];
+        columns.extend(vec![self.default_column; self.num_columns]);
+
         let mut table = egui_table::Table::new()
             .id_salt(id_salt)
             .num_rows(self.num_rows)
-            .columns(vec![self.default_column; self.num_columns])
-            // .num_sticky_cols(self.num_sticky_cols)
+            .columns(columns)
+            .num_sticky_cols(self.num_sticky_cols)
             .headers([
                 egui_table::HeaderRow {
                     height: self.top_row_height,
                     groups: if self.num_columns > 0 {
-                        vec![0..self.num_columns]
+                        vec![0..GUTTER_COLS, GUTTER_COLS..self.num_columns + GUTTER_COLS]
                     } else {
                         vec![]
                     },
@@ -340,6 +485,10 @@ impl<'a> Table<'a> {
         }
 
         table.show(ui, self);
+
+        if let Some(format) = self.pending_copy.take() {
+            self.copy_selection(ui.ctx(), format);
+        }
     }
 }
 
@@ -363,12 +512,18 @@ impl<'a> egui_table::TableDelegate for Table<'a> {
             ..
         } = cell_inf;
 
+        if *row_nr > 0 && *group_index < GUTTER_COLS {
+            self.gutter_header_ui(ui);
+            return;
+        }
+        let col_nr = group_index.saturating_sub(GUTTER_COLS);
+
         // Map visible column index to actual column index
         let actual_col_index = self
             .visible_col_indices
-            .get(*group_index)
+            .get(col_nr)
             .copied()
-            .unwrap_or(*group_index);
+            .unwrap_or(col_nr);
 
         let margin = 4;
 
@@ -376,7 +531,7 @@ impl<'a> egui_table::TableDelegate for Table<'a> {
             .inner_margin(Margin::symmetric(margin, 0))
             .show(ui, |ui| {
                 if *row_nr == 0 {
-                    if col_range.start > 0 {
+                    if col_range.start > GUTTER_COLS {
                         // Our special grouped column.
                         let sticky = true;
                         let text = format!("This is group {group_index}");
@@ -414,7 +569,37 @@ impl<'a> egui_table::TableDelegate for Table<'a> {
                         } else {
                             &header.name
                         };
-                        ui.heading(name);
+                        let heading = ui
+                            .add(
+                                egui::Label::new(egui::RichText::new(name).heading())
+                                    .sense(Sense::click()),
+                            )
+                            .on_hover_cursor(egui::CursorIcon::PointingHand)
+                            .on_hover_text(
+                                "Click to select column, Cmd/Shift+click to add, right-click for more",
+                            );
+                        heading.context_menu(|ui| {
+                            if ui.button("Profile column").clicked() {
+                                if let Err(e) = self.sender.send(UiMessage::ProfileColumn(
+                                    self.filename.clone(),
+                                    self.tab_id,
+                                    actual_col_index,
+                                )) {
+                                    eprintln!("Failed to send ProfileColumn: {:?}", e);
+                                }
+                                ui.close();
+                            }
+                        });
+                        if heading.clicked() {
+                            let modifiers = ui.input(|i| i.modifiers);
+                            if modifiers.command {
+                                self.selection.toggle_col(col_nr);
+                            } else if modifiers.shift {
+                                self.selection.extend_cols_to(col_nr);
+                            } else {
+                                self.selection.select_col(col_nr);
+                            }
+                        }
 
                         let button_symbol = if let Some(sort) = header.sort {
                             if sort == SortOrder::Asc { "⬆" } else { "⬇" }
@@ -467,6 +652,12 @@ impl<'a> egui_table::TableDelegate for Table<'a> {
                 .rect_filled(ui.max_rect(), 0.0, ui.visuals().faint_bg_color);
         }
 
+        if col_nr < GUTTER_COLS {
+            self.gutter_cell_ui(ui, row_nr);
+            return;
+        }
+        let col_nr = col_nr - GUTTER_COLS;
+
         let cell_rect = ui.max_rect();
 
         if *self.editing_cell == Some((row_nr, col_nr)) {
@@ -495,7 +686,9 @@ impl<'a> egui_table::TableDelegate for Table<'a> {
             }
         }
 
-        if cell_response.double_clicked() {
+        if cell_response.double_clicked() && self.pivot.is_some() {
+            self.drill_down(row_nr, col_nr);
+        } else if cell_response.double_clicked() && !self.read_only {
             let actual_col = self
                 .visible_col_indices
                 .get(col_nr)
@@ -520,6 +713,14 @@ impl<'a> egui_table::TableDelegate for Table<'a> {
             }
         }
 
+        if cell_response.secondary_clicked() && !self.selection.contains(row_nr, col_nr) {
+            self.selection.select_single(row_nr, col_nr);
+        }
+        cell_response.context_menu(|ui| {
+            self.copy_menu_ui(ui);
+            self.delete_rows_ui(ui);
+        });
+
         if self.selection.contains(row_nr, col_nr) {
             self.draw_selection_border(ui, row_nr, col_nr, cell_rect);
         }
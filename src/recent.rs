@@ -0,0 +1,126 @@
+use jonathan_core::readers::split_sheet;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::types::Filename;
+
+/// Key of the recent files list in eframe's storage.
+pub const RECENT_FILES_KEY: &str = "jonathan_recent_files";
+/// Unpinned entries beyond this many are forgotten.
+pub const MAX_RECENT: usize = 20;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RecentFile {
+    pub path: Filename,
+    /// Unix timestamp (seconds) of the last time the file was opened
+    pub opened_at: i64,
+    pub pinned: bool,
+}
+
+impl RecentFile {
+    pub fn file_name(&self) -> &str {
+        self.path.split('/').next_back().unwrap_or(&self.path)
+    }
+
+    pub fn opened_at_label(&self) -> String {
+        chrono::DateTime::from_timestamp(self.opened_at, 0)
+            .map(|d| {
+                d.with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Size in bytes, or `None` when the file no longer exists. A worksheet or
+    /// database table counts the file it is in.
+    pub fn size(&self) -> Option<u64> {
+        std::fs::metadata(Path::new(split_sheet(&self.path).0))
+            .ok()
+            .filter(|m| m.is_file())
+            .map(|m| m.len())
+    }
+}
+
+/// Most-recently-used files, pinned entries first, then newest first.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct RecentFiles {
+    pub entries: Vec<RecentFile>,
+}
+
+impl RecentFiles {
+    /// Record that `path` was just opened.
+    pub fn touch(&mut self, path: &str) {
+        let pinned = self.entries.iter().any(|e| e.path == path && e.pinned);
+        self.entries.retain(|e| e.path != path);
+        self.entries.push(RecentFile {
+            path: path.to_string(),
+            opened_at: chrono::Utc::now().timestamp(),
+            pinned,
+        });
+        self.normalize();
+    }
+
+    pub fn remove(&mut self, path: &str) {
+        self.entries.retain(|e| e.path != path);
+    }
+
+    pub fn set_pinned(&mut self, path: &str, pinned: bool) {
+        if let Some(entry) = self.entries.iter_mut().find(|e| e.path == path) {
+            entry.pinned = pinned;
+        }
+        self.normalize();
+    }
+
+    fn normalize(&mut self) {
+        self.entries
+            .sort_by(|a, b| b.pinned.cmp(&a.pinned).then(b.opened_at.cmp(&a.opened_at)));
+        let mut unpinned = 0;
+        self.entries.retain(|e| {
+            if !e.pinned {
+                unpinned += 1;
+            }
+            e.pinned || unpinned <= MAX_RECENT
+        });
+    }
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: String) -> RecentFile {
+        RecentFile {
+            path,
+            opened_at: 0,
+            pinned: false,
+        }
+    }
+
+    #[test]
+    fn sheets_and_tables_take_the_size_of_their_file() {
+        let file =
+            std::env::temp_dir().join(format!("jonathan_recent_{}.xlsx", std::process::id()));
+        std::fs::write(&file, b"not really a workbook").unwrap();
+        let path = file.to_str().unwrap();
+
+        assert_eq!(entry(path.to_string()).size(), Some(21));
+        assert_eq!(entry(format!("{path}#Sheet1")).size(), Some(21));
+        std::fs::remove_file(&file).unwrap();
+        assert_eq!(entry(format!("{path}#Sheet1")).size(), None);
+    }
+}
@@ -0,0 +1,204 @@
+use egui_dock::DockState;
+use jonathan_core::formats::ClipboardFormat;
+use jonathan_core::read_csv::sniff_dialect;
+use jonathan_core::readers::{FileFormat, column_headers, detect_format, split_sheet};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use crate::types::{Facets, FileHeader, Filename, Filter, MyApp, SheetTab, TabId};
+
+/// Key of the session in eframe's storage.
+pub const SESSION_KEY: &str = "jonathan_session";
+
+/// What a tab remembers across restarts.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct SessionTab {
+    pub id: TabId,
+    pub chosen_file: Filename,
+    /// Column visibility and sort order per file
+    pub columns: HashMap<Filename, Vec<FileHeader>>,
+    pub show_facets: bool,
+}
+
+/// Open files, dock layout and per-tab view state, as saved by `eframe::App::save`.
+#[derive(Serialize, Deserialize, Default)]
+pub struct Session {
+    pub files: Vec<Filename>,
+    pub tree: Option<DockState<SessionTab>>,
+    pub counter: usize,
+    pub global_filter: Filter,
+    pub filters: Vec<(Filename, TabId, Filter)>,
+    pub facets: Vec<(Filename, TabId, Facets)>,
+    pub copy_format: ClipboardFormat,
+    #[serde(default)]
+    pub keep_backups: bool,
+    /// Formats that differ from what would be detected, e.g. drawn fixed-width columns
+    #[serde(default)]
+    pub formats: Vec<(Filename, FileFormat)>,
+}
+
+impl MyApp {
+    /// Snapshot of the current session. Pivot and chart tabs are derived from other
+    /// views and aren't kept, and what was read from standard input is gone once
+    /// the app quits.
+    pub fn session(&self) -> Session {
+        let kept =
+            |file: &Filename| self.files_list.contains(file) && !self.stdin_files.contains(file);
+        let tree = self.tree.filter_map_tabs(|tab| {
+            if tab.pivot.is_some() || tab.chart.is_some() {
+                return None;
+            }
+            let columns = tab
+                .columns
+                .iter()
+                .filter(|(file, _)| kept(file))
+                .map(|(file, headers)| (file.clone(), headers.clone()))
+                .collect();
+            let chosen_file = if kept(&tab.chosen_file) {
+                tab.chosen_file.clone()
+            } else {
+                String::new()
+            };
+            Some(SessionTab {
+                id: tab.id,
+                chosen_file,
+                columns,
+                show_facets: tab.show_facets,
+            })
+        });
+
+        let mut seen = HashSet::new();
+        let mut files = self.files_list.clone();
+        files.retain(|file| !self.stdin_files.contains(file) && seen.insert(file.clone()));
+
+        Session {
+            files,
+            tree: (tree.iter_all_tabs().count() > 0).then_some(tree),
+            counter: self.counter,
+            global_filter: self.global_filter.clone(),
+            filters: self
+                .filters
+                .iter()
+                .filter(|((file, _), filter)| kept(file) && !filter.is_empty())
+                .map(|((file, tab_id), filter)| (file.clone(), *tab_id, filter.clone()))
+                .collect(),
+            facets: self
+                .facets
+                .iter()
+                .filter(|((file, _), facets)| kept(file) && !facets.is_empty())
+                .map(|((file, tab_id), facets)| (file.clone(), *tab_id, facets.clone()))
+                .collect(),
+            copy_format: self.copy_format,
+            keep_backups: self.keep_backups,
+            formats: self
+                .file_formats
+                .iter()
+                .filter(|(file, format)| kept(file) && **format != detect_format(file))
+                .map(|(file, format)| (file.clone(), format.clone()))
+                .collect(),
+        }
+    }
+
+    /// Reopen the files of a saved session and put its tabs back. Files that no
+    /// longer exist (or can't be read as CSV) are dropped, and tabs that showed them
+    /// fall back to empty tabs.
+    pub fn restore_session(&mut self, ctx: &egui::Context, session: Session) {
+        self.file_formats.extend(session.formats);
+        let (files, missing): (Vec<_>, Vec<_>) = session.files.into_iter().partition(|file| {
+            let format = self
+                .file_formats
+                .get(file)
+                .cloned()
+                .unwrap_or_else(|| detect_format(file));
+            Path::new(split_sheet(file).0).is_file()
+                && column_headers(file, &format, &sniff_dialect(file)).is_ok()
+        });
+
+        if let Some(tree) = session.tree {
+            self.tree = tree.map_tabs(|tab| {
+                let columns = tab
+                    .columns
+                    .iter()
+                    .filter(|(file, _)| files.contains(file))
+                    .map(|(file, headers)| (file.clone(), headers.clone()))
+                    .collect();
+                SheetTab {
+                    id: tab.id,
+                    chosen_file: if files.contains(&tab.chosen_file) {
+                        tab.chosen_file.clone()
+                    } else {
+                        String::new()
+                    },
+                    columns,
+                    show_facets: tab.show_facets,
+                    ..Default::default()
+                }
+            });
+            let max_id = self.tree.iter_all_tabs().map(|(_, t)| t.id).max();
+            self.counter = session.counter.max(max_id.map_or(1, |id| id + 1));
+        }
+        self.global_filter = session.global_filter;
+        self.copy_format = session.copy_format;
+        self.keep_backups = session.keep_backups;
+
+        for (file, tab_id, filter) in session.filters {
+            if files.contains(&file) {
+                self.filters.insert((file, tab_id), filter);
+            }
+        }
+        for (file, tab_id, facets) in session.facets {
+            if files.contains(&file) {
+                self.facets.insert((file, tab_id), facets);
+            }
+        }
+
+        for file in files {
+            let saved = self
+                .tree
+                .iter_all_tabs()
+                .filter_map(|(_, tab)| Some((tab.id, tab.columns.get(&file)?.clone())))
+                .collect::<Vec<_>>();
+
+            self.load_file(ctx, file.clone(), None);
+
+            // Keep saved visibility and sort order unless the file's columns changed.
+            // Columns of JSON Lines files aren't known yet; they are checked once read.
+            for (_, tab) in self.tree.iter_all_tabs_mut() {
+                let Some((_, headers)) = saved.iter().find(|(id, _)| *id == tab.id) else {
+                    continue;
+                };
+                let Some(current) = tab.columns.get_mut(&file) else {
+                    continue;
+                };
+                if current.is_empty() {
+                    *current = headers.clone();
+                } else if current.len() == headers.len()
+                    && current.iter().zip(headers).all(|(a, b)| a.name == b.name)
+                {
+                    // Types from a Parquet or Arrow schema aren't part of the session.
+                    for (current, saved) in current.iter_mut().zip(headers) {
+                        current.visible = saved.visible;
+                        current.sort = saved.sort;
+                    }
+                }
+            }
+            for (_, tab) in self.tree.iter_all_tabs() {
+                if tab.chosen_file == file {
+                    self.filters.entry((file.clone(), tab.id)).or_default();
+                }
+            }
+        }
+
+        if !missing.is_empty() {
+            eprintln!("Session: files no longer available: {:?}", missing);
+            crate::toast::show(
+                ctx,
+                format!(
+                    "{} file(s) from the last session no longer exist",
+                    missing.len()
+                ),
+            );
+        }
+    }
+}
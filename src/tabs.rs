@@ -1,20 +1,29 @@
 use egui::{Align, Align2, Id, LayerId, Order, Response, TextStyle};
-use egui::{Key, Stroke};
+use egui::Stroke;
 use egui_dock::tab_viewer::OnCloseResponse;
 use egui_dock::{NodeIndex, SurfaceIndex};
 
 use egui::Color32;
+use jonathan_core::pivot::PivotSpec;
+use jonathan_core::readers::{FileFormat, OPENABLE_EXTENSIONS, split_sheet};
 
-use crate::types::{active_sheet_data, CsvTabViewer, FileHeader, SheetTab, UiMessage};
+use crate::types::{
+    CsvTabViewer, FileHeader, SheetTab, UiMessage, active_sheet_data, visible_col_indices,
+};
 use eframe::egui;
 
 use std::sync::mpsc::Sender;
 
+use crate::commands::Action;
 use crate::new_table::Table;
+use crate::ui::fixed_width::{self, FixedWidthEditor};
+use crate::ui::{
+    chart_view, export_dialog, facet_panel, pivot_builder, profile_panel, recent_files,
+};
 
-fn open_file_dialog(sender: &Sender<UiMessage>, tab: &usize) {
+pub fn open_file_dialog(sender: &Sender<UiMessage>, tab: &usize) {
     if let Some(paths) = rfd::FileDialog::new()
-        .add_filter("csv", &["csv"])
+        .add_filter("Tables", &OPENABLE_EXTENSIONS)
         .pick_files()
     {
         for path in paths {
@@ -118,6 +127,25 @@ impl egui_dock::TabViewer for CsvTabViewer<'_> {
                     }
                 }
             }
+
+            let recent = self
+                .recent_files
+                .entries
+                .iter()
+                .filter(|e| !self.files_list.contains(&e.path) && e.size().is_some())
+                .collect::<Vec<_>>();
+            if !recent.is_empty() {
+                ui.separator();
+                ui.weak("Recent");
+                for entry in recent {
+                    if file_button(ui, entry.file_name())
+                        .on_hover_text(&entry.path)
+                        .clicked()
+                    {
+                        self.added_nodes.push((surface, node, entry.path.clone()));
+                    }
+                }
+            }
         });
     }
 
@@ -130,6 +158,12 @@ impl egui_dock::TabViewer for CsvTabViewer<'_> {
         let tab_id = &tab.id;
         let dirty = self.dirty_files.contains(&tab.chosen_file);
 
+        if let Some(file) = file
+            && tab.chart.is_some()
+        {
+            return format!("[tab {tab_id}] Chart of {file}").into();
+        }
+
         if let Some(file) = file {
             if file.is_empty() {
                 format!("[tab {tab_id}] Load file").into()
@@ -148,11 +182,31 @@ impl egui_dock::TabViewer for CsvTabViewer<'_> {
 
         ui.add_space(4.0);
 
+        if let Some(chart) = tab.chart.as_mut() {
+            let headers = tab
+                .columns
+                .get(&tab.chosen_file)
+                .map_or(&[][..], |c| c.as_slice());
+            chart_view::show(ui, tab_id, chart, headers, self.sender);
+            return;
+        }
+
+        let previous_file = tab.chosen_file.clone();
+
         ui.horizontal(|ui| {
             if ui.button("Open file…").clicked() {
                 open_file_dialog(&self.sender, &tab_id);
             }
 
+            ui.menu_button("Recent", |ui| {
+                if let Some(path) = recent_files::list_ui(ui, self.recent_files) {
+                    if let Err(e) = self.sender.send(UiMessage::OpenFile(path, Some(tab_id))) {
+                        eprintln!("Worker: Failed to send page data to UI thread: {:?}", e);
+                    }
+                    ui.close();
+                }
+            });
+
             if !self.files_list.is_empty() {
                 let radio = &tab.chosen_file;
                 egui::ComboBox::from_id_salt("file_selector")
@@ -168,8 +222,137 @@ impl egui_dock::TabViewer for CsvTabViewer<'_> {
                         }
                     });
             }
+
+            if !tab.chosen_file.is_empty() {
+                let format = self.file_formats.get(&tab.chosen_file);
+                if !tab.read_only
+                    && ui
+                        .button("Save as…")
+                        .on_hover_text(match format {
+                            Some(format) if !format.writable() => {
+                                format!("Save this {} file as CSV or Parquet (⌘⇧S)", format.label())
+                            }
+                            _ => "Save this file under a new name, as CSV or Parquet (⌘⇧S)"
+                                .to_string(),
+                        })
+                        .clicked()
+                    && let Err(e) = self.sender.send(UiMessage::SaveAs(tab.chosen_file.clone()))
+                {
+                    eprintln!("Failed to send SaveAs: {:?}", e);
+                }
+
+                if let Some(FileFormat::FixedWidth(starts)) = format
+                    && ui
+                        .button("Columns…")
+                        .on_hover_text("Draw where the columns of this fixed-width file start")
+                        .clicked()
+                {
+                    tab.fixed_width_editor = Some(FixedWidthEditor::new(&tab.chosen_file, starts));
+                }
+
+                if let Some(FileFormat::Sqlite(_)) = format
+                    && ui
+                        .button("Query…")
+                        .on_hover_text("Open another table of this database, or run a query")
+                        .clicked()
+                {
+                    let database = split_sheet(&tab.chosen_file).0.to_string();
+                    if let Err(e) = self
+                        .sender
+                        .send(UiMessage::OpenFile(database, Some(tab_id)))
+                    {
+                        eprintln!("Failed to send OpenFile: {:?}", e);
+                    }
+                }
+
+                if !tab.read_only {
+                    let mut follow = self.following.contains(&tab.chosen_file);
+                    if format.is_none_or(FileFormat::appendable)
+                        && ui
+                        .toggle_value(&mut follow, "Follow")
+                        .on_hover_text(
+                            "Read rows appended to the file as they come in and stay at the bottom",
+                        )
+                        .changed()
+                        && let Err(e) = self
+                            .sender
+                            .send(UiMessage::SetFollow(tab.chosen_file.clone(), follow))
+                    {
+                        eprintln!("Failed to send SetFollow: {:?}", e);
+                    }
+
+                    if self.stale_files.contains(&tab.chosen_file) {
+                        ui.colored_label(ui.visuals().warn_fg_color, "Changed on disk");
+                        let hover = if self.dirty_files.contains(&tab.chosen_file) {
+                            "Read the file again; you are asked about your unsaved edits first"
+                        } else {
+                            "Read the file again, keeping filters and sort"
+                        };
+                        if ui.button("Reload").on_hover_text(hover).clicked()
+                            && let Err(e) = self
+                                .sender
+                                .send(UiMessage::ReloadFile(tab.chosen_file.clone()))
+                        {
+                            eprintln!("Failed to send ReloadFile: {:?}", e);
+                        }
+                    }
+
+                    ui.checkbox(self.keep_backups, "Keep .bak").on_hover_text(
+                        "Copy the previous version to <name>.bak before saving over it",
+                    );
+                }
+
+                if ui
+                    .button("Export view…")
+                    .on_hover_text("Write the filtered and sorted rows with the visible columns")
+                    .clicked()
+                {
+                    tab.export_dialog = Some(export_dialog::spec_for(&tab.chosen_file));
+                }
+
+                if ui
+                    .button("Pivot…")
+                    .on_hover_text("Group and aggregate this view into a new tab")
+                    .clicked()
+                {
+                    tab.pivot_builder = Some(PivotSpec::default());
+                }
+
+                if ui
+                    .button("Chart…")
+                    .on_hover_text("Plot this view in a new tab")
+                    .clicked()
+                    && let Err(e) = self
+                        .sender
+                        .send(UiMessage::OpenChart(tab.chosen_file.clone(), tab_id))
+                {
+                    eprintln!("Failed to send OpenChart: {:?}", e);
+                }
+
+                let toggle = ui
+                    .toggle_value(&mut tab.show_facets, "Facets")
+                    .on_hover_text("Filter by checking values of low-cardinality columns");
+                if toggle.changed() && tab.show_facets {
+                    tab.facet_counts = None;
+                    if let Err(e) = self
+                        .sender
+                        .send(UiMessage::RefreshFacets(tab.chosen_file.clone(), tab_id))
+                    {
+                        eprintln!("Failed to send RefreshFacets: {:?}", e);
+                    }
+                }
+            }
         });
 
+        if tab.show_facets
+            && tab.chosen_file != previous_file
+            && let Err(e) = self
+                .sender
+                .send(UiMessage::RefreshFacets(tab.chosen_file.clone(), tab_id))
+        {
+            eprintln!("Failed to send RefreshFacets: {:?}", e);
+        }
+
         ui.add_space(4.0);
 
         let chosen_file = &tab.chosen_file;
@@ -193,7 +376,13 @@ impl egui_dock::TabViewer for CsvTabViewer<'_> {
                         }
                     }
 
-                    if ui.button("Clear (esc)").clicked() {
+                    let clear = match self.keymap.shortcut(Action::ClearFilter) {
+                        Some(shortcut) => {
+                            format!("Clear ({})", self.ctx.format_shortcut(&shortcut))
+                        }
+                        None => "Clear".to_string(),
+                    };
+                    if ui.button(clear).clicked() {
                         if let Err(e) = &self.sender.send(UiMessage::FilterSheet(
                             chosen_file.to_string(),
                             "".to_string(),
@@ -203,28 +392,30 @@ impl egui_dock::TabViewer for CsvTabViewer<'_> {
                             eprintln!("Worker: Failed to send page data to UI thread: {:?}", e);
                         }
                     }
-                });
-            }
-        }
-
-        if let Some(focused_tab) = self.focused_tab {
-            if focused_tab == tab.id {
-                let cmd_f = self.ctx.input(|i| i.key_pressed(Key::F) && i.modifiers.command);
-                if cmd_f {
-                    self.ctx.memory_mut(|m| m.request_focus(filter_input_id));
-                }
 
-                if self.ctx.input(|i| i.key_pressed(Key::Escape)) {
-                    self.ctx.memory_mut(|m| m.surrender_focus(filter_input_id));
-                    if let Err(e) = &self.sender.send(UiMessage::FilterSheet(
-                        chosen_file.to_string(),
-                        "".to_string(),
-                        tab_id,
-                        None,
-                    )) {
-                        eprintln!("Worker: Failed to send page data to UI thread: {:?}", e);
+                    let facets = self.facets.get(&(chosen_file.clone(), tab_id));
+                    for (column, values) in facets.into_iter().flatten() {
+                        let name = tab
+                            .columns
+                            .get(chosen_file)
+                            .and_then(|c| c.get(*column))
+                            .map_or("?", |h| h.name.as_str());
+                        let values = values.iter().cloned().collect::<Vec<_>>().join(", ");
+                        let chip = ui
+                            .button(format!("{name} = {values}  ✕"))
+                            .on_hover_text("Remove this column filter");
+                        if chip.clicked()
+                            && let Err(e) = self.sender.send(UiMessage::FacetSheet(
+                                chosen_file.to_string(),
+                                tab_id,
+                                *column,
+                                Default::default(),
+                            ))
+                        {
+                            eprintln!("Failed to send FacetSheet: {:?}", e);
+                        }
                     }
-                }
+                });
             }
         }
 
@@ -246,10 +437,79 @@ impl egui_dock::TabViewer for CsvTabViewer<'_> {
             }
         }
 
+        if let Some((file, column)) = tab.profile_column.clone()
+            && file == *chosen_file
+        {
+            let name = tab
+                .columns
+                .get(&file)
+                .and_then(|c| c.get(column))
+                .map_or("", |h| h.name.as_str());
+            let name = if name.is_empty() && column == 0 {
+                "id"
+            } else {
+                name
+            };
+            if !profile_panel::show(ui, tab_id, &file, name, tab.profile.as_ref(), self.sender) {
+                tab.profile_column = None;
+                tab.profile = None;
+            }
+        }
+
+        if let Some(columns) = tab.columns.get(chosen_file) {
+            pivot_builder::show(
+                self.ctx,
+                tab_id,
+                chosen_file,
+                columns,
+                &mut tab.pivot_builder,
+                self.sender,
+            );
+        }
+
+        export_dialog::show(
+            self.ctx,
+            tab_id,
+            chosen_file,
+            &mut tab.export_dialog,
+            self.sender,
+        );
+        fixed_width::show(
+            self.ctx,
+            tab_id,
+            chosen_file,
+            &mut tab.fixed_width_editor,
+            self.sender,
+        );
+
+        if tab.show_facets
+            && let Some(columns) = tab.columns.get(chosen_file)
+        {
+            let counts = tab
+                .facet_counts
+                .as_ref()
+                .filter(|(file, _)| file == chosen_file)
+                .map(|(_, counts)| counts);
+            facet_panel::show(
+                ui,
+                tab_id,
+                chosen_file,
+                columns,
+                counts,
+                self.facets.get(&(chosen_file.clone(), tab_id)),
+                self.sender,
+            );
+        }
+
         if let Some(columns) = tab.columns.get_mut(chosen_file) {
             display_headers(ui, columns.as_mut());
             ui.add_space(4.0);
 
+            let facets_active = self
+                .facets
+                .get(&(chosen_file.clone(), tab_id))
+                .is_some_and(|f| !f.is_empty());
+
             let filter = if !self.global_filter.is_empty() {
                 self.global_filter
             } else if let Some(chosen_file) = self.filters.get_mut(&(chosen_file.clone(), tab_id)) {
@@ -263,21 +523,26 @@ impl egui_dock::TabViewer for CsvTabViewer<'_> {
                 self.filtered_data,
                 chosen_file,
                 tab_id,
-                !filter.is_empty(),
+                !filter.is_empty() || facets_active,
             );
 
             let len = sheet_data.len();
 
             // Calculate visible column indices
-            let visible_col_indices: Vec<usize> = columns
-                .iter()
-                .enumerate()
-                .filter(|(_, h)| h.visible)
-                .map(|(i, _)| i)
-                .collect();
+            let visible_col_indices = visible_col_indices(columns);
 
             let num_visible_columns = visible_col_indices.len();
 
+            let scroll_to = if std::mem::take(&mut tab.scroll_to_bottom) {
+                (len as u64).checked_sub(1)
+            } else if let Some(row) = tab.start_row.filter(|&row| row < len as u64) {
+                tab.start_row = None;
+                tab.selection.select_single(row, 0);
+                Some(row)
+            } else {
+                None
+            };
+
             let mut t = Table {
                 data: sheet_data,
                 num_columns: num_visible_columns,
@@ -301,15 +566,27 @@ impl egui_dock::TabViewer for CsvTabViewer<'_> {
                 edit_buffer: &mut tab.edit_buffer,
                 selection: &mut tab.selection,
                 last_visible_rows: &mut tab.last_visible_rows,
+                copy_format: self.copy_format,
+                pending_copy: None,
+                read_only: tab.read_only,
+                pivot: tab.pivot.as_ref(),
+                scroll_to,
+                keymap: self.keymap,
             };
 
             t.ui(ui);
         }
     }
 
-    fn on_close(&mut self, _tab: &mut Self::Tab) -> OnCloseResponse {
+    fn on_close(&mut self, tab: &mut Self::Tab) -> OnCloseResponse {
         if self.tabs_no > 1 {
-            OnCloseResponse::Close
+            if tab.chart.is_none() && self.dirty_files.contains(&tab.chosen_file) {
+                // The app closes it once it knows the edits are safe (or the user says so).
+                self.close_requests.push((tab.id, tab.chosen_file.clone()));
+                OnCloseResponse::Ignore
+            } else {
+                OnCloseResponse::Close
+            }
         } else {
             OnCloseResponse::Ignore
         }
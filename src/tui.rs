@@ -0,0 +1,338 @@
+use iocraft::prelude::*;
+use jonathan_core::dataset::{self, Dataset};
+use std::sync::Arc;
+use std::thread;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::batch::{load, with_input};
+use crate::cli::{Args, sort_column, view_filters};
+use crate::types::{SheetVec, SortOrder, visible_col_indices};
+
+/// Widest a column gets, so one long cell doesn't push the others off screen.
+const MAX_COLUMN_WIDTH: usize = 40;
+/// Lines taken by the header and the status line.
+const CHROME_LINES: usize = 2;
+
+/// `jonathan --tui`: show the first file given as a table in the terminal, for
+/// machines without a display.
+pub fn run(args: &Args) -> Result<(), String> {
+    let Some(input) = args.files.first() else {
+        return Err("--tui needs a file to show".to_string());
+    };
+    if args.files.len() > 1 {
+        eprintln!("Only the first file is shown in the terminal");
+    }
+    let dataset = with_input(input, |input| load(input, &args.read))?;
+
+    let (filter, facets) = view_filters(&args.view.filter, &dataset.headers)?;
+    let sort = args
+        .view
+        .sort
+        .as_deref()
+        .map(|spec| sort_column(spec, &dataset.headers))
+        .transpose()?;
+    let view = dataset::View {
+        filter,
+        facets,
+        sort,
+    };
+    let title = match input.as_str() {
+        "-" => "standard input".to_string(),
+        _ => dataset
+            .path
+            .split('/')
+            .next_back()
+            .unwrap_or_default()
+            .to_string(),
+    };
+    let start_row = args.row.map_or(0, |row| row.saturating_sub(1) as usize);
+
+    futures::executor::block_on(
+        element! {
+            Browser(
+                title,
+                dataset: Arc::new(dataset),
+                view,
+                start_row,
+            )
+        }
+        .fullscreen(),
+    )
+    .map_err(|e| format!("terminal error: {e}"))
+}
+
+#[derive(Default, Props)]
+struct BrowserProps {
+    title: String,
+    dataset: Arc<Dataset>,
+    view: dataset::View,
+    start_row: usize,
+}
+
+/// The rows shown, and the view they were made with.
+#[derive(Default)]
+struct Rows {
+    rows: Arc<SheetVec>,
+    view: dataset::View,
+    /// Bumped for every new filter or sort; older results are dropped
+    generation: u64,
+    busy: bool,
+}
+
+/// Filter and sort the rows on a worker thread, as the viewer's tabs do.
+fn update_view(mut shown: State<Rows>, dataset: Arc<Dataset>, view: dataset::View) {
+    let generation = {
+        let mut current = shown.write();
+        current.view = view.clone();
+        current.generation += 1;
+        current.busy = true;
+        current.generation
+    };
+    thread::spawn(move || {
+        let rows = view.rows(&dataset);
+        let mut current = shown.write();
+        if current.generation == generation {
+            current.rows = Arc::new(rows);
+            current.busy = false;
+        }
+    });
+}
+
+#[component]
+fn Browser(props: &BrowserProps, mut hooks: Hooks) -> impl Into<AnyElement<'static>> {
+    let (width, height) = hooks.use_terminal_size();
+    let mut system = hooks.use_context_mut::<SystemContext>();
+    let shown = hooks.use_state(Rows::default);
+    let mut cursor = hooks.use_state(|| props.start_row);
+    let mut top = hooks.use_state(|| 0usize);
+    let mut column = hooks.use_state(|| 0usize);
+    let mut left = hooks.use_state(|| 0usize);
+    // Text filter being typed, after `/`
+    let mut typing = hooks.use_state(|| None::<String>);
+    let mut should_exit = hooks.use_state(|| false);
+    let started = hooks.use_state(|| false);
+
+    if !started.get() {
+        let mut started = started;
+        started.set(true);
+        update_view(shown, props.dataset.clone(), props.view.clone());
+    }
+
+    let columns = visible_col_indices(&props.dataset.headers);
+    let page = (height as usize).saturating_sub(CHROME_LINES).max(1);
+    let headers = &props.dataset.headers;
+    let rows = shown.read().rows.clone();
+    let num_rows = rows.len();
+
+    // Keep the cursor on a row and in sight.
+    let cursor_row = cursor.get().min(num_rows.saturating_sub(1));
+    let top_row = top
+        .get()
+        .min(cursor_row)
+        .max((cursor_row + 1).saturating_sub(page));
+    if top_row != top.get() {
+        top.set(top_row);
+    }
+    let page_rows = &rows[top_row.min(num_rows)..(top_row + page).min(num_rows)];
+
+    let widths = columns
+        .iter()
+        .map(|&col| {
+            let header = headers.get(col).map_or(0, |h| h.name.width() + 2);
+            page_rows
+                .iter()
+                .map(|record| record.get(col).unwrap_or_default().width())
+                .fold(header, usize::max)
+                .clamp(3, MAX_COLUMN_WIDTH)
+        })
+        .collect::<Vec<_>>();
+
+    // Scroll sideways just enough to show the cursor's column.
+    let cursor_col = column.get().min(columns.len().saturating_sub(1));
+    let mut first_col = left.get().min(cursor_col);
+    while first_col < cursor_col
+        && widths[first_col..=cursor_col]
+            .iter()
+            .map(|w| w + 1)
+            .sum::<usize>()
+            > width as usize
+    {
+        first_col += 1;
+    }
+    if first_col != left.get() {
+        left.set(first_col);
+    }
+
+    let sort = shown.read().view.sort;
+    let header = line(
+        columns[first_col.min(columns.len())..].iter().map(|&col| {
+            let name = headers.get(col).map_or("", |h| h.name.as_str());
+            match sort {
+                Some((sorted, SortOrder::Asc)) if sorted == col => format!("{name} ▲"),
+                Some((sorted, SortOrder::Dsc)) if sorted == col => format!("{name} ▼"),
+                _ => name.to_string(),
+            }
+        }),
+        &widths[first_col.min(widths.len())..],
+        width as usize,
+    );
+    let lines = page_rows
+        .iter()
+        .map(|record| {
+            line(
+                columns[first_col.min(columns.len())..]
+                    .iter()
+                    .map(|&col| record.get(col).unwrap_or_default().to_string()),
+                &widths[first_col.min(widths.len())..],
+                width as usize,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let status = match typing.read().as_ref() {
+        Some(text) => format!("Filter: {text}▏  (Enter to apply, Esc to cancel)"),
+        None => {
+            let current = shown.read();
+            let mut status = format!(
+                "{}  {} of {} rows",
+                props.title,
+                num_rows,
+                props.dataset.rows.len()
+            );
+            if current.busy {
+                status += "  working…";
+            }
+            if !current.view.filter.is_empty() {
+                status += &format!("  filter: {}", current.view.filter);
+            }
+            status + "  ·  ↑↓←→ move  / filter  s sort  q quit"
+        }
+    };
+
+    let dataset = props.dataset.clone();
+    hooks.use_terminal_events(move |event| {
+        let TerminalEvent::Key(KeyEvent { code, kind, .. }) = event else {
+            return;
+        };
+        if kind == KeyEventKind::Release {
+            return;
+        }
+
+        let typed = typing.read().clone();
+        if let Some(mut text) = typed {
+            match code {
+                KeyCode::Char(c) => {
+                    text.push(c);
+                    typing.set(Some(text));
+                }
+                KeyCode::Backspace => {
+                    text.pop();
+                    typing.set(Some(text));
+                }
+                KeyCode::Enter => {
+                    typing.set(None);
+                    cursor.set(0);
+                    let view = dataset::View {
+                        filter: text,
+                        ..shown.read().view.clone()
+                    };
+                    update_view(shown, dataset.clone(), view);
+                }
+                KeyCode::Esc => typing.set(None),
+                _ => {}
+            }
+            return;
+        }
+
+        // Keys can come in faster than renders, so positions are read afresh.
+        let last = num_rows.saturating_sub(1);
+        let cursor_row = cursor.get().min(last);
+        let cursor_col = column.get().min(columns.len().saturating_sub(1));
+        match code {
+            KeyCode::Char('q') => should_exit.set(true),
+            KeyCode::Esc if !shown.read().view.filter.is_empty() => {
+                let view = dataset::View {
+                    filter: String::new(),
+                    ..shown.read().view.clone()
+                };
+                update_view(shown, dataset.clone(), view);
+            }
+            KeyCode::Esc => should_exit.set(true),
+            KeyCode::Up | KeyCode::Char('k') => cursor.set(cursor_row.saturating_sub(1)),
+            KeyCode::Down | KeyCode::Char('j') => cursor.set((cursor_row + 1).min(last)),
+            KeyCode::PageUp => cursor.set(cursor_row.saturating_sub(page)),
+            KeyCode::PageDown => cursor.set((cursor_row + page).min(last)),
+            KeyCode::Home | KeyCode::Char('g') => cursor.set(0),
+            KeyCode::End | KeyCode::Char('G') => cursor.set(last),
+            KeyCode::Left | KeyCode::Char('h') => column.set(cursor_col.saturating_sub(1)),
+            KeyCode::Right | KeyCode::Char('l') => {
+                column.set((cursor_col + 1).min(columns.len().saturating_sub(1)))
+            }
+            KeyCode::Char('/') => typing.set(Some(shown.read().view.filter.clone())),
+            // Ascending, descending, unsorted, like clicking a column header.
+            KeyCode::Char('s') if let Some(&col) = columns.get(cursor_col) => {
+                let mut view = shown.read().view.clone();
+                view.sort = match view.sort {
+                    Some((sorted, SortOrder::Asc)) if sorted == col => Some((col, SortOrder::Dsc)),
+                    Some((sorted, SortOrder::Dsc)) if sorted == col => None,
+                    _ => Some((col, SortOrder::Asc)),
+                };
+                update_view(shown, dataset.clone(), view);
+            }
+            _ => {}
+        }
+    });
+
+    if should_exit.get() {
+        system.exit();
+    }
+
+    let highlighted = cursor_row.checked_sub(top_row);
+    element! {
+        View(width, height, flex_direction: FlexDirection::Column) {
+            Text(content: header, weight: Weight::Bold, wrap: TextWrap::NoWrap)
+            View(flex_grow: 1.0, flex_direction: FlexDirection::Column) {
+                #(lines.into_iter().enumerate().map(|(i, content)| element! {
+                    View(
+                        key: i,
+                        background_color: (highlighted == Some(i)).then_some(Color::DarkGrey),
+                    ) {
+                        Text(content, wrap: TextWrap::NoWrap)
+                    }
+                }))
+            }
+            Text(content: status, color: Color::Cyan, wrap: TextWrap::NoWrap)
+        }
+    }
+}
+
+/// Cells padded or cut to their column's width, as far as `width` allows.
+fn line(cells: impl Iterator<Item = String>, widths: &[usize], width: usize) -> String {
+    let mut line = String::new();
+    for (cell, &column_width) in cells.zip(widths) {
+        if line.width() >= width {
+            break;
+        }
+        line += &fit(&cell, column_width);
+        line.push(' ');
+    }
+    line
+}
+
+/// `text` on one line, exactly `width` columns wide.
+fn fit(text: &str, width: usize) -> String {
+    let mut fitted = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let c = if c.is_control() { ' ' } else { c };
+        let w = c.width().unwrap_or(0);
+        if used + w > width.saturating_sub(1) && text.width() > width {
+            fitted.push('…');
+            used += 1;
+            break;
+        }
+        fitted.push(c);
+        used += w;
+    }
+    fitted + &" ".repeat(width.saturating_sub(used))
+}
@@ -1,127 +1,115 @@
-use csv::StringRecord;
 use egui::Context;
 use egui_dock::{DockState, NodeIndex, SurfaceIndex};
-use std::collections::{HashMap, HashSet};
-
+use jonathan_core::export::ExportSpec;
+use jonathan_core::formats::ClipboardFormat;
+use jonathan_core::pivot::{PivotSource, PivotSpec};
+use jonathan_core::profile::ColumnProfile;
+use jonathan_core::read_csv::{Dialect, DialectOverrides, FileStamp};
+use jonathan_core::readers::FileFormat;
+use jonathan_core::sqlite::{SqlEdit, SqlSource};
+use jonathan_core::stats::SelectionStats;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::ops::Range;
+use std::path::PathBuf;
 use std::sync::mpsc::{Receiver, Sender};
 
-#[derive(Clone, Default)]
-pub struct FileHeader {
-    pub name: String,
-    pub visible: bool,
-    pub sort: Option<SortOrder>,
-}
+pub use jonathan_core::selection::SelectionState;
+pub use jonathan_core::types::{
+    ColumnId, FacetCounts, Facets, FileHeader, Filename, Filter, SheetVec, SortOrder,
+    visible_col_indices,
+};
 
-pub type TabId = usize;
-pub type ColumnId = usize;
-pub type Filter = String;
-pub type Filename = String;
+use crate::chart::{ChartData, ChartTab};
+use crate::keymap::Keymap;
+use crate::recent::RecentFiles;
+use crate::ui::command_palette::CommandPalette;
+use crate::ui::fixed_width::FixedWidthEditor;
+use crate::ui::sheet_picker::SheetPicker;
+use crate::watch::FileWatcher;
 
+pub type TabId = usize;
 pub type Ping = bool;
 
-pub type SheetVec = Vec<StringRecord>;
-
 pub enum UiMessage {
     OpenFile(String, Option<TabId>),
+    /// Open a file in a new tab next to the focused one
+    OpenInNewTab(Filename),
+    /// Open the rows of a `SELECT` on a database: path, query, tab
+    OpenQuery(String, String, Option<TabId>),
     FilterSheet(Filename, Filter, TabId, Option<usize>),
     SortSheet(Filename, (ColumnId, SortOrder), TabId),
     FilterGlobal(Filter),
-    SetDisplayData(SheetVec, String, TabId),
+    /// A tab's view, with the master row behind each of its rows; `None` for
+    /// views SQLite built
+    SetDisplayData(SheetVec, Option<Vec<usize>>, String, TabId),
     SetMaster(SheetVec, String),
+    /// Columns of a file whose columns are only known after reading it
+    SetHeaders(Filename, Vec<FileHeader>),
+    /// Read a fixed-width file again with these column starts
+    SetFixedWidth(Filename, Vec<usize>),
+    /// Hash and modification time of a file as it was read
+    SetFileStamp(Filename, FileStamp),
+    /// The watcher saw an open file change on disk
+    FileChanged(Filename),
+    /// Read a file again, keeping filters, facets and sort; asks first when it
+    /// has unsaved edits
+    ReloadFile(Filename),
+    /// Turn follow mode of a file on or off
+    SetFollow(Filename, bool),
+    /// Rows appended to a followed file, and its stamp after reading them
+    AppendRows(Filename, SheetVec, FileStamp),
+    /// Rows of one row group of a Parquet or Arrow file, by group index
+    SetRowGroup(Filename, usize, SheetVec),
     /// filename, tab_id, row_nr (in displayed data), actual col index, new value
     EditCell(Filename, TabId, u64, usize, String),
+    /// Delete rows of a tab's current view, by row number in it
+    DeleteRows(Filename, TabId, Vec<u64>),
+    /// tab_id, selection generation, aggregates
+    SetSelectionStats(TabId, u64, SelectionStats),
+    /// Open the profile panel of a tab for a (data) column of the file
+    ProfileColumn(Filename, TabId, ColumnId),
+    SetProfile(Filename, TabId, ColumnProfile),
+    /// Replace the exact-match values of one column; an empty set drops the facet
+    FacetSheet(Filename, TabId, ColumnId, BTreeSet<String>),
+    /// Recount the facet sidebar of a tab
+    RefreshFacets(Filename, TabId),
+    SetFacetCounts(Filename, TabId, FacetCounts),
+    /// Build a pivot of a tab's current view and open it in a new tab
+    OpenPivot(Filename, TabId, PivotSpec),
+    SetPivot(Vec<FileHeader>, SheetVec, PivotSource),
+    /// Open a new tab on a file, narrowed down to a text filter and facets
+    OpenDrillDown(Filename, Filter, Facets),
+    /// Write a file to a new path chosen by the user and continue on that path
+    SaveAs(Filename),
+    /// Write the rows and visible columns of a tab's current view to a new file
+    ExportView(Filename, TabId, ExportSpec),
+    /// Open a chart tab on a tab's current view
+    OpenChart(Filename, TabId),
+    /// Recompute the data of a chart tab
+    RefreshChart(TabId),
+    SetChart(TabId, ChartData),
+    /// Replace a tab's selection with whole rows of its current view
+    SelectRows(TabId, Vec<u64>),
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
-pub enum SortOrder {
-    Asc,
-    Dsc,
-}
-
-#[derive(Default)]
-pub struct SelectionState {
-    pub selected_cells: HashSet<(u64, usize)>,
-    /// Fixed corner for range operations (keyboard nav, shift+click, drag)
-    pub anchor_cell: Option<(u64, usize)>,
-    /// Movable corner of the selection rectangle
-    pub selection_end: Option<(u64, usize)>,
-    /// Cell where a drag-select started
-    pub drag_origin: Option<(u64, usize)>,
-}
-
-impl SelectionState {
-    /// The current movable corner: `selection_end` if set, otherwise `anchor_cell`.
-    pub fn cursor(&self) -> Option<(u64, usize)> {
-        self.selection_end.or(self.anchor_cell)
-    }
-
-    pub fn contains(&self, row: u64, col: usize) -> bool {
-        self.selected_cells.contains(&(row, col))
-    }
-
-    pub fn is_dragging(&self) -> bool {
-        self.drag_origin.is_some()
-    }
-
-    /// Clear everything and select a single cell, resetting the anchor.
-    pub fn select_single(&mut self, row: u64, col: usize) {
-        self.selected_cells.clear();
-        self.selected_cells.insert((row, col));
-        self.anchor_cell = Some((row, col));
-        self.selection_end = None;
-    }
-
-    /// Toggle a cell in/out of the selection; updates anchor but keeps other cells.
-    pub fn toggle(&mut self, row: u64, col: usize) {
-        if self.selected_cells.contains(&(row, col)) {
-            self.selected_cells.remove(&(row, col));
-        } else {
-            self.selected_cells.insert((row, col));
-        }
-        self.anchor_cell = Some((row, col));
-    }
-
-    /// Fill the rectangle from `anchor_cell` to `(row, col)` and update `selection_end`.
-    /// Falls back to `select_single` if there is no anchor yet.
-    pub fn extend_to(&mut self, row: u64, col: usize) {
-        if let Some((anchor_row, anchor_col)) = self.anchor_cell {
-            self.fill_rect(anchor_row, anchor_col, row, col);
-            self.selection_end = Some((row, col));
-        } else {
-            self.select_single(row, col);
-        }
-    }
-
-    pub fn start_drag(&mut self, row: u64, col: usize) {
-        self.drag_origin = Some((row, col));
-        self.anchor_cell = Some((row, col));
-        self.selection_end = None;
-        self.selected_cells.clear();
-        self.selected_cells.insert((row, col));
-    }
-
-    /// Extend the drag rectangle from `drag_origin` to `(row, col)`.
-    pub fn update_drag(&mut self, row: u64, col: usize) {
-        if let Some((origin_row, origin_col)) = self.drag_origin {
-            self.fill_rect(origin_row, origin_col, row, col);
-            self.selection_end = Some((row, col));
-        }
-    }
-
-    pub fn end_drag(&mut self) {
-        self.drag_origin = None;
-    }
-
-    fn fill_rect(&mut self, r1: u64, c1: usize, r2: u64, c2: usize) {
-        let row_min = r1.min(r2);
-        let row_max = r1.max(r2);
-        let col_min = c1.min(c2);
-        let col_max = c1.max(c2);
-        self.selected_cells.clear();
-        for r in row_min..=row_max {
-            for c in col_min..=col_max {
-                self.selected_cells.insert((r, c));
-            }
+impl UiMessage {
+    /// The file whose every row this message works on, so it has to wait until a
+    /// partly read Parquet or Arrow file is read completely.
+    pub fn needs_all_rows(&self) -> Option<&Filename> {
+        match self {
+            UiMessage::FilterSheet(file, ..)
+            | UiMessage::SortSheet(file, ..)
+            | UiMessage::EditCell(file, ..)
+            | UiMessage::DeleteRows(file, ..)
+            | UiMessage::ProfileColumn(file, ..)
+            | UiMessage::FacetSheet(file, ..)
+            | UiMessage::RefreshFacets(file, ..)
+            | UiMessage::OpenPivot(file, ..)
+            | UiMessage::OpenDrillDown(file, ..)
+            | UiMessage::SaveAs(file)
+            | UiMessage::ExportView(file, ..)
+            | UiMessage::OpenChart(file, ..) => Some(file),
+            _ => None,
         }
     }
 }
@@ -136,12 +124,54 @@ pub struct SheetTab {
     pub edit_buffer: String,
     pub selection: SelectionState,
     /// Last known visible row range (from previous frame's prepare())
-    pub last_visible_rows: Option<std::ops::Range<u64>>,
+    pub last_visible_rows: Option<Range<u64>>,
+    /// Aggregates of the current selection, tagged with the selection generation they belong to
+    pub selection_stats: Option<(u64, SelectionStats)>,
+    /// Selection generation a background stats job is running for
+    pub stats_pending: Option<u64>,
+    /// Column shown in the profile panel, if open
+    pub profile_column: Option<(Filename, ColumnId)>,
+    /// Profile of `profile_column`, `None` while it is being computed
+    pub profile: Option<ColumnProfile>,
+    pub show_facets: bool,
+    /// Facet sidebar contents for the chosen file, `None` while being counted
+    pub facet_counts: Option<(Filename, FacetCounts)>,
+    /// Cells can't be edited (derived views such as pivots)
+    pub read_only: bool,
+    /// Set on pivot result tabs; used to drill down into the rows behind a cell
+    pub pivot: Option<PivotSource>,
+    /// Spec being edited in the pivot builder window, if open
+    pub pivot_builder: Option<PivotSpec>,
+    /// Set on chart tabs, which plot another tab's view instead of showing a table
+    pub chart: Option<ChartTab>,
+    /// Column starts being drawn for a fixed-width file, if open
+    pub fixed_width_editor: Option<FixedWidthEditor>,
+    /// Format being picked in the export dialog, if open
+    pub export_dialog: Option<ExportSpec>,
+    /// Scroll to the last row on the next frame (follow mode)
+    pub scroll_to_bottom: bool,
+    /// Row to scroll to and select once the view has it (`--row`)
+    pub start_row: Option<u64>,
 }
 
 pub type Chan<Msg> = (Sender<Msg>, Receiver<Msg>);
 
 pub type Filters = HashMap<(Filename, TabId), String>;
+pub type FacetFilters = HashMap<(Filename, TabId), Facets>;
+
+/// A tab shows a filtered view when the global filter, its own filter or any facet is set.
+pub fn filter_active(
+    global_filter: &str,
+    filters: &Filters,
+    facets: &FacetFilters,
+    filename: &str,
+    tab_id: TabId,
+) -> bool {
+    let key = (filename.to_string(), tab_id);
+    !global_filter.is_empty()
+        || filters.get(&key).is_some_and(|f| !f.is_empty())
+        || facets.get(&key).is_some_and(|f| !f.is_empty())
+}
 
 /// Returns the sheet data to display for a given file+tab:
 /// - the filtered/sorted view if one exists
@@ -164,6 +194,62 @@ pub fn active_sheet_data<'a>(
     }
 }
 
+/// Row groups of a Parquet or Arrow file that aren't in memory yet. Until they
+/// are, the master data holds empty rows in their place.
+pub struct LazyFile {
+    pub format: FileFormat,
+    /// Row range of each group in the master data
+    pub groups: Vec<Range<usize>>,
+    pub requested: Vec<bool>,
+    pub loaded: Vec<bool>,
+    /// Messages that need every row, handled once the last group is in
+    pub deferred: Vec<UiMessage>,
+}
+
+impl LazyFile {
+    pub fn new(format: FileFormat, group_rows: &[usize]) -> LazyFile {
+        let mut start = 0;
+        let groups = group_rows
+            .iter()
+            .map(|&rows| {
+                start += rows;
+                start - rows..start
+            })
+            .collect::<Vec<_>>();
+        LazyFile {
+            format,
+            requested: vec![false; groups.len()],
+            loaded: vec![false; groups.len()],
+            groups,
+            deferred: Vec::new(),
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.groups.last().map_or(0, |g| g.end)
+    }
+
+    /// Groups overlapping `rows` that haven't been asked for yet.
+    pub fn missing(&self, rows: Range<usize>) -> Vec<usize> {
+        self.groups
+            .iter()
+            .enumerate()
+            .filter(|(i, g)| !self.requested[*i] && g.start < rows.end && rows.start < g.end)
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+/// A close or reload that would lose unsaved edits.
+pub enum ClosePrompt {
+    /// Closing the last tab showing a dirty file
+    Tab(TabId, Filename),
+    /// Quitting the app with these dirty files
+    App(Vec<Filename>),
+    /// Reading a dirty file from disk again
+    Reload(Filename),
+}
+
 pub struct MyApp {
     pub picked_path: Option<String>,
     pub loading: bool,
@@ -173,12 +259,60 @@ pub struct MyApp {
     // Filtered/sorted views keyed by (filename, tab_id). Each tab can show
     // the same master file filtered or sorted differently.
     pub filtered_data: HashMap<(Filename, TabId), SheetVec>,
+    /// Master row number of each row of a view, so edits and deletes reach the
+    /// file. Views SQLite built have none; their rows are found by content.
+    pub filtered_rows: HashMap<(Filename, TabId), Vec<usize>>,
     pub tree: DockState<SheetTab>,
     pub counter: usize,
     pub files_list: Vec<String>,
     pub global_filter: String,
     pub filters: Filters,
+    pub facets: FacetFilters,
     pub dirty_files: HashSet<Filename>,
+    /// Delimiter, quoting, line endings and BOM each file was read with
+    pub dialects: HashMap<Filename, Dialect>,
+    /// Delimiter, encoding and header row given on the command line
+    pub dialect_overrides: HashMap<Filename, DialectOverrides>,
+    /// CSV, JSON Lines, fixed-width, Parquet, Arrow, worksheet or SQLite, per file
+    pub file_formats: HashMap<Filename, FileFormat>,
+    /// What each file looked like on disk when it was loaded or last saved
+    pub file_stamps: HashMap<Filename, FileStamp>,
+    /// Copy the previous version of a file to `name.bak` before saving over it
+    pub keep_backups: bool,
+    pub copy_format: ClipboardFormat,
+    pub recent_files: RecentFiles,
+    /// Close waiting for a Save / Discard / Cancel answer
+    pub close_prompt: Option<ClosePrompt>,
+    /// The user chose to quit despite unsaved edits
+    pub exit_confirmed: bool,
+    /// Saves waiting for confirmation because the file changed on disk, asked
+    /// about one at a time in order
+    pub overwrite_prompts: Vec<Filename>,
+    pub watcher: FileWatcher,
+    /// Temporary files standard input was saved to; they are left out of the
+    /// session and recent files, and deleted on exit
+    pub stdin_files: HashSet<Filename>,
+    /// Files that changed on disk since they were loaded, offered for reload
+    pub stale_files: HashSet<Filename>,
+    /// Files in follow mode: appended rows are read as they come in
+    pub following: HashSet<Filename>,
+    /// Followed files with appended rows being read
+    pub tailing: HashSet<Filename>,
+    /// Parquet and Arrow files with row groups still to be read
+    pub lazy_files: HashMap<Filename, LazyFile>,
+    /// Workbook or database waiting for its sheets to be chosen
+    pub sheet_picker: Option<SheetPicker>,
+    /// Command palette, while open
+    pub command_palette: Option<CommandPalette>,
+    /// Which keys run which actions
+    pub keymap: Keymap,
+    /// Key bindings file the keymap was read from
+    pub keymap_path: Option<PathBuf>,
+    /// What SQLite tables, views and queries are read with; views of them are
+    /// filtered and sorted by SQLite
+    pub sql_sources: HashMap<Filename, SqlSource>,
+    /// Cell edits of SQLite tables, written as `UPDATE`s on save
+    pub sql_edits: HashMap<Filename, Vec<SqlEdit>>,
 }
 
 pub struct CsvTabViewer<'a> {
@@ -189,8 +323,50 @@ pub struct CsvTabViewer<'a> {
     pub sender: &'a Sender<UiMessage>,
     pub files_list: &'a Vec<String>,
     pub tabs_no: usize,
-    pub focused_tab: Option<usize>,
     pub global_filter: &'a String,
     pub filters: &'a mut Filters,
+    pub facets: &'a FacetFilters,
     pub dirty_files: &'a HashSet<Filename>,
+    pub stale_files: &'a HashSet<Filename>,
+    pub file_formats: &'a HashMap<Filename, FileFormat>,
+    pub following: &'a HashSet<Filename>,
+    pub copy_format: &'a mut ClipboardFormat,
+    pub keep_backups: &'a mut bool,
+    pub recent_files: &'a mut RecentFiles,
+    /// Tabs whose close was held back because their file has unsaved edits
+    pub close_requests: &'a mut Vec<(TabId, Filename)>,
+    pub keymap: &'a Keymap,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use csv::StringRecord;
+
+    fn sheet(cells: &[&str]) -> SheetVec {
+        cells.iter().map(|c| StringRecord::from(vec![*c])).collect()
+    }
+
+    #[test]
+    fn active_sheet_data_prefers_the_view_of_the_tab() {
+        let master = HashMap::from([("a.csv".to_string(), sheet(&["1", "2", "3"]))]);
+        let filtered = HashMap::from([(("a.csv".to_string(), 7), sheet(&["2"]))]);
+        assert_eq!(
+            active_sheet_data(&master, &filtered, "a.csv", 7, true),
+            &sheet(&["2"])
+        );
+        // The view of another tab of the same file doesn't count.
+        assert_eq!(
+            active_sheet_data(&master, &filtered, "a.csv", 8, false).len(),
+            3
+        );
+    }
+
+    #[test]
+    fn active_sheet_data_is_empty_while_a_filter_runs() {
+        let master = HashMap::from([("a.csv".to_string(), sheet(&["1"]))]);
+        let filtered = HashMap::new();
+        assert!(active_sheet_data(&master, &filtered, "a.csv", 0, true).is_empty());
+        assert!(active_sheet_data(&master, &filtered, "b.csv", 0, false).is_empty());
+    }
 }
@@ -0,0 +1,235 @@
+use eframe::egui;
+use egui::Color32;
+use egui_plot::{Bar, BarChart, Line, Plot, PlotPoint, PlotPoints, Points, Polygon};
+use std::sync::mpsc::Sender;
+
+use crate::chart::{ChartData, ChartKind, ChartTab, MAX_DRAWN_POINTS};
+use crate::types::{FileHeader, TabId, UiMessage};
+
+/// Clicks closer than this (in points) to a scatter/line point select it.
+const CLICK_RADIUS: f32 = 6.0;
+
+fn column_name(headers: &[FileHeader], col: usize) -> &str {
+    match headers.get(col) {
+        Some(h) if h.name.is_empty() && col == 0 => "id",
+        Some(h) => &h.name,
+        None => "",
+    }
+}
+
+fn column_combo(
+    ui: &mut egui::Ui,
+    id_salt: impl std::hash::Hash,
+    headers: &[FileHeader],
+    column: &mut usize,
+) -> bool {
+    let before = *column;
+    egui::ComboBox::from_id_salt(id_salt)
+        .selected_text(column_name(headers, *column))
+        .show_ui(ui, |ui| {
+            for col in 0..headers.len() {
+                ui.selectable_value(column, col, column_name(headers, col));
+            }
+        });
+    *column != before
+}
+
+fn send_selection(sender: &Sender<UiMessage>, source_tab: TabId, rows: Vec<u64>) {
+    if let Err(e) = sender.send(UiMessage::SelectRows(source_tab, rows)) {
+        eprintln!("Failed to send SelectRows: {:?}", e);
+    }
+}
+
+/// Contents of a chart tab: chart controls and the plot of the source tab's view.
+/// Clicking a bar or point, or dragging a box, selects the rows behind it in the
+/// source tab.
+pub fn show(
+    ui: &mut egui::Ui,
+    tab_id: TabId,
+    chart: &mut ChartTab,
+    headers: &[FileHeader],
+    sender: &Sender<UiMessage>,
+) {
+    if headers.is_empty() {
+        ui.label("No columns to chart.");
+        return;
+    }
+
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        let kind = chart.spec.kind;
+        egui::ComboBox::from_id_salt(("chart_kind", tab_id))
+            .selected_text(kind.label())
+            .show_ui(ui, |ui| {
+                for k in ChartKind::ALL {
+                    ui.selectable_value(&mut chart.spec.kind, k, k.label());
+                }
+            });
+        changed |= chart.spec.kind != kind;
+
+        ui.label(match chart.spec.kind {
+            ChartKind::Line => "date",
+            _ => "x",
+        });
+        changed |= column_combo(ui, ("chart_x", tab_id), headers, &mut chart.spec.x);
+        if chart.spec.kind.uses_y() {
+            ui.label("y");
+            changed |= column_combo(ui, ("chart_y", tab_id), headers, &mut chart.spec.y);
+        }
+    });
+
+    if changed {
+        chart.data = None;
+        chart.brush_start = None;
+        if let Err(e) = sender.send(UiMessage::RefreshChart(tab_id)) {
+            eprintln!("Failed to send RefreshChart: {:?}", e);
+        }
+    }
+
+    let Some(data) = chart.data.as_ref().filter(|d| d.spec == chart.spec) else {
+        ui.horizontal(|ui| {
+            ui.spinner();
+            ui.label("Computing…");
+        });
+        return;
+    };
+
+    if data.skipped > 0 {
+        ui.weak(match data.spec.kind {
+            ChartKind::ValueCounts => {
+                format!("{} rows in less frequent values not shown", data.skipped)
+            }
+            _ => format!("{} rows without a plottable value", data.skipped),
+        });
+    }
+
+    let x_name = column_name(headers, data.spec.x).to_string();
+    let y_name = column_name(headers, data.spec.y).to_string();
+    let brush_start = chart.brush_start;
+
+    let mut plot = Plot::new(("chart", tab_id))
+        .allow_drag(false)
+        .allow_boxed_zoom(false)
+        .x_axis_label(x_name.clone());
+    plot = match data.spec.kind {
+        ChartKind::Histogram | ChartKind::ValueCounts => plot.y_axis_label("rows"),
+        ChartKind::Scatter | ChartKind::Line => plot.y_axis_label(y_name.clone()),
+    };
+    if data.spec.kind == ChartKind::ValueCounts {
+        let labels = data
+            .bars
+            .iter()
+            .map(|b| b.label.clone())
+            .collect::<Vec<_>>();
+        plot = plot.x_axis_formatter(move |mark, _| {
+            let i = mark.value.round();
+            if (mark.value - i).abs() > f64::EPSILON || i < 0.0 {
+                return String::new();
+            }
+            labels.get(i as usize).cloned().unwrap_or_default()
+        });
+    }
+    if data.spec.kind == ChartKind::Line {
+        plot = plot.x_axis_formatter(|mark, _| {
+            chrono::DateTime::from_timestamp(mark.value as i64, 0)
+                .map(|d| d.format("%Y-%m-%d").to_string())
+                .unwrap_or_default()
+        });
+    }
+
+    let response = plot.show(ui, |plot_ui| {
+        match data.spec.kind {
+            ChartKind::Histogram | ChartKind::ValueCounts => {
+                let bars = data
+                    .bars
+                    .iter()
+                    .map(|b| {
+                        Bar::new((b.start + b.end) / 2.0, b.rows.len() as f64)
+                            .width(b.end - b.start)
+                            .name(&b.label)
+                    })
+                    .collect();
+                plot_ui.bar_chart(BarChart::new(x_name.clone(), bars));
+            }
+            ChartKind::Scatter | ChartKind::Line => {
+                let step = data.points.len().div_ceil(MAX_DRAWN_POINTS).max(1);
+                let points = data
+                    .points
+                    .iter()
+                    .step_by(step)
+                    .copied()
+                    .collect::<PlotPoints>();
+                if data.spec.kind == ChartKind::Line {
+                    plot_ui.line(Line::new(y_name.clone(), points));
+                } else {
+                    plot_ui.points(Points::new(y_name.clone(), points).radius(2.0));
+                }
+            }
+        }
+
+        if let (Some(start), Some(end)) = (brush_start, plot_ui.pointer_coordinate()) {
+            let rect = vec![
+                [start[0], start[1]],
+                [end.x, start[1]],
+                [end.x, end.y],
+                [start[0], end.y],
+            ];
+            plot_ui.polygon(
+                Polygon::new("brush", rect)
+                    .fill_color(Color32::from_rgba_unmultiplied(100, 100, 255, 40)),
+            );
+        }
+    });
+
+    let transform = response.transform;
+    let response = response.response;
+    let pointer = response
+        .interact_pointer_pos()
+        .map(|pos| transform.value_from_position(pos));
+
+    if response.drag_started() {
+        chart.brush_start = pointer.map(|p| [p.x, p.y]);
+    } else if response.drag_stopped() {
+        if let (Some(start), Some(end)) = (chart.brush_start.take(), pointer) {
+            let rows = match data.spec.kind {
+                ChartKind::Histogram | ChartKind::ValueCounts => {
+                    data.rows_in_bars(start[0].min(end.x), start[0].max(end.x))
+                }
+                ChartKind::Scatter | ChartKind::Line => data.rows_in_rect(start, [end.x, end.y]),
+            };
+            send_selection(sender, chart.source_tab, rows);
+        }
+    } else if response.clicked()
+        && let Some(point) = pointer
+    {
+        let rows = clicked_rows(data, point, |p| transform.position_from_point(&p));
+        send_selection(sender, chart.source_tab, rows);
+    }
+}
+
+/// Rows behind the bar under `point`, or behind the closest point within reach.
+fn clicked_rows(
+    data: &ChartData,
+    point: PlotPoint,
+    to_screen: impl Fn(PlotPoint) -> egui::Pos2,
+) -> Vec<u64> {
+    match data.spec.kind {
+        ChartKind::Histogram | ChartKind::ValueCounts => data
+            .bars
+            .iter()
+            .find(|b| (b.start..=b.end).contains(&point.x) && point.y <= b.rows.len() as f64)
+            .map(|b| b.rows.clone())
+            .unwrap_or_default(),
+        ChartKind::Scatter | ChartKind::Line => {
+            let target = to_screen(point);
+            data.points
+                .iter()
+                .zip(&data.point_rows)
+                .map(|(p, row)| (to_screen(PlotPoint::new(p[0], p[1])).distance(target), *row))
+                .filter(|(distance, _)| *distance <= CLICK_RADIUS)
+                .min_by(|a, b| a.0.total_cmp(&b.0))
+                .map(|(_, row)| vec![row])
+                .unwrap_or_default()
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use eframe::egui;
+use egui::Id;
+
+use crate::types::ClosePrompt;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CloseChoice {
+    Save,
+    Discard,
+    Cancel,
+}
+
+fn short_name(path: &str) -> &str {
+    path.split('/').next_back().unwrap_or(path)
+}
+
+/// Modal asking what to do with unsaved edits. Returns the answer once given;
+/// Escape or clicking outside counts as Cancel.
+pub fn show(ctx: &egui::Context, prompt: &ClosePrompt) -> Option<CloseChoice> {
+    let mut choice = None;
+
+    let modal = egui::Modal::new(Id::new("close_prompt")).show(ctx, |ui| {
+        ui.set_max_width(360.0);
+
+        match prompt {
+            ClosePrompt::Tab(_, filename) => {
+                ui.heading("Save changes?");
+                ui.label(format!(
+                    "{} has unsaved changes that will be lost if you close its last tab.",
+                    short_name(filename)
+                ));
+            }
+            ClosePrompt::Reload(filename) => {
+                ui.heading("Save changes?");
+                ui.label(format!(
+                    "{} has unsaved changes that will be lost if you reload it.",
+                    short_name(filename)
+                ));
+            }
+            ClosePrompt::App(files) => {
+                ui.heading("Save changes before quitting?");
+                ui.label("These files have unsaved changes:");
+                for file in files {
+                    ui.label(format!("• {}", short_name(file)))
+                        .on_hover_text(file);
+                }
+            }
+        }
+
+        ui.add_space(8.0);
+        ui.horizontal(|ui| {
+            if ui.button("Save").clicked() {
+                choice = Some(CloseChoice::Save);
+            }
+            if ui.button("Discard").clicked() {
+                choice = Some(CloseChoice::Discard);
+            }
+            if ui.button("Cancel").clicked() {
+                choice = Some(CloseChoice::Cancel);
+            }
+        });
+    });
+
+    if modal.should_close() && choice.is_none() {
+        choice = Some(CloseChoice::Cancel);
+    }
+    choice
+}
@@ -0,0 +1,330 @@
+use eframe::egui;
+use egui::text::LayoutJob;
+use egui::{Id, Key, KeyboardShortcut, Modifiers, TextFormat};
+use std::cmp::Reverse;
+
+use crate::commands::{Action, Command, Entry};
+
+/// Entries listed at most; typing narrows the rest down.
+const MAX_SHOWN: usize = 100;
+
+/// What is typed into the command palette, and the highlighted entry.
+#[derive(Default)]
+pub struct CommandPalette {
+    pub query: String,
+    selected: usize,
+}
+
+impl CommandPalette {
+    /// The palette asking for a row, as "Go to row…" opens it.
+    pub fn go_to_row() -> CommandPalette {
+        CommandPalette {
+            query: ":".to_string(),
+            selected: 0,
+        }
+    }
+}
+
+/// An entry that matches what is typed, with the characters that matched.
+struct Shown<'a> {
+    label: String,
+    matched: Vec<usize>,
+    shortcut: Option<&'a KeyboardShortcut>,
+    command: Command,
+}
+
+/// A row typed as `:42` or `42`, counted from 1.
+fn row_number(query: &str) -> Option<u64> {
+    query
+        .trim()
+        .trim_start_matches(':')
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .filter(|&row| row > 0)
+}
+
+fn word_start(text: &[char], i: usize) -> bool {
+    i == 0
+        || !text[i - 1].is_alphanumeric()
+        || (text[i - 1].is_lowercase() && text[i].is_uppercase())
+}
+
+fn same_letter(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
+
+/// Positions of the characters of `query` in `text`, in order. With `words`, a
+/// character that doesn't follow the previous match is looked for at the start of
+/// a word first.
+fn match_positions(query: &[char], text: &[char], words: bool) -> Option<Vec<usize>> {
+    let mut positions = Vec::with_capacity(query.len());
+    let mut next = 0;
+    for &q in query {
+        let follows = positions.last().is_some_and(|&last| last + 1 == next);
+        let at = |i: &usize| same_letter(q, text[*i]);
+        let found = (next..text.len())
+            .filter(at)
+            .find(|&i| !words || (follows && i == next) || word_start(text, i))
+            .or_else(|| (next..text.len()).find(at))?;
+        positions.push(found);
+        next = found + 1;
+    }
+    Some(positions)
+}
+
+/// How well `query` matches `text`: all its characters in order, ignoring case
+/// and spaces. Matches at the start of words and runs of adjacent matches count
+/// more, and shorter texts win ties. Also returns the positions (in characters)
+/// of the matched characters.
+pub fn fuzzy_match(query: &str, text: &str) -> Option<(i32, Vec<usize>)> {
+    let query = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+    let positions =
+        match_positions(&query, &text, true).or_else(|| match_positions(&query, &text, false))?;
+
+    let mut score = -(text.len() as i32) / 8;
+    for (n, &i) in positions.iter().enumerate() {
+        score += 1;
+        if word_start(&text, i) {
+            score += 8;
+        }
+        if n > 0 && positions[n - 1] + 1 == i {
+            score += 5;
+        }
+    }
+    Some((score, positions))
+}
+
+/// `label` with the `matched` characters stressed.
+fn highlighted(ui: &egui::Ui, label: &str, matched: &[usize]) -> LayoutJob {
+    let plain = TextFormat {
+        color: ui.visuals().text_color(),
+        ..Default::default()
+    };
+    let stressed = TextFormat {
+        color: ui.visuals().strong_text_color(),
+        underline: egui::Stroke::new(1.0, ui.visuals().strong_text_color()),
+        ..Default::default()
+    };
+    let mut job = LayoutJob::default();
+    for (i, c) in label.chars().enumerate() {
+        let format = if matched.contains(&i) {
+            &stressed
+        } else {
+            &plain
+        };
+        job.append(c.encode_utf8(&mut [0; 4]), 0.0, format.clone());
+    }
+    job
+}
+
+/// The entries matching `query`, best first; `:` followed by a number goes to
+/// that row instead.
+fn matching<'a>(query: &str, entries: &'a [Entry]) -> Vec<Shown<'a>> {
+    let mut shown = Vec::new();
+    if let Some(row) = row_number(query) {
+        shown.push(Shown {
+            label: format!("Go to row {row}"),
+            matched: Vec::new(),
+            shortcut: None,
+            command: Command::GoToRow(row - 1),
+        });
+    }
+    if query.starts_with(':') {
+        return shown;
+    }
+
+    let mut scored = entries
+        .iter()
+        .enumerate()
+        .filter_map(|(i, entry)| {
+            let (score, matched) = fuzzy_match(query, &entry.label)?;
+            Some((Reverse(score), i, entry, matched))
+        })
+        .collect::<Vec<_>>();
+    scored.sort_by_key(|(score, i, _, _)| (*score, *i));
+    shown.extend(
+        scored
+            .into_iter()
+            .take(MAX_SHOWN)
+            .map(|(_, _, entry, matched)| Shown {
+                label: entry.label.clone(),
+                matched,
+                shortcut: entry.shortcut.as_ref(),
+                command: entry.command.clone(),
+            }),
+    );
+    shown
+}
+
+/// Modal listing the `entries` that match what is typed, best first, with their
+/// shortcuts. Returns the picked command and closes itself (sets `palette` to
+/// `None`) once one is picked or the palette is dismissed. Keys pressed while it
+/// is open are meant for it, so they are taken out of the input before the
+/// tables underneath see them.
+pub fn show(
+    ctx: &egui::Context,
+    palette: &mut Option<CommandPalette>,
+    entries: &[Entry],
+) -> Option<Command> {
+    let current = palette.as_mut()?;
+    let (up, down, enter, escape) = ctx.input_mut(|i| {
+        (
+            i.consume_key(Modifiers::NONE, Key::ArrowUp),
+            i.consume_key(Modifiers::NONE, Key::ArrowDown),
+            i.consume_key(Modifiers::NONE, Key::Enter),
+            i.consume_key(Modifiers::NONE, Key::Escape),
+        )
+    });
+    let mut picked = None;
+
+    let modal = egui::Modal::new(Id::new("command_palette")).show(ctx, |ui| {
+        ui.set_width(420.0);
+        let edit = ui.add(
+            egui::TextEdit::singleline(&mut current.query)
+                .hint_text("Type a command, or : and a row number")
+                .desired_width(f32::INFINITY),
+        );
+        edit.request_focus();
+        if edit.changed() {
+            current.selected = 0;
+        }
+
+        let shown = matching(&current.query, entries);
+        if down {
+            current.selected += 1;
+        }
+        if up {
+            current.selected = current.selected.saturating_sub(1);
+        }
+        current.selected = current.selected.min(shown.len().saturating_sub(1));
+
+        ui.add_space(4.0);
+        if shown.is_empty() {
+            ui.weak(if current.query.starts_with(':') {
+                "Type the number of a row"
+            } else {
+                "No command matches"
+            });
+        }
+        egui::ScrollArea::vertical()
+            .max_height(320.0)
+            .show(ui, |ui| {
+                for (i, entry) in shown.iter().enumerate() {
+                    let selected = i == current.selected;
+                    let row = ui.horizontal(|ui| {
+                        let label = ui.selectable_label(
+                            selected,
+                            highlighted(ui, &entry.label, &entry.matched),
+                        );
+                        if let Some(shortcut) = entry.shortcut {
+                            ui.with_layout(
+                                egui::Layout::right_to_left(egui::Align::Center),
+                                |ui| {
+                                    ui.weak(ctx.format_shortcut(shortcut));
+                                },
+                            );
+                        }
+                        label
+                    });
+                    if selected && (up || down) {
+                        row.response.scroll_to_me(None);
+                    }
+                    if row.inner.clicked() || (selected && enter) {
+                        picked = Some(entry.command.clone());
+                    }
+                }
+            });
+    });
+
+    // Nothing under the palette gets the keys typed into it.
+    ctx.input_mut(|i| {
+        i.events
+            .retain(|e| !matches!(e, egui::Event::Key { .. } | egui::Event::Copy))
+    });
+
+    match picked {
+        Some(Command::Run(Action::GoToRow)) => {
+            *current = CommandPalette::go_to_row();
+            None
+        }
+        Some(command) => {
+            *palette = None;
+            Some(command)
+        }
+        None => {
+            if escape || modal.should_close() {
+                *palette = None;
+            }
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_match_takes_characters_in_order_ignoring_case() {
+        assert_eq!(
+            fuzzy_match("sva", "Save as…").map(|m| m.1),
+            Some(vec![0, 2, 5])
+        );
+        assert_eq!(
+            fuzzy_match("SAVE", "save").map(|m| m.1),
+            Some(vec![0, 1, 2, 3])
+        );
+        assert_eq!(
+            fuzzy_match("sort city", "Sort by city ascending")
+                .map(|m| m.1)
+                .unwrap()
+                .len(),
+            8
+        );
+        assert!(fuzzy_match("as", "Save").is_none());
+        assert_eq!(fuzzy_match("", "Save"), Some((0, Vec::new())));
+    }
+
+    #[test]
+    fn fuzzy_match_prefers_word_starts() {
+        // The `a` of "all", not of "Chart".
+        assert_eq!(
+            fuzzy_match("ca", "Chart all").map(|m| m.1),
+            Some(vec![0, 6])
+        );
+        let score = |text| fuzzy_match("sa", text).unwrap().0;
+        assert!(score("Save") > score("Pisa"));
+        assert!(score("Select all") > score("Pisa"));
+    }
+
+    #[test]
+    fn fuzzy_match_falls_back_to_any_position() {
+        // Taking the `o` of "col" would leave no `c` after it.
+        assert_eq!(
+            fuzzy_match("oc", "foo col o").map(|m| m.1),
+            Some(vec![1, 4])
+        );
+    }
+
+    #[test]
+    fn colon_and_number_go_to_a_row() {
+        let entries = [Entry {
+            label: "Switch to tab 12: a.csv".to_string(),
+            shortcut: None,
+            command: Command::SwitchTab(12),
+        }];
+        let shown = matching(":12", &entries);
+        assert_eq!(shown.len(), 1);
+        assert_eq!(shown[0].command, Command::GoToRow(11));
+        // A bare number also offers the row, before what it matches.
+        let shown = matching("12", &entries);
+        assert_eq!(shown.len(), 2);
+        assert_eq!(shown[1].command, Command::SwitchTab(12));
+        assert!(matching(":0", &entries).is_empty());
+    }
+}
@@ -0,0 +1,93 @@
+use eframe::egui;
+use egui::Id;
+use jonathan_core::export::{ExportFormat, ExportSpec, SqlDialect};
+use std::path::Path;
+use std::sync::mpsc::Sender;
+
+use crate::types::{Filename, TabId, UiMessage};
+
+/// What the dialog starts out with for a view of `filename`: the first format,
+/// and the file's name for the SQL table.
+pub fn spec_for(filename: &str) -> ExportSpec {
+    let stem = Path::new(filename)
+        .file_stem()
+        .and_then(|n| n.to_str())
+        .unwrap_or("export");
+    ExportSpec {
+        table_name: stem.to_string(),
+        ..Default::default()
+    }
+}
+
+/// Window for picking the format of a view export. Closes itself (sets `spec` to
+/// `None`) once the export is started or the window is dismissed.
+pub fn show(
+    ctx: &egui::Context,
+    tab_id: TabId,
+    filename: &Filename,
+    spec: &mut Option<ExportSpec>,
+    sender: &Sender<UiMessage>,
+) {
+    let Some(current) = spec else {
+        return;
+    };
+    let mut open = true;
+    let mut export = false;
+
+    egui::Window::new("Export view")
+        .id(Id::new(("export_dialog", tab_id)))
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.weak("Filtered and sorted rows, visible columns only.");
+            ui.add_space(4.0);
+
+            for format in ExportFormat::ALL {
+                ui.radio_value(&mut current.format, format, format.label());
+            }
+
+            if current.format == ExportFormat::Sql {
+                ui.add_space(4.0);
+                egui::Grid::new(("export_sql", tab_id))
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Dialect");
+                        egui::ComboBox::from_id_salt(("export_sql_dialect", tab_id))
+                            .selected_text(current.sql_dialect.label())
+                            .show_ui(ui, |ui| {
+                                for dialect in SqlDialect::ALL {
+                                    ui.selectable_value(
+                                        &mut current.sql_dialect,
+                                        dialect,
+                                        dialect.label(),
+                                    );
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Table");
+                        ui.text_edit_singleline(&mut current.table_name);
+                        ui.end_row();
+                    });
+            }
+
+            ui.separator();
+            if ui.button("Export…").clicked() {
+                export = true;
+            }
+        });
+
+    if export {
+        if let Err(e) = sender.send(UiMessage::ExportView(
+            filename.clone(),
+            tab_id,
+            current.clone(),
+        )) {
+            eprintln!("Failed to send ExportView: {:?}", e);
+        }
+        *spec = None;
+    } else if !open {
+        *spec = None;
+    }
+}
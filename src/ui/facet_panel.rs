@@ -0,0 +1,86 @@
+use eframe::egui;
+use egui::Id;
+use std::collections::BTreeSet;
+use std::sync::mpsc::Sender;
+
+use crate::types::{FacetCounts, Facets, FileHeader, Filename, TabId, UiMessage};
+
+/// Left sidebar with a checkbox per value of every low-cardinality column.
+pub fn show(
+    ui: &mut egui::Ui,
+    tab_id: TabId,
+    filename: &Filename,
+    headers: &[FileHeader],
+    counts: Option<&FacetCounts>,
+    active: Option<&Facets>,
+    sender: &Sender<UiMessage>,
+) {
+    egui::SidePanel::left(Id::new(("facet_panel", tab_id)))
+        .resizable(true)
+        .default_width(200.0)
+        .show_inside(ui, |ui| {
+            let Some(counts) = counts else {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Counting…");
+                });
+                return;
+            };
+            if counts.is_empty() {
+                ui.weak("No low-cardinality columns");
+                return;
+            }
+
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for (column, values) in counts {
+                    let name = headers.get(*column).map_or("", |h| h.name.as_str());
+                    let name = if name.is_empty() && *column == 0 {
+                        "id"
+                    } else {
+                        name
+                    };
+                    let selected = active.and_then(|f| f.get(column));
+
+                    let mut update: Option<BTreeSet<String>> = None;
+
+                    egui::CollapsingHeader::new(name)
+                        .id_salt(("facet", tab_id, *column))
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            if selected.is_some_and(|s| !s.is_empty()) && ui.link("clear").clicked()
+                            {
+                                update = Some(BTreeSet::new());
+                            }
+
+                            for (value, count) in values {
+                                let mut checked = selected.is_some_and(|s| s.contains(value));
+                                let enabled = *count > 0 || checked;
+                                let label = if value.is_empty() { "(empty)" } else { value };
+                                let checkbox =
+                                    egui::Checkbox::new(&mut checked, format!("{label} ({count})"));
+                                if ui.add_enabled(enabled, checkbox).changed() {
+                                    let mut next = selected.cloned().unwrap_or_default();
+                                    if checked {
+                                        next.insert(value.clone());
+                                    } else {
+                                        next.remove(value);
+                                    }
+                                    update = Some(next);
+                                }
+                            }
+                        });
+
+                    if let Some(values) = update
+                        && let Err(e) = sender.send(UiMessage::FacetSheet(
+                            filename.clone(),
+                            tab_id,
+                            *column,
+                            values,
+                        ))
+                    {
+                        eprintln!("Failed to send FacetSheet: {:?}", e);
+                    }
+                }
+            });
+        });
+}
@@ -0,0 +1,160 @@
+use eframe::egui;
+use egui::{Align2, FontId, Id, Sense, Stroke, vec2};
+use jonathan_core::read_csv::read_head;
+use jonathan_core::readers::{FIXED_WIDTH_SAMPLE, infer_column_starts};
+use std::sync::mpsc::Sender;
+
+use crate::types::{Filename, TabId, UiMessage};
+
+/// Column starts being drawn over the first lines of a fixed-width file.
+pub struct FixedWidthEditor {
+    pub starts: Vec<usize>,
+    lines: Vec<String>,
+}
+
+impl FixedWidthEditor {
+    pub fn new(path: &str, starts: &[usize]) -> FixedWidthEditor {
+        let lines = match read_head(path) {
+            Ok(bytes) => String::from_utf8_lossy(&bytes)
+                .lines()
+                .take(FIXED_WIDTH_SAMPLE)
+                // One character either way, so positions line up on screen
+                .map(|l| l.replace('\t', " "))
+                .collect(),
+            Err(e) => {
+                eprintln!("Could not read {}: {:?}", path, e);
+                Vec::new()
+            }
+        };
+        FixedWidthEditor {
+            starts: starts.to_vec(),
+            lines,
+        }
+    }
+}
+
+/// Window for drawing the column boundaries of a fixed-width file. Closes itself
+/// (sets `editor` to `None`) when the columns are applied or the window is dismissed.
+pub fn show(
+    ctx: &egui::Context,
+    tab_id: TabId,
+    filename: &Filename,
+    editor: &mut Option<FixedWidthEditor>,
+    sender: &Sender<UiMessage>,
+) {
+    let Some(current) = editor else {
+        return;
+    };
+    let mut open = true;
+    let mut apply = false;
+
+    egui::Window::new("Fixed-width columns")
+        .id(Id::new(("fixed_width", tab_id)))
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(true)
+        .default_width(600.0)
+        .show(ctx, |ui| {
+            ui.weak("Click between two characters to add or remove a column boundary.");
+            egui::ScrollArea::both()
+                .max_height(320.0)
+                .show(ui, |ui| columns_ui(ui, current));
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui
+                    .button("Guess")
+                    .on_hover_text("Start a column after every run of blanks shared by all lines")
+                    .clicked()
+                {
+                    let lines = current.lines.iter().map(String::as_str).collect::<Vec<_>>();
+                    current.starts = infer_column_starts(&lines);
+                }
+                if ui.button("Clear").clicked() {
+                    current.starts = vec![0];
+                }
+                ui.weak(format!("{} columns", current.starts.len()));
+                if ui
+                    .button("Apply")
+                    .on_hover_text("Read the file again with these columns; unsaved edits are lost")
+                    .clicked()
+                {
+                    apply = true;
+                }
+            });
+        });
+
+    if apply {
+        if let Err(e) = sender.send(UiMessage::SetFixedWidth(
+            filename.clone(),
+            current.starts.clone(),
+        )) {
+            eprintln!("Failed to send SetFixedWidth: {:?}", e);
+        }
+        *editor = None;
+    } else if !open {
+        *editor = None;
+    }
+}
+
+fn columns_ui(ui: &mut egui::Ui, editor: &mut FixedWidthEditor) {
+    let font = FontId::monospace(13.0);
+    let (char_width, row_height) =
+        ui.fonts_mut(|f| (f.glyph_width(&font, 'M'), f.row_height(&font)));
+    let width = editor
+        .lines
+        .iter()
+        .map(|l| l.chars().count())
+        .max()
+        .unwrap_or(0)
+        + 1;
+    let size = vec2(
+        width as f32 * char_width,
+        (editor.lines.len() + 1) as f32 * row_height,
+    );
+    let (rect, response) = ui.allocate_exact_size(size, Sense::click());
+    let painter = ui.painter_at(rect);
+    let weak = ui.visuals().weak_text_color();
+    let text = ui.visuals().text_color();
+
+    // Ruler with a mark every ten characters, then the lines themselves
+    for i in (0..width).step_by(10) {
+        painter.text(
+            rect.left_top() + vec2(i as f32 * char_width, 0.0),
+            Align2::LEFT_TOP,
+            format!("|{i}"),
+            font.clone(),
+            weak,
+        );
+    }
+    for (row, line) in editor.lines.iter().enumerate() {
+        painter.text(
+            rect.left_top() + vec2(0.0, (row + 1) as f32 * row_height),
+            Align2::LEFT_TOP,
+            line,
+            font.clone(),
+            text,
+        );
+    }
+
+    let x = |col: usize| rect.left() + col as f32 * char_width;
+    let boundary = Stroke::new(1.5, ui.visuals().selection.stroke.color);
+    for &start in editor.starts.iter().filter(|&&s| s > 0) {
+        painter.vline(x(start), rect.y_range(), boundary);
+    }
+
+    if let Some(pos) = response.hover_pos() {
+        let col = ((pos.x - rect.left()) / char_width).round() as usize;
+        if col > 0 {
+            painter.vline(x(col), rect.y_range(), Stroke::new(1.0, weak));
+            if response.clicked() {
+                if let Some(i) = editor.starts.iter().position(|&s| s == col) {
+                    editor.starts.remove(i);
+                } else {
+                    editor.starts.push(col);
+                    editor.starts.sort_unstable();
+                }
+            }
+        }
+    }
+}
@@ -1,2 +1,14 @@
+pub mod chart_view;
+pub mod close_prompt;
+pub mod command_palette;
 pub mod drop;
+pub mod export_dialog;
+pub mod facet_panel;
+pub mod fixed_width;
 pub mod fonts;
+pub mod overwrite_prompt;
+pub mod pivot_builder;
+pub mod profile_panel;
+pub mod recent_files;
+pub mod sheet_picker;
+pub mod status_bar;
@@ -0,0 +1,36 @@
+use eframe::egui;
+use egui::Id;
+
+/// Modal shown when a file changed on disk since it was loaded. Returns `Some(true)`
+/// to save over it anyway and `Some(false)` to leave it alone; Escape or clicking
+/// outside counts as the latter.
+pub fn show(ctx: &egui::Context, filename: &str) -> Option<bool> {
+    let mut choice = None;
+    let short_name = filename.split('/').next_back().unwrap_or(filename);
+
+    let modal = egui::Modal::new(Id::new("overwrite_prompt")).show(ctx, |ui| {
+        ui.set_max_width(360.0);
+
+        ui.heading("File changed on disk");
+        ui.label(format!(
+            "{short_name} was modified by another program since it was opened. \
+             Saving will replace those changes with yours."
+        ))
+        .on_hover_text(filename);
+
+        ui.add_space(8.0);
+        ui.horizontal(|ui| {
+            if ui.button("Overwrite").clicked() {
+                choice = Some(true);
+            }
+            if ui.button("Cancel").clicked() {
+                choice = Some(false);
+            }
+        });
+    });
+
+    if modal.should_close() && choice.is_none() {
+        choice = Some(false);
+    }
+    choice
+}
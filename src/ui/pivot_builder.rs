@@ -0,0 +1,141 @@
+use eframe::egui;
+use egui::Id;
+use jonathan_core::pivot::{Aggregation, PivotSpec};
+use std::sync::mpsc::Sender;
+
+use crate::types::{FileHeader, Filename, TabId, UiMessage};
+
+fn column_name(headers: &[FileHeader], col: usize) -> &str {
+    match headers.get(col) {
+        Some(h) if h.name.is_empty() && col == 0 => "id",
+        Some(h) => &h.name,
+        None => "",
+    }
+}
+
+/// Window for choosing group and value columns of a pivot. Closes itself (sets
+/// `spec` to `None`) when the pivot is created or the window is dismissed.
+pub fn show(
+    ctx: &egui::Context,
+    tab_id: TabId,
+    filename: &Filename,
+    headers: &[FileHeader],
+    spec: &mut Option<PivotSpec>,
+    sender: &Sender<UiMessage>,
+) {
+    let Some(current) = spec else {
+        return;
+    };
+    let mut open = true;
+    let mut create = false;
+
+    egui::Window::new("Pivot")
+        .id(Id::new(("pivot_builder", tab_id)))
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(true)
+        .show(ctx, |ui| {
+            ui.strong("Rows grouped by");
+            ui.horizontal_wrapped(|ui| {
+                for col in 0..headers.len() {
+                    let mut checked = current.row_groups.contains(&col);
+                    if ui
+                        .checkbox(&mut checked, column_name(headers, col))
+                        .changed()
+                    {
+                        if checked {
+                            current.row_groups.push(col);
+                        } else {
+                            current.row_groups.retain(|c| *c != col);
+                        }
+                    }
+                }
+            });
+            if !current.row_groups.is_empty() {
+                let order = current
+                    .row_groups
+                    .iter()
+                    .map(|c| column_name(headers, *c))
+                    .collect::<Vec<_>>()
+                    .join(" › ");
+                ui.weak(order);
+            }
+
+            ui.add_space(4.0);
+            ui.strong("Columns grouped by");
+            egui::ComboBox::from_id_salt(("pivot_column_group", tab_id))
+                .selected_text(
+                    current
+                        .column_group
+                        .map_or("(none)", |c| column_name(headers, c)),
+                )
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut current.column_group, None, "(none)");
+                    for col in 0..headers.len() {
+                        ui.selectable_value(
+                            &mut current.column_group,
+                            Some(col),
+                            column_name(headers, col),
+                        );
+                    }
+                });
+
+            ui.add_space(4.0);
+            ui.strong("Values");
+            let mut remove: Option<usize> = None;
+            for (i, (col, aggregation)) in current.values.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_salt(("pivot_aggregation", tab_id, i))
+                        .selected_text(aggregation.label())
+                        .show_ui(ui, |ui| {
+                            for a in Aggregation::ALL {
+                                ui.selectable_value(aggregation, a, a.label());
+                            }
+                        });
+                    if *aggregation != Aggregation::Count {
+                        egui::ComboBox::from_id_salt(("pivot_value_column", tab_id, i))
+                            .selected_text(column_name(headers, *col))
+                            .show_ui(ui, |ui| {
+                                for c in 0..headers.len() {
+                                    ui.selectable_value(col, c, column_name(headers, c));
+                                }
+                            });
+                    }
+                    if ui.small_button("✕").clicked() {
+                        remove = Some(i);
+                    }
+                });
+            }
+            if let Some(i) = remove {
+                current.values.remove(i);
+            }
+            if ui.button("+ Add value").clicked() {
+                current.values.push((0, Aggregation::Count));
+            }
+            if current.values.is_empty() {
+                ui.weak("Without values the pivot counts rows.");
+            }
+
+            ui.separator();
+            let can_create = !current.row_groups.is_empty() || current.column_group.is_some();
+            if ui
+                .add_enabled(can_create, egui::Button::new("Create pivot tab"))
+                .clicked()
+            {
+                create = true;
+            }
+        });
+
+    if create {
+        if let Err(e) = sender.send(UiMessage::OpenPivot(
+            filename.clone(),
+            tab_id,
+            current.clone(),
+        )) {
+            eprintln!("Failed to send OpenPivot: {:?}", e);
+        }
+        *spec = None;
+    } else if !open {
+        *spec = None;
+    }
+}
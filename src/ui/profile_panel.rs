@@ -0,0 +1,154 @@
+use eframe::egui;
+use egui::{Color32, Id, Sense};
+use jonathan_core::profile::{Bin, ColumnProfile};
+use jonathan_core::stats::format_number;
+use std::collections::BTreeSet;
+use std::sync::mpsc::Sender;
+
+use crate::types::{Filename, TabId, UiMessage};
+
+/// Side panel with the profile of one column. Returns `false` once the user closes it.
+pub fn show(
+    ui: &mut egui::Ui,
+    tab_id: TabId,
+    filename: &Filename,
+    column_name: &str,
+    profile: Option<&ColumnProfile>,
+    sender: &Sender<UiMessage>,
+) -> bool {
+    let mut open = true;
+
+    egui::SidePanel::right(Id::new(("profile_panel", tab_id)))
+        .resizable(true)
+        .default_width(240.0)
+        .show_inside(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.heading(column_name);
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui
+                        .small_button("✕")
+                        .on_hover_text("Close profile")
+                        .clicked()
+                    {
+                        open = false;
+                    }
+                });
+            });
+
+            let Some(profile) = profile else {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Profiling…");
+                });
+                return;
+            };
+
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                summary_ui(ui, profile);
+
+                ui.separator();
+                ui.label("Most frequent (click to filter)");
+                for (value, count) in &profile.top_values {
+                    let text = if value.is_empty() { "(empty)" } else { value };
+                    let clicked = ui
+                        .horizontal(|ui| {
+                            let clicked = ui.link(text).clicked();
+                            ui.weak(count.to_string());
+                            clicked
+                        })
+                        .inner;
+                    if clicked
+                        && let Err(e) = sender.send(UiMessage::FacetSheet(
+                            filename.clone(),
+                            tab_id,
+                            profile.column,
+                            BTreeSet::from([value.clone()]),
+                        ))
+                    {
+                        eprintln!("Failed to send FacetSheet: {:?}", e);
+                    }
+                }
+
+                if !profile.histogram.is_empty() {
+                    ui.separator();
+                    ui.label("Distribution");
+                    histogram_ui(ui, &profile.histogram);
+                }
+
+                if !profile.lengths.is_empty() {
+                    ui.separator();
+                    ui.label("Value length");
+                    histogram_ui(ui, &profile.lengths);
+                }
+            });
+        });
+
+    open
+}
+
+fn summary_ui(ui: &mut egui::Ui, profile: &ColumnProfile) {
+    egui::Grid::new(("profile_summary", profile.column))
+        .num_columns(2)
+        .show(ui, |ui| {
+            let row = |ui: &mut egui::Ui, name: &str, value: String| {
+                ui.label(name);
+                ui.label(value);
+                ui.end_row();
+            };
+            row(ui, "Type", profile.kind.label().to_string());
+            row(ui, "Rows", profile.count.to_string());
+            row(ui, "Null/empty", profile.nulls.to_string());
+            row(ui, "Distinct", profile.distinct.to_string());
+            if let Some(min) = &profile.min {
+                row(ui, "Min", min.clone());
+            }
+            if let Some(max) = &profile.max {
+                row(ui, "Max", max.clone());
+            }
+            if let Some(numeric) = &profile.numeric {
+                row(ui, "Mean", format_number(numeric.mean));
+                row(ui, "Median", format_number(numeric.median));
+                row(ui, "Std dev", format_number(numeric.stddev));
+            }
+        });
+}
+
+pub fn histogram_ui(ui: &mut egui::Ui, bins: &[Bin]) {
+    let height = 60.0;
+    let (rect, response) =
+        ui.allocate_exact_size(egui::vec2(ui.available_width(), height), Sense::hover());
+    let max_count = bins.iter().map(|b| b.count).max().unwrap_or(0).max(1);
+    let bar_width = rect.width() / bins.len() as f32;
+    let painter = ui.painter_at(rect);
+    let hover = response.hover_pos();
+
+    for (i, bin) in bins.iter().enumerate() {
+        let bar_height = height * bin.count as f32 / max_count as f32;
+        let bar = egui::Rect::from_min_max(
+            egui::pos2(
+                rect.left() + i as f32 * bar_width + 1.0,
+                rect.bottom() - bar_height,
+            ),
+            egui::pos2(
+                rect.left() + (i + 1) as f32 * bar_width - 1.0,
+                rect.bottom(),
+            ),
+        );
+        let hovered = hover.is_some_and(|p| p.x >= bar.left() && p.x < bar.right() + 2.0);
+        let color = if hovered {
+            Color32::from_rgb(100, 100, 255)
+        } else {
+            Color32::from_rgb(0, 160, 70)
+        };
+        painter.rect_filled(bar, 0.0, color);
+
+        if hovered {
+            response.clone().on_hover_text(format!(
+                "{} – {}: {}",
+                format_number(bin.start),
+                format_number(bin.end),
+                bin.count
+            ));
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use eframe::egui;
+
+use crate::recent::{RecentFiles, format_size};
+use crate::types::Filename;
+
+/// Recent files with their last-opened time and size, plus pin/remove buttons.
+/// Returns the path of the entry the user picked, if any. Missing files can't be
+/// picked, only removed.
+pub fn list_ui(ui: &mut egui::Ui, recent: &mut RecentFiles) -> Option<Filename> {
+    if recent.entries.is_empty() {
+        ui.weak("No recent files");
+        return None;
+    }
+
+    let mut picked = None;
+    let mut pin: Option<(Filename, bool)> = None;
+    let mut remove: Option<Filename> = None;
+
+    for entry in &recent.entries {
+        let size = entry.size();
+        ui.horizontal(|ui| {
+            let pin_button = ui
+                .selectable_label(entry.pinned, "📌")
+                .on_hover_text(if entry.pinned { "Unpin" } else { "Pin" });
+            if pin_button.clicked() {
+                pin = Some((entry.path.clone(), !entry.pinned));
+            }
+
+            let open = ui
+                .add_enabled(
+                    size.is_some(),
+                    egui::Button::new(entry.file_name()).frame(false),
+                )
+                .on_hover_text(&entry.path)
+                .on_disabled_hover_text(format!("{} no longer exists", entry.path));
+            if open.clicked() {
+                picked = Some(entry.path.clone());
+            }
+
+            ui.weak(match size {
+                Some(size) => format!("{} · {}", entry.opened_at_label(), format_size(size)),
+                None => format!("{} · missing", entry.opened_at_label()),
+            });
+
+            if ui
+                .small_button("✕")
+                .on_hover_text("Remove from recent files")
+                .clicked()
+            {
+                remove = Some(entry.path.clone());
+            }
+        });
+    }
+
+    if let Some((path, pinned)) = pin {
+        recent.set_pinned(&path, pinned);
+    }
+    if let Some(path) = remove {
+        recent.remove(&path);
+    }
+
+    picked
+}
@@ -0,0 +1,134 @@
+use eframe::egui;
+use egui::Id;
+use jonathan_core::readers::sheet_file;
+use std::sync::mpsc::Sender;
+
+use crate::types::{TabId, UiMessage};
+
+/// Worksheets of a workbook, or tables and views of a database, being opened and
+/// which of them to open.
+pub struct SheetPicker {
+    pub path: String,
+    /// Tab the workbook was opened from, which shows the first chosen sheet
+    pub tab_id: Option<TabId>,
+    sheets: Vec<(String, bool)>,
+    separate_tabs: bool,
+    /// `SELECT` to run on a database, `None` for workbooks
+    query: Option<String>,
+}
+
+impl SheetPicker {
+    pub fn new(
+        path: String,
+        sheets: Vec<String>,
+        tab_id: Option<TabId>,
+        database: bool,
+    ) -> SheetPicker {
+        SheetPicker {
+            path,
+            tab_id,
+            sheets: sheets
+                .into_iter()
+                .enumerate()
+                .map(|(i, sheet)| (sheet, i == 0))
+                .collect(),
+            separate_tabs: true,
+            query: database.then(String::new),
+        }
+    }
+}
+
+/// Modal for choosing the worksheets of a workbook, or the tables of a database, to
+/// open. Closes itself (sets `picker` to `None`) once sheets are opened or the
+/// modal is dismissed.
+pub fn show(ctx: &egui::Context, picker: &mut Option<SheetPicker>, sender: &Sender<UiMessage>) {
+    let Some(current) = picker else {
+        return;
+    };
+    let short_name = current
+        .path
+        .split('/')
+        .next_back()
+        .unwrap_or(&current.path)
+        .to_string();
+    let mut open = false;
+    let mut run = false;
+    let mut cancel = false;
+
+    let modal = egui::Modal::new(Id::new("sheet_picker")).show(ctx, |ui| {
+        ui.set_max_width(360.0);
+
+        ui.heading(format!("Open {short_name}"));
+        ui.label(if current.query.is_some() {
+            "Choose the tables and views to open."
+        } else {
+            "Choose the worksheets to open."
+        });
+        ui.add_space(4.0);
+        egui::ScrollArea::vertical()
+            .max_height(240.0)
+            .show(ui, |ui| {
+                for (sheet, chosen) in current.sheets.iter_mut() {
+                    ui.checkbox(chosen, sheet.as_str());
+                }
+            });
+
+        ui.add_space(4.0);
+        ui.checkbox(&mut current.separate_tabs, "Open each sheet in its own tab")
+            .on_hover_text("Otherwise the other sheets are only added to the file list");
+
+        if let Some(query) = current.query.as_mut() {
+            ui.separator();
+            ui.label("Or run a query:");
+            ui.add(
+                egui::TextEdit::multiline(query)
+                    .code_editor()
+                    .desired_rows(3)
+                    .hint_text("SELECT …"),
+            );
+            if ui
+                .add_enabled(!query.trim().is_empty(), egui::Button::new("Run"))
+                .on_hover_text("Open the rows of this query in a tab")
+                .clicked()
+            {
+                run = true;
+            }
+        }
+
+        ui.add_space(8.0);
+        ui.horizontal(|ui| {
+            let any = current.sheets.iter().any(|(_, chosen)| *chosen);
+            if ui.add_enabled(any, egui::Button::new("Open")).clicked() {
+                open = true;
+            }
+            if ui.button("Cancel").clicked() {
+                cancel = true;
+            }
+        });
+    });
+
+    if run {
+        let query = current.query.clone().unwrap_or_default();
+        let message = UiMessage::OpenQuery(current.path.clone(), query, current.tab_id);
+        if let Err(e) = sender.send(message) {
+            eprintln!("Failed to send OpenQuery: {:?}", e);
+        }
+        *picker = None;
+    } else if open {
+        let chosen = current.sheets.iter().filter(|(_, chosen)| *chosen);
+        for (i, (sheet, _)) in chosen.enumerate() {
+            let file = sheet_file(&current.path, sheet);
+            let message = match (i, current.tab_id) {
+                (0, tab_id) => UiMessage::OpenFile(file, tab_id),
+                _ if current.separate_tabs => UiMessage::OpenInNewTab(file),
+                _ => UiMessage::OpenFile(file, None),
+            };
+            if let Err(e) = sender.send(message) {
+                eprintln!("Failed to send sheet to open: {:?}", e);
+            }
+        }
+        *picker = None;
+    } else if cancel || modal.should_close() {
+        *picker = None;
+    }
+}
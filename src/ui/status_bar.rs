@@ -0,0 +1,55 @@
+use eframe::egui;
+use jonathan_core::stats::{SelectionStats, format_number};
+
+/// Everything the status bar shows for the focused tab.
+pub struct StatusInfo<'a> {
+    pub total_rows: usize,
+    pub view_rows: usize,
+    pub filter_active: bool,
+    /// 0-based row in the current view and the column name under the cursor
+    pub cursor: Option<(u64, &'a str)>,
+    pub stats: Option<&'a SelectionStats>,
+    pub stats_pending: bool,
+}
+
+pub fn show(ctx: &egui::Context, info: &StatusInfo) {
+    egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            ui.label(format!("Rows: {}", info.total_rows));
+            if info.filter_active {
+                ui.separator();
+                ui.label(format!("Filtered: {}", info.view_rows));
+            }
+            if let Some((row, column)) = info.cursor {
+                ui.separator();
+                ui.label(format!("R{} · {}", row + 1, column));
+            }
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if info.stats_pending {
+                    ui.spinner();
+                    ui.label("Computing…");
+                } else if let Some(stats) = info.stats {
+                    stats_ui(ui, stats);
+                }
+            });
+        });
+    });
+}
+
+fn stats_ui(ui: &mut egui::Ui, stats: &SelectionStats) {
+    // right-to-left layout: last item shown first
+    ui.label(format!("Distinct: {}", stats.distinct));
+    if stats.numeric > 0 {
+        if let (Some(min), Some(max)) = (stats.min, stats.max) {
+            ui.label(format!("Max: {}", format_number(max)));
+            ui.label(format!("Min: {}", format_number(min)));
+        }
+        if let Some(avg) = stats.average() {
+            ui.label(format!("Avg: {}", format_number(avg)));
+        }
+        ui.label(format!("Sum: {}", format_number(stats.sum)));
+    }
+    ui.label(format!("Non-empty: {}", stats.non_empty));
+    ui.label(format!("Count: {}", stats.count));
+}
@@ -0,0 +1,83 @@
+use jonathan_core::readers::split_sheet;
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Sender, TryRecvError};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::types::{Filename, UiMessage};
+
+/// How often watched files are looked at.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+enum WatchCommand {
+    Watch(Filename),
+    Unwatch(Filename),
+}
+
+/// Background thread that polls the size and modification time of open files
+/// and sends `UiMessage::FileChanged` when they move.
+pub struct FileWatcher {
+    commands: Sender<WatchCommand>,
+}
+
+impl FileWatcher {
+    pub fn spawn(ctx: egui::Context, chan: Sender<UiMessage>) -> FileWatcher {
+        let (commands, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let mut files: HashMap<Filename, Option<(u64, SystemTime)>> = HashMap::new();
+            loop {
+                thread::sleep(POLL_INTERVAL);
+
+                loop {
+                    match receiver.try_recv() {
+                        Ok(WatchCommand::Watch(path)) => {
+                            let seen = file_state(&path);
+                            files.insert(path, seen);
+                        }
+                        Ok(WatchCommand::Unwatch(path)) => {
+                            files.remove(&path);
+                        }
+                        Err(TryRecvError::Empty) => break,
+                        Err(TryRecvError::Disconnected) => return,
+                    }
+                }
+
+                for (path, seen) in files.iter_mut() {
+                    let now = file_state(path);
+                    if now == *seen {
+                        continue;
+                    }
+                    *seen = now;
+                    // A file that disappeared is reported once it's back.
+                    if now.is_none() {
+                        continue;
+                    }
+                    if let Err(e) = chan.send(UiMessage::FileChanged(path.clone())) {
+                        eprintln!("Watcher: Failed to send change to UI thread: {:?}", e);
+                    }
+                    ctx.request_repaint();
+                }
+            }
+        });
+
+        FileWatcher { commands }
+    }
+
+    pub fn watch(&self, path: &str) {
+        if let Err(e) = self.commands.send(WatchCommand::Watch(path.to_string())) {
+            eprintln!("Failed to watch {}: {:?}", path, e);
+        }
+    }
+
+    pub fn unwatch(&self, path: &str) {
+        if let Err(e) = self.commands.send(WatchCommand::Unwatch(path.to_string())) {
+            eprintln!("Failed to unwatch {}: {:?}", path, e);
+        }
+    }
+}
+
+fn file_state(path: &str) -> Option<(u64, SystemTime)> {
+    let metadata = std::fs::metadata(split_sheet(path).0).ok()?;
+    Some((metadata.len(), metadata.modified().ok()?))
+}